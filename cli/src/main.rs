@@ -1,6 +1,7 @@
 use atomic_lib::{agents::generate_public_key, mapping::Mapping};
 use atomic_lib::{agents::Agent, config::Config};
 use atomic_lib::{errors::AtomicResult, Storelike};
+use atomic_lib::{hierarchy::Right, tokens::CapabilityToken};
 use clap::{crate_version, Arg, ArgMatches, Command};
 use colored::*;
 use dirs::home_dir;
@@ -192,6 +193,47 @@ fn main() -> AtomicResult<()> {
                     .required(true)
                 )
         )
+        .subcommand(
+            Command::new("token")
+                .about("Creates a capability token, which grants some of your rights to scripts and other apps.")
+                .after_help("\
+                    Use the token in an `Authorization: Bearer {token}` header. \
+                    Revoke it by sending a signed `POST` request to `{server}/revoke-token`, with the token as the body. \
+                    ")
+                .arg(Arg::new("scope")
+                    .help("Subject URL or bookmark of the Resource that the token grants access to, including its children")
+                    .required(true)
+                )
+                .arg(Arg::new("rights")
+                    .long("rights")
+                    .possible_values(&["read", "write", "append"])
+                    .multiple_values(true)
+                    .default_value("read")
+                    .help("The rights that the token grants")
+                )
+                .arg(Arg::new("expires")
+                    .long("expires")
+                    .default_value("24")
+                    .help("Amount of hours until the token expires")
+                    .takes_value(true)
+                )
+        )
+        .subcommand(
+            Command::new("rotate-key")
+                .about("Replaces the key of your Agent with a new one, and stores the new private key in your config file.")
+                .after_help("\
+                    The old key can no longer be used to sign new Commits and requests. \
+                    If your private key has leaked, also revoke the old key using `revoke-key`. \
+                    ")
+        )
+        .subcommand(
+            Command::new("revoke-key")
+                .about("Revokes a key of your Agent, e.g. because its private key has leaked. Everything signed with this key will be rejected.")
+                .arg(Arg::new("public-key")
+                    .help("The public key that should be revoked")
+                    .required(true)
+                )
+        )
         .subcommand(Command::new("list").about("List all bookmarks"))
         .subcommand(Command::new("validate").about("Validates the store").hide(true))
         .get_matches();
@@ -261,9 +303,18 @@ fn exec_command(context: &mut Context) -> AtomicResult<()> {
         Some("remove") => {
             commit::remove(context)?;
         }
+        Some("revoke-key") => {
+            revoke_key(context)?;
+        }
+        Some("rotate-key") => {
+            rotate_key(context)?;
+        }
         Some("set") => {
             commit::set(context)?;
         }
+        Some("token") => {
+            token(context)?;
+        }
         Some("tpf") => {
             tpf(context)?;
         }
@@ -291,6 +342,72 @@ fn list(context: &mut Context) {
     println!("{}", string)
 }
 
+/// Creates a capability token for the configured Agent
+fn token(context: &Context) -> AtomicResult<()> {
+    let subcommand_matches = context.matches.subcommand_matches("token").unwrap();
+    let scope = context
+        .mapping
+        .lock()
+        .unwrap()
+        .try_mapping_or_url(subcommand_matches.value_of("scope").unwrap())
+        .ok_or("Scope must be a URL or a bookmark")?;
+    let rights: Vec<Right> = subcommand_matches
+        .values_of("rights")
+        .unwrap()
+        .map(|r| match r {
+            "write" => Right::Write,
+            "append" => Right::Append,
+            _ => Right::Read,
+        })
+        .collect();
+    let hours: i64 = subcommand_matches
+        .value_of("expires")
+        .unwrap()
+        .parse()
+        .map_err(|_e| "Expires must be a number of hours")?;
+    context.get_write_context();
+    let agent = context.store.get_default_agent()?;
+    let expires_at = atomic_lib::utils::now() + hours * 60 * 60 * 1000;
+    let token = CapabilityToken::new(&agent.subject, &scope, &rights, expires_at);
+    println!("{}", token.sign(&agent)?);
+    Ok(())
+}
+
+/// Replaces the key of the configured Agent, and writes the new private key to the config file
+fn rotate_key(context: &Context) -> AtomicResult<()> {
+    let mut config = context.get_write_context();
+    let agent = context.store.get_default_agent()?;
+    let (rotated, commit) = agent.rotate_key(&context.store)?;
+    atomic_lib::client::post_commit(&commit, &context.store)?;
+    let private_key = rotated.private_key.ok_or("No private key generated")?;
+    config.private_key = private_key.clone();
+    let config_path = atomic_lib::config::default_config_file_path()?;
+    // The old key no longer works, so the new one should never get lost
+    atomic_lib::config::write_config(&config_path, config).map_err(|e| {
+        format!(
+            "Key was rotated, but the config file could not be written. Store this private key: {} . {}",
+            private_key, e
+        )
+    })?;
+    println!(
+        "Rotated the key of {}. New public key: {}. The private key is stored in {:?}",
+        agent.subject, rotated.public_key, config_path
+    );
+    Ok(())
+}
+
+/// Revokes a key of the configured Agent
+fn revoke_key(context: &Context) -> AtomicResult<()> {
+    let subcommand_matches = context.matches.subcommand_matches("revoke-key").unwrap();
+    let public_key = subcommand_matches.value_of("public-key").unwrap();
+    context.get_write_context();
+    let agent = context.store.get_default_agent()?;
+    let commit = agent.revoke_key(public_key, &context.store)?;
+    atomic_lib::client::post_commit(&commit, &context.store)?;
+    println!("Revoked key {} of {}", public_key, agent.subject);
+    Ok(())
+}
+
 /// Triple Pattern Fragment Query
 fn tpf(context: &Context) -> AtomicResult<()> {
     let subcommand_matches = context.matches.subcommand_matches("tpf").unwrap();
@@ -280,6 +280,29 @@ fn prompt_field(
                 None => (return Ok(None)),
             }
         }
+        DataType::Uri
+        | DataType::Json
+        | DataType::Decimal
+        | DataType::Duration
+        | DataType::GeoPoint
+        | DataType::LangString => {
+            let msg = format!("{:?}{}", property.data_type, msg_appendix).to_lowercase();
+            let string: Option<String> = prompt_opt(&msg)?;
+            match string {
+                Some(val) => {
+                    if let Err(e) = Value::new(&val, &property.data_type) {
+                        println!("{}", e);
+                        return Ok(None);
+                    }
+                    input = Some(val);
+                }
+                None => (return Ok(None)),
+            }
+        }
+        DataType::NestedResource => {
+            println!("Nested resources can't be created in the CLI yet, skipping.");
+            return Ok(None);
+        }
         DataType::Boolean => {
             let msg = format!("boolean{}", msg_appendix);
             let number: Option<bool> = prompt_opt(&msg)?;
@@ -32,6 +32,18 @@ pub fn get_path(context: &mut Context) -> AtomicResult<()> {
                 let atoms: Vec<Atom> = vec![*atom];
                 serialize::atoms_to_ntriples(atoms, store)?
             }
+            Format::Turtle => {
+                let atoms: Vec<Atom> = vec![*atom];
+                serialize::atoms_to_turtle(atoms, store)?
+            }
+            Format::RdfXml => {
+                let atoms: Vec<Atom> = vec![*atom];
+                serialize::atoms_to_rdf_xml(atoms, store)?
+            }
+            Format::TriG => {
+                let resource = store.get_resource(&atom.subject)?;
+                serialize::resources_to_trig(&[resource], store)?
+            }
         },
     };
     println!("{}", out);
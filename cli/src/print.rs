@@ -9,8 +9,9 @@ use colored::*;
 use crate::Context;
 
 /// List of serialization options. Should match /path.rs/get
-pub const SERIALIZE_OPTIONS: [&str; 7] =
-    ["pretty", "json", "jsonld", "jsonad", "nt", "turtle", "n3"];
+pub const SERIALIZE_OPTIONS: [&str; 10] = [
+    "pretty", "json", "jsonld", "jsonad", "nt", "turtle", "ttl", "n3", "rdfxml", "trig",
+];
 
 /// Returns preferred serialization format. Defaults to pretty.
 pub fn get_serialization(argmatches: &ArgMatches) -> AtomicResult<Format> {
@@ -21,8 +22,11 @@ pub fn get_serialization(argmatches: &ArgMatches) -> AtomicResult<Format> {
             "jsonld" => (Format::JsonLd),
             "jsonad" => (Format::JsonAd),
             "nt" => (Format::NTriples),
-            "turtle" => (Format::NTriples),
+            "turtle" => (Format::Turtle),
+            "ttl" => (Format::Turtle),
             "n3" => (Format::NTriples),
+            "rdfxml" => (Format::RdfXml),
+            "trig" => (Format::TriG),
             format => {
                 return Err(
                     format!("As {} not supported. Try {:?}", format, SERIALIZE_OPTIONS).into(),
@@ -66,6 +70,9 @@ pub fn print_resource(
         Format::JsonAd => resource.to_json_ad()?,
         Format::NTriples => serialize::atoms_to_ntriples(resource.to_atoms()?, &context.store)?,
         Format::Pretty => pretty_print_resource(resource, &context.store)?,
+        Format::Turtle => serialize::atoms_to_turtle(resource.to_atoms()?, &context.store)?,
+        Format::RdfXml => serialize::atoms_to_rdf_xml(resource.to_atoms()?, &context.store)?,
+        Format::TriG => serialize::resources_to_trig(&[resource.clone()], &context.store)?,
     };
     println!("{}", out);
     Ok(())
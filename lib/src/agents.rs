@@ -2,7 +2,14 @@
 //! Agents are actors (such as users) that can edit content.
 //! https://docs.atomicdata.dev/commits/concepts.html
 
-use crate::{errors::AtomicResult, urls, Resource, Storelike};
+use crate::{
+    commit::{Commit, CommitBuilder},
+    errors::AtomicResult,
+    resources::PropVals,
+    urls,
+    values::SubResource,
+    Resource, Storelike, Value,
+};
 
 #[derive(Clone, Debug)]
 pub struct Agent {
@@ -70,6 +77,243 @@ impl Agent {
             created_at: crate::utils::now(),
         })
     }
+
+    /// Generates a new Keypair for this Agent, and creates a Commit (signed with the current key) that adds it to the `agentKeys`.
+    /// The current key stops being valid when the Commit is created.
+    /// Returns the Agent with the new private key, and the Commit that still has to be applied.
+    pub fn rotate_key(&self, store: &impl Storelike) -> AtomicResult<(Agent, Commit)> {
+        let keypair = generate_keypair()?;
+        let resource = store.get_resource(&self.subject)?;
+        let now = crate::utils::now();
+        let mut keys = get_agent_keys(&resource)?;
+        for key in keys.iter_mut() {
+            if key.public_key == self.public_key && key.valid_until.is_none() {
+                key.valid_until = Some(now);
+            }
+        }
+        keys.push(AgentKey::new(&keypair.public, now));
+        let commit = self.keys_commit(&resource, &keys, store)?;
+        let rotated = Agent {
+            private_key: Some(keypair.private),
+            public_key: keypair.public,
+            ..self.clone()
+        };
+        Ok((rotated, commit))
+    }
+
+    /// Creates a Commit that revokes one of the keys of this Agent, e.g. because its private key has leaked.
+    /// Fails if it is the last valid key, since that would lock the Agent out.
+    pub fn revoke_key(&self, public_key: &str, store: &impl Storelike) -> AtomicResult<Commit> {
+        let resource = store.get_resource(&self.subject)?;
+        let now = crate::utils::now();
+        let mut keys = get_agent_keys(&resource)?;
+        let key = keys
+            .iter_mut()
+            .find(|k| k.public_key == public_key)
+            .ok_or_else(|| format!("{} is not a key of Agent {}", public_key, self.subject))?;
+        key.revoked_at = Some(now);
+        if !keys.iter().any(|k| k.is_valid_at(now, true)) {
+            return Err(format!(
+                "Can't revoke the last valid key of Agent {}. Rotate the key first.",
+                self.subject
+            )
+            .into());
+        }
+        self.keys_commit(&resource, &keys, store)
+    }
+
+    fn keys_commit(
+        &self,
+        resource: &Resource,
+        keys: &[AgentKey],
+        store: &impl Storelike,
+    ) -> AtomicResult<Commit> {
+        let mut commit = CommitBuilder::new(self.subject.clone());
+        let nested = keys
+            .iter()
+            .map(|k| SubResource::Nested(k.to_propvals()))
+            .collect();
+        commit.set(urls::AGENT_KEYS.into(), Value::ResourceArray(nested));
+        commit.sign(self, store, resource)
+    }
+}
+
+/// A public key of an Agent, and the period in which it can be used for signing Commits and requests.
+/// Note that a rotated key can still be used to sign messages with a forged (older) timestamp, so revoke keys that have leaked.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AgentKey {
+    /// Base64 encoded Ed25519 public key
+    pub public_key: String,
+    /// Unix timestamp (ms) from which the key is valid
+    pub valid_from: i64,
+    /// Set when the key is rotated
+    pub valid_until: Option<i64>,
+    /// Set when the key is revoked
+    pub revoked_at: Option<i64>,
+}
+
+impl AgentKey {
+    pub fn new(public_key: &str, valid_from: i64) -> AgentKey {
+        AgentKey {
+            public_key: public_key.into(),
+            valid_from,
+            valid_until: None,
+            revoked_at: None,
+        }
+    }
+
+    fn from_propvals(propvals: &PropVals) -> AtomicResult<AgentKey> {
+        let public_key = propvals
+            .get(urls::PUBLIC_KEY)
+            .ok_or("Agent key has no publicKey")?
+            .to_string();
+        verify_public_key(&public_key)?;
+        let timestamp = |prop: &str| -> AtomicResult<Option<i64>> {
+            propvals.get(prop).map(|v| v.to_int()).transpose()
+        };
+        Ok(AgentKey {
+            public_key,
+            valid_from: timestamp(urls::VALID_FROM)?.unwrap_or(0),
+            valid_until: timestamp(urls::VALID_UNTIL)?,
+            revoked_at: timestamp(urls::REVOKED_AT)?,
+        })
+    }
+
+    fn to_propvals(&self) -> PropVals {
+        let mut propvals = PropVals::new();
+        propvals.insert(urls::IS_A.into(), vec![urls::AGENT_KEY].into());
+        propvals.insert(
+            urls::PUBLIC_KEY.into(),
+            Value::String(self.public_key.clone()),
+        );
+        propvals.insert(urls::VALID_FROM.into(), Value::Timestamp(self.valid_from));
+        if let Some(until) = self.valid_until {
+            propvals.insert(urls::VALID_UNTIL.into(), Value::Timestamp(until));
+        }
+        if let Some(revoked) = self.revoked_at {
+            propvals.insert(urls::REVOKED_AT.into(), Value::Timestamp(revoked));
+        }
+        propvals
+    }
+
+    /// Whether a signature created at `timestamp` can be verified with this key.
+    /// Revoked keys are valid up until their revocation, unless `reject_revoked` is set.
+    /// Use `reject_revoked` for new requests and Commits, since anyone with the leaked private key can forge timestamps.
+    pub fn is_valid_at(&self, timestamp: i64, reject_revoked: bool) -> bool {
+        if let Some(revoked) = self.revoked_at {
+            if reject_revoked || timestamp >= revoked {
+                return false;
+            }
+        }
+        timestamp >= self.valid_from && self.valid_until.is_none_or(|until| timestamp < until)
+    }
+
+    /// Checks a base64 encoded Ed25519 signature of the message.
+    pub fn verify(&self, message: &str, signature: &str) -> AtomicResult<()> {
+        let public_key = base64::decode(&self.public_key)?;
+        let signature_bytes = base64::decode(signature)?;
+        ring::signature::UnparsedPublicKey::new(&ring::signature::ED25519, public_key)
+            .verify(message.as_bytes(), &signature_bytes)
+            .map_err(|_| "Signature does not match the public key".into())
+    }
+}
+
+/// Returns all keys of an Agent Resource.
+/// Agents that never rotated their key only have their `publicKey`, which is valid forever.
+pub fn get_agent_keys(agent: &Resource) -> AtomicResult<Vec<AgentKey>> {
+    match agent.get(urls::AGENT_KEYS) {
+        Ok(Value::ResourceArray(items)) => items
+            .iter()
+            .map(|item| match item {
+                SubResource::Nested(propvals) => AgentKey::from_propvals(propvals),
+                SubResource::Resource(r) => AgentKey::from_propvals(r.get_propvals()),
+                SubResource::Subject(s) => {
+                    Err(format!("Agent keys must be Nested Resources, not a link to {}", s).into())
+                }
+            })
+            .collect(),
+        Ok(other) => Err(format!("agentKeys must be a ResourceArray, got {}", other).into()),
+        Err(_) => Ok(vec![AgentKey::new(
+            &agent.get(urls::PUBLIC_KEY)?.to_string(),
+            0,
+        )]),
+    }
+}
+
+/// Checks whether the signature has been made by any of the keys of the Agent that were valid at `timestamp`.
+pub fn verify_agent_signature(
+    agent: &Resource,
+    message: &str,
+    signature: &str,
+    timestamp: i64,
+    reject_revoked: bool,
+) -> AtomicResult<()> {
+    let keys = get_agent_keys(agent)?;
+    let mut valid_keys = keys
+        .iter()
+        .filter(|k| k.is_valid_at(timestamp, reject_revoked))
+        .peekable();
+    if valid_keys.peek().is_none() {
+        return Err(format!(
+            "Agent {} has no valid keys at timestamp {}. Its keys might have been rotated or revoked.",
+            agent.get_subject(),
+            timestamp
+        )
+        .into());
+    }
+    if valid_keys.any(|k| k.verify(message, signature).is_ok()) {
+        return Ok(());
+    }
+    Err(format!(
+        "Signature does not match any of the valid keys of Agent {}",
+        agent.get_subject()
+    )
+    .into())
+}
+
+/// Makes sure that only the Agent itself can change its keys, and that its `publicKey` (which is part of its subject) stays the same.
+pub fn check_key_changes(
+    commit: &Commit,
+    resource_old: &Resource,
+    resource_new: &Resource,
+) -> AtomicResult<()> {
+    let touches = |prop: &str| {
+        commit.set.as_ref().is_some_and(|s| s.contains_key(prop))
+            || commit.push.as_ref().is_some_and(|p| p.contains_key(prop))
+            || commit
+                .remove
+                .as_ref()
+                .is_some_and(|r| r.iter().any(|p| p == prop))
+    };
+    if !touches(urls::AGENT_KEYS) && !touches(urls::PUBLIC_KEY) {
+        return Ok(());
+    }
+    let old_key = match resource_old.get(urls::PUBLIC_KEY) {
+        Ok(key) => key.to_string(),
+        // Not an Agent (yet)
+        Err(_) => return Ok(()),
+    };
+    if resource_new
+        .get(urls::PUBLIC_KEY)
+        .map(|k| k.to_string())
+        .ok()
+        != Some(old_key)
+    {
+        return Err("The publicKey of an Agent can't be changed. Rotate the key instead.".into());
+    }
+    if &commit.signer != resource_old.get_subject() {
+        return Err(format!(
+            "Only Agent {} itself can change its keys, not {}",
+            resource_old.get_subject(),
+            commit.signer
+        )
+        .into());
+    }
+    let keys = get_agent_keys(resource_new)?;
+    if !keys.iter().any(|k| k.is_valid_at(commit.created_at, true)) {
+        return Err("An Agent needs at least one valid key.".into());
+    }
+    Ok(())
 }
 
 /// keypair, serialized using base64
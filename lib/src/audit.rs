@@ -0,0 +1,116 @@
+//! The audit log records authorization decisions and sensitive operations, such as denied rights checks, uploads and destroyed Resources.
+//! Entries are append-only. The [crate::Db] stores them in a separate tree, other Stores ignore them.
+//! Only denied rights checks are recorded, so reading Resources is not slowed down by writing to the log.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{resources::PropVals, urls, Value};
+
+/// What happened in an [AuditEntry].
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum AuditAction {
+    /// A [crate::hierarchy::check_rights] call failed.
+    Denied,
+    /// An Invite was accepted, giving an Agent rights.
+    AcceptInvite,
+    /// A new Agent was created.
+    CreateAgent,
+    /// A File was uploaded.
+    Upload,
+    /// A File was downloaded.
+    Download,
+    /// A Resource was destroyed using a Commit.
+    Destroy,
+    /// A Resource was created by importing a CSV row.
+    Import,
+}
+
+impl std::fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            AuditAction::Denied => "denied",
+            AuditAction::AcceptInvite => "acceptInvite",
+            AuditAction::CreateAgent => "createAgent",
+            AuditAction::Upload => "upload",
+            AuditAction::Download => "download",
+            AuditAction::Destroy => "destroy",
+            AuditAction::Import => "import",
+        };
+        f.write_str(str)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct AuditEntry {
+    /// Unix timestamp (ms) of when the action happened.
+    pub timestamp: i64,
+    /// Subject of the Agent that performed the action, or the Public Agent.
+    pub agent: String,
+    /// The Resource that the action was performed on.
+    pub subject: String,
+    pub action: AuditAction,
+    /// Whether the action was allowed.
+    pub allowed: bool,
+    /// Explanation, e.g. the reason why a rights check failed.
+    pub message: Option<String>,
+}
+
+impl AuditEntry {
+    /// Creates an entry with the current time.
+    /// Capability tokens are secrets, so if `agent` is a token, its issuer is recorded instead.
+    /// Unreadable tokens grant nothing, so these are recorded as the Public Agent.
+    pub fn new(
+        action: AuditAction,
+        agent: &str,
+        subject: &str,
+        allowed: bool,
+        message: Option<String>,
+    ) -> AuditEntry {
+        let agent = if crate::tokens::is_token(agent) {
+            crate::tokens::token_issuer(agent).unwrap_or_else(|| urls::PUBLIC_AGENT.into())
+        } else {
+            agent.into()
+        };
+        AuditEntry {
+            timestamp: crate::utils::now(),
+            agent,
+            subject: subject.into(),
+            action,
+            allowed,
+            message,
+        }
+    }
+
+    /// Converts the entry to PropVals, which can be used in a Nested Resource.
+    pub fn to_propvals(&self) -> PropVals {
+        let mut propvals = PropVals::new();
+        propvals.insert(urls::CREATED_AT.into(), Value::Timestamp(self.timestamp));
+        propvals.insert(
+            urls::AUDIT_AGENT.into(),
+            Value::AtomicUrl(self.agent.clone()),
+        );
+        propvals.insert(
+            urls::AUDIT_SUBJECT.into(),
+            Value::AtomicUrl(self.subject.clone()),
+        );
+        propvals.insert(
+            urls::AUDIT_ACTION.into(),
+            Value::String(self.action.to_string()),
+        );
+        propvals.insert(urls::AUDIT_ALLOWED.into(), Value::Boolean(self.allowed));
+        if let Some(message) = &self.message {
+            propvals.insert(urls::DESCRIPTION.into(), Value::Markdown(message.clone()));
+        }
+        propvals
+    }
+}
+
+/// Serializes audit entries to a JSON-AD array of anonymous Resources.
+pub fn entries_to_json_ad(entries: &[AuditEntry]) -> crate::errors::AtomicResult<String> {
+    let items = entries
+        .iter()
+        .map(|entry| crate::serialize::propvals_to_json_ad_map(&entry.to_propvals(), None))
+        .collect::<crate::errors::AtomicResult<Vec<serde_json::Value>>>()?;
+    Ok(serde_json::to_string_pretty(&items)?)
+}
@@ -50,17 +50,23 @@ pub fn get_agent_from_headers_and_check(
             .map_err(|e| format!("Error checking authentication headers. {}", e))?;
         // check if the timestamp is valid
         check_timestamp(auth_vals.timestamp)?;
-        // check if the public key belongs to the agent
+        // check if the public key belongs to the agent, and is currently valid
         let agent = store.get_resource(&auth_vals.agent_subject)?;
-        let found_public_key = agent.get(crate::urls::PUBLIC_KEY)?;
-        if found_public_key.to_string() != auth_vals.public_key {
-            return Err(
-                "The public key in the auth headers does not match the public key in the agent"
-                    .to_string()
-                    .into(),
-            );
-        } else {
-            for_agent = auth_vals.agent_subject;
+        let keys = crate::agents::get_agent_keys(&agent)?;
+        match keys.iter().find(|k| k.public_key == auth_vals.public_key) {
+            None => {
+                return Err(
+                    "The public key in the auth headers does not match any of the keys of the agent"
+                        .into(),
+                )
+            }
+            Some(key) if !key.is_valid_at(auth_vals.timestamp, true) => {
+                return Err(
+                    "The public key in the auth headers has been rotated or revoked. Use the current key of the agent."
+                        .into(),
+                )
+            }
+            Some(_) => for_agent = auth_vals.agent_subject,
         }
     };
     Ok(for_agent)
@@ -28,6 +28,9 @@ pub struct CommitOpts {
     pub validate_schema: bool,
     /// Checks the public key and the signature of the Commit.
     pub validate_signature: bool,
+    /// Validates the resulting Resource against all of its Classes: required properties, datatypes, `allowsOnly` and `classtype`.
+    /// Returns all violations in a single error. Stricter (and more expensive) than `validate_schema`.
+    pub validate_strict_schema: bool,
     /// Checks whether the Commit isn't too old, or has been created in the future.
     pub validate_timestamp: bool,
     /// Checks whether the creator of the Commit has the rights to edit the Resource.
@@ -98,23 +101,22 @@ impl Commit {
                 Some(sig) => sig,
                 None => return Err("No signature set".into()),
             };
-            let pubkey_b64 = store
-                .get_resource(&self.signer)?
-                .get(urls::PUBLIC_KEY)?
-                .to_string();
-            let agent_pubkey = base64::decode(pubkey_b64)?;
+            let signer = store.get_resource(&self.signer)?;
             let stringified_commit = self.serialize_deterministically_json_ad(store)?;
-            let peer_public_key =
-                ring::signature::UnparsedPublicKey::new(&ring::signature::ED25519, agent_pubkey);
-            let signature_bytes = base64::decode(signature.clone())?;
-            peer_public_key
-                .verify(stringified_commit.as_bytes(), &signature_bytes)
-                .map_err(|_e| {
-                    format!(
-                        "Incorrect signature for Commit. This could be due to an error during signing or serialization of the commit. Compare this to the serialized commit in the client: {}",
-                        stringified_commit,
-                    )
-                })?;
+            // Use the key that was valid when the Commit was created. Revoked keys are only accepted for older Commits, e.g. when importing.
+            crate::agents::verify_agent_signature(
+                &signer,
+                &stringified_commit,
+                signature,
+                self.created_at,
+                opts.validate_timestamp,
+            )
+            .map_err(|e| {
+                format!(
+                    "Incorrect signature for Commit. {} This could be due to an error during signing or serialization of the commit. Compare this to the serialized commit in the client: {}",
+                    e, stringified_commit,
+                )
+            })?;
         }
         // Check if the created_at lies in the past
         if opts.validate_timestamp {
@@ -175,11 +177,38 @@ impl Commit {
                 }
                 // This should use the _old_ resource, no the new one, as the new one might maliciously give itself write rights.
                 hierarchy::check_write(store, &resource_old, &self.signer)?;
+                crate::agents::check_key_changes(self, &resource_old, &resource_new)?;
             }
         };
         // Check if all required props are there
         if opts.validate_schema {
             resource_new.check_required_props(store)?;
+            // Only the changed Properties can introduce duplicates, unless the Resource moves to another Parent
+            let set = self.set.iter().flat_map(|set| set.keys());
+            let pushed = self.push.iter().flat_map(|push| push.keys());
+            let moved = set.clone().any(|prop| prop == urls::PARENT);
+            let violations = if moved {
+                crate::validate::check_unique_within_parent(&resource_new, store)?
+            } else {
+                crate::validate::check_unique_props_within_parent(
+                    &resource_new,
+                    set.chain(pushed),
+                    store,
+                )?
+            };
+            if let Some(violation) = violations.into_iter().next() {
+                return Err(violation.message.into());
+            }
+        }
+        // Destroyed Resources don't have to be valid anymore
+        if opts.validate_strict_schema && self.destroy != Some(true) {
+            let violations = crate::validate::validate_resource_schema(&resource_new, store)?;
+            if !violations.is_empty() {
+                return Err(crate::AtomicError::schema_violations(
+                    &self.subject,
+                    violations,
+                ));
+            }
         }
 
         // Set the `lastCommit` to the newly created Commit
@@ -199,6 +228,9 @@ impl Commit {
                 urls::INVITE => {
                     crate::plugins::invite::before_apply_commit(store, self, &resource_new)?
                 }
+                urls::PROPERTY if self.destroy != Some(true) => {
+                    crate::schema::check_property_constraints(&resource_new)?
+                }
                 _other => {}
             };
         }
@@ -209,7 +241,16 @@ impl Commit {
             if destroy {
                 // Note: the value index is updated before this action, in resource.apply_changes()
                 store.remove_resource(&self.subject)?;
+                store.add_audit_entry(&crate::audit::AuditEntry::new(
+                    crate::audit::AuditAction::Destroy,
+                    &self.signer,
+                    &self.subject,
+                    true,
+                    None,
+                ));
                 store.add_resource_opts(&commit_resource, false, opts.update_index, false)?;
+                #[cfg(feature = "db")]
+                crate::plugins::files::after_apply_commit(store, Some(&resource_old), None)?;
                 return Ok(CommitResponse {
                     resource_new: None,
                     resource_old: Some(resource_old),
@@ -240,6 +281,12 @@ impl Commit {
         // Commit has been checked and saved.
         // Here you can add side-effects, such as creating new Commits.
         #[cfg(feature = "db")]
+        crate::plugins::files::after_apply_commit(
+            store,
+            commit_response.resource_old.as_ref(),
+            Some(&resource_new),
+        )?;
+        #[cfg(feature = "db")]
         for class in _resource_new_classes {
             match class.subject.as_str() {
                 urls::MESSAGE => crate::plugins::chatroom::after_apply_commit_message(
@@ -363,6 +410,7 @@ impl Commit {
     pub fn apply_unsafe(&self, store: &impl Storelike) -> AtomicResult<CommitResponse> {
         let opts = CommitOpts {
             validate_schema: false,
+            validate_strict_schema: false,
             validate_signature: false,
             validate_timestamp: false,
             validate_rights: false,
@@ -671,6 +719,7 @@ mod test {
     lazy_static::lazy_static! {
         pub static ref OPTS: CommitOpts = CommitOpts {
             validate_schema: true,
+            validate_strict_schema: false,
             validate_signature: true,
             validate_timestamp: true,
             validate_previous_commit: true,
@@ -806,4 +855,44 @@ mod test {
             commit.apply_opts(&store, &OPTS).unwrap();
         }
     }
+
+    #[test]
+    fn rotate_and_revoke_agent_keys() {
+        let store = crate::test_utils::init_store();
+        let agent = store.get_default_agent().unwrap();
+        let (rotated, commit) = agent.rotate_key(&store).unwrap();
+        commit.apply_opts(&store, &OPTS).unwrap();
+        let agent_resource = store.get_resource(&agent.subject).unwrap();
+        assert_eq!(
+            crate::agents::get_agent_keys(&agent_resource)
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(rotated.subject, agent.subject);
+        // Make sure the new commits are created after the old key expired
+        std::thread::sleep(std::time::Duration::from_millis(2));
+
+        let subject = "https://localhost/rotated_thing";
+        let resource = Resource::new(subject.into());
+        let set_description = || {
+            let mut builder = CommitBuilder::new(subject.into());
+            builder.set(urls::DESCRIPTION.into(), Value::Markdown("Rotated".into()));
+            builder
+        };
+        let old_commit = set_description().sign(&agent, &store, &resource).unwrap();
+        old_commit.apply_opts(&store, &OPTS).unwrap_err();
+        let new_commit = set_description().sign(&rotated, &store, &resource).unwrap();
+        new_commit.apply_opts(&store, &OPTS).unwrap();
+
+        rotated
+            .revoke_key(&rotated.public_key, &store)
+            .expect_err("Revoking the last valid key should fail");
+        let revoke_commit = rotated.revoke_key(&agent.public_key, &store).unwrap();
+        revoke_commit.apply_opts(&store, &OPTS).unwrap();
+        let agent_resource = store.get_resource(&agent.subject).unwrap();
+        let keys = crate::agents::get_agent_keys(&agent_resource).unwrap();
+        assert!(keys[0].revoked_at.is_some());
+        assert!(keys[1].revoked_at.is_none());
+    }
 }
@@ -0,0 +1,366 @@
+//! CSV export of Collections, for spreadsheets, and importing CSV rows as new Resources.
+//! Members become rows, Properties become columns. Headers use the shortnames of the Properties.
+
+use std::collections::BTreeSet;
+
+use crate::{
+    datatype::DataType,
+    errors::AtomicResult,
+    urls,
+    values::{SubResource, Value},
+    Resource, Storelike,
+};
+
+/// Header of the column that contains the subjects of the members.
+pub const ID_COLUMN: &str = "@id";
+/// Separator between the items of a ResourceArray in a single cell.
+const ARRAY_SEPARATOR: &str = ", ";
+/// Spreadsheets treat cells that start with these as formulas.
+const FORMULA_PREFIXES: [char; 4] = ['=', '+', '-', '@'];
+/// Put in front of cells that would be read as a formula.
+const FORMULA_ESCAPE: char = '\'';
+
+/// Serializes the members of the current page of a Collection to CSV, including a header row.
+pub fn collection_to_csv(collection: &Resource, store: &impl Storelike) -> AtomicResult<String> {
+    let columns = collection_columns(collection, store)?;
+    let mut out = header_row(&columns, store);
+    out.push_str(&collection_rows(collection, &columns, store)?);
+    Ok(out)
+}
+
+/// Returns the Property URLs that are used as columns for a Collection.
+/// If the Collection contains instances of a Class, these are the `requires` and `recommends` of that Class.
+/// Otherwise, it's every Property used by the members in the current page.
+pub fn collection_columns(
+    collection: &Resource,
+    store: &impl Storelike,
+) -> AtomicResult<Vec<String>> {
+    let class_filter = match (
+        collection.get(urls::COLLECTION_PROPERTY),
+        collection.get(urls::COLLECTION_VALUE),
+    ) {
+        (Ok(property), Ok(value)) if property.to_string() == urls::IS_A => {
+            store.get_class(&value.to_string()).ok()
+        }
+        _ => None,
+    };
+    if let Some(class) = class_filter {
+        let mut columns = class.requires;
+        columns.extend(class.recommends);
+        return Ok(columns);
+    }
+    let mut columns = BTreeSet::new();
+    for member in members(collection, store)? {
+        columns.extend(member.get_propvals().keys().cloned());
+    }
+    Ok(columns.into_iter().collect())
+}
+
+/// Creates the header row, which contains the shortnames of the Properties.
+/// Uses the full URL if the Property is unknown, or if its shortname is already used by another column.
+pub fn header_row(columns: &[String], store: &impl Storelike) -> String {
+    let mut headers = vec![ID_COLUMN.to_string()];
+    for column in columns {
+        let header = match store.get_property(column) {
+            Ok(property) if !headers.contains(&property.shortname) => property.shortname,
+            _ => column.clone(),
+        };
+        headers.push(header);
+    }
+    to_row(&headers)
+}
+
+/// Creates a row for every member of the current page of the Collection.
+pub fn collection_rows(
+    collection: &Resource,
+    columns: &[String],
+    store: &impl Storelike,
+) -> AtomicResult<String> {
+    let mut out = String::new();
+    for member in members(collection, store)? {
+        let mut cells = vec![member.get_subject().clone()];
+        for column in columns {
+            cells.push(match member.get(column) {
+                Ok(value) => escape_formula(value_to_cell(value)),
+                Err(_) => "".into(),
+            });
+        }
+        out.push_str(&to_row(&cells));
+    }
+    Ok(out)
+}
+
+/// Returns the subject of a page of the Collection, e.g. for exporting all pages.
+pub fn page_subject(collection_subject: &str, page: usize) -> AtomicResult<String> {
+    let mut url = url::Url::parse(collection_subject)?;
+    let params: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(k, _)| k != "current_page" && k != "all")
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(params)
+        .append_pair("current_page", &page.to_string());
+    Ok(url.to_string())
+}
+
+/// Returns the members of the Collection. Uses the nested Resources if the Collection includes them.
+fn members(collection: &Resource, store: &impl Storelike) -> AtomicResult<Vec<Resource>> {
+    let members = match collection.get(urls::COLLECTION_MEMBERS) {
+        Ok(Value::ResourceArray(members)) => members,
+        Ok(_) => return Err("Members of the Collection are not a ResourceArray".into()),
+        Err(_) => return Ok(Vec::new()),
+    };
+    let mut resources = Vec::new();
+    for member in members {
+        match member {
+            SubResource::Resource(resource) => resources.push(*resource.clone()),
+            SubResource::Subject(subject) => resources.push(store.get_resource(subject)?),
+            SubResource::Nested(_) => {}
+        }
+    }
+    Ok(resources)
+}
+
+fn value_to_cell(value: &Value) -> String {
+    match value {
+        Value::ResourceArray(items) => items
+            .iter()
+            .map(|item| match item {
+                SubResource::Subject(subject) => subject.clone(),
+                SubResource::Resource(resource) => resource.get_subject().clone(),
+                SubResource::Nested(_) => "".into(),
+            })
+            .collect::<Vec<String>>()
+            .join(ARRAY_SEPARATOR),
+        other => other.to_string(),
+    }
+}
+
+/// Creates a CSV line, quoting cells that contain separators, quotes or line breaks.
+fn to_row(cells: &[String]) -> String {
+    let cells: Vec<String> = cells
+        .iter()
+        .map(|cell| {
+            if cell.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell.clone()
+            }
+        })
+        .collect();
+    format!("{}\r\n", cells.join(","))
+}
+
+/// Parses CSV (RFC 4180) into rows of cells.
+pub fn parse_csv(input: &str) -> Vec<Vec<String>> {
+    let input = input.trim_start_matches('\u{feff}');
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut cell = String::new();
+    let mut in_quotes = false;
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, in_quotes) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                cell.push('"');
+                chars.next();
+            }
+            ('"', true) => in_quotes = false,
+            ('"', false) if cell.is_empty() => in_quotes = true,
+            (',', false) => row.push(std::mem::take(&mut cell)),
+            ('\r', false) => {}
+            ('\n', false) => {
+                row.push(std::mem::take(&mut cell));
+                rows.push(std::mem::take(&mut row));
+            }
+            (c, _) => cell.push(c),
+        }
+    }
+    if !cell.is_empty() || !row.is_empty() {
+        row.push(cell);
+        rows.push(row);
+    }
+    rows
+}
+
+/// Creates a Resource of the `class` under the `parent` for every row of the CSV, and saves them.
+/// The header row should contain shortnames or URLs of Properties.
+/// The `@id`, `is-a`, `parent` and `last-commit` columns are ignored, so exports can be imported as copies.
+/// All rows are checked before anything is saved, so an invalid row does not lead to a partial import.
+/// Does not check rights, so make sure that the importing Agent (`for_agent`) can write to the `parent`.
+/// The Commits are signed by the default Agent of the Store, like uploads. The audit log records which Agent imported the Resources.
+pub fn import_csv(
+    csv: &str,
+    class: &str,
+    parent: &str,
+    for_agent: &str,
+    store: &impl Storelike,
+) -> AtomicResult<Vec<Resource>> {
+    let mut rows = parse_csv(csv).into_iter();
+    let header = rows.next().ok_or("The CSV is empty")?;
+    let class = store.get_class(class)?;
+    let mut context = None;
+    // The Property of every column, or None if the column is ignored
+    let mut columns: Vec<Option<crate::schema::Property>> = Vec::new();
+    for name in header.iter().map(|h| h.trim()) {
+        if name == ID_COLUMN || name.is_empty() {
+            columns.push(None);
+            continue;
+        }
+        let url = if name.contains("://") {
+            name.to_string()
+        } else {
+            let from_class = class
+                .requires
+                .iter()
+                .chain(class.recommends.iter())
+                .find(|p| {
+                    store
+                        .get_property(p)
+                        .map(|p| p.shortname == name)
+                        .unwrap_or(false)
+                });
+            match from_class {
+                Some(url) => url.clone(),
+                None => {
+                    if context.is_none() {
+                        context = Some(crate::serialize::json_ld_context(store)?);
+                    }
+                    let item = context.as_ref().and_then(|c| c.get(name));
+                    item.and_then(|i| i.get("@id").unwrap_or(i).as_str())
+                        .ok_or(format!(
+                            "Column `{}` is not the shortname of a Property",
+                            name
+                        ))?
+                        .to_string()
+                }
+            }
+        };
+        if [urls::IS_A, urls::PARENT, urls::LAST_COMMIT].contains(&url.as_str()) {
+            columns.push(None);
+            continue;
+        }
+        columns.push(Some(store.get_property(&url)?));
+    }
+
+    let mut checked = Vec::new();
+    for (i, row) in rows.enumerate() {
+        if row.iter().all(|cell| cell.trim().is_empty()) {
+            continue;
+        }
+        let subject = format!(
+            "{}/{}",
+            store.get_server_url(),
+            crate::utils::random_string(10)
+        );
+        let mut resource = Resource::new(subject);
+        resource.set_class(&class.subject, store)?;
+        resource.set_propval(urls::PARENT.into(), Value::AtomicUrl(parent.into()), store)?;
+        for (property, cell) in columns.iter().zip(row.iter()) {
+            let cell = unescape_formula(cell.trim());
+            let property = match property {
+                Some(property) if !cell.is_empty() => property,
+                _ => continue,
+            };
+            let value = match property.data_type {
+                DataType::ResourceArray => Value::ResourceArray(
+                    cell.split(',')
+                        .map(|s| s.trim())
+                        .filter(|s| !s.is_empty())
+                        .map(|s| SubResource::Subject(s.into()))
+                        .collect(),
+                ),
+                ref datatype => Value::new(cell, datatype)?,
+            };
+            resource
+                .set_propval(property.subject.clone(), value, store)
+                .map_err(|e| format!("Row {}, {}: {}", i + 2, property.shortname, e))?;
+        }
+        resource
+            .check_required_props(store)
+            .map_err(|e| format!("Row {}: {}", i + 2, e))?;
+        checked.push((i + 2, resource));
+    }
+
+    let mut created: Vec<Resource> = Vec::new();
+    for (row, mut resource) in checked {
+        if let Err(e) = resource.save_locally(store) {
+            let imported: Vec<&str> = created.iter().map(|r| r.get_subject().as_str()).collect();
+            return Err(format!(
+                "Row {}: {}. The rows before it have been imported: {:?}",
+                row, e, imported
+            )
+            .into());
+        }
+        store.add_audit_entry(&crate::audit::AuditEntry::new(
+            crate::audit::AuditAction::Import,
+            for_agent,
+            resource.get_subject(),
+            true,
+            None,
+        ));
+        created.push(resource);
+    }
+    Ok(created)
+}
+
+/// Escapes values that start like a formula with a `'`, so opening an export in a spreadsheet can't run one.
+fn escape_formula(cell: String) -> String {
+    if cell.starts_with(FORMULA_PREFIXES) {
+        format!("{}{}", FORMULA_ESCAPE, cell)
+    } else {
+        cell
+    }
+}
+
+/// Removes the escape that [escape_formula] adds to cells that start like a formula, so exports can be imported again.
+fn unescape_formula(cell: &str) -> &str {
+    match cell.strip_prefix(FORMULA_ESCAPE) {
+        Some(rest) if rest.starts_with(FORMULA_PREFIXES) => rest,
+        _ => cell,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_and_write_csv() {
+        let cells = vec!["a".to_string(), "b, \"c\"".into(), "d\ne".into()];
+        let row = to_row(&cells);
+        assert_eq!(row, "a,\"b, \"\"c\"\"\",\"d\ne\"\r\n");
+        assert_eq!(
+            parse_csv(&format!("x,y,z\r\n{}", row)),
+            vec![vec!["x".to_string(), "y".into(), "z".into()], cells]
+        );
+    }
+
+    #[test]
+    fn escape_formulas() {
+        let cells = vec![
+            "=1+1".to_string(),
+            "@SUM(A1)".into(),
+            "-5".into(),
+            "a=b".into(),
+        ];
+        let escaped: Vec<String> = cells.iter().cloned().map(escape_formula).collect();
+        let row = to_row(&escaped);
+        assert_eq!(row, "'=1+1,'@SUM(A1),'-5,a=b\r\n");
+        let parsed: Vec<String> = parse_csv(&row)[0]
+            .iter()
+            .map(|cell| unescape_formula(cell).to_string())
+            .collect();
+        assert_eq!(parsed, cells);
+    }
+
+    #[test]
+    fn page_subjects() {
+        assert_eq!(
+            page_subject("https://localhost/things?all=true&page_size=2", 3).unwrap(),
+            "https://localhost/things?page_size=2&current_page=3"
+        );
+    }
+}
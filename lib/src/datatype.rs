@@ -18,6 +18,21 @@ pub enum DataType {
     String,
     Timestamp,
     Unsupported(String),
+    /// Any absolute URI, including non-HTTP schemes such as `mailto:` and `urn:`
+    Uri,
+    /// Any valid JSON value
+    Json,
+    /// Arbitrary precision decimal number, stored as a string
+    Decimal,
+    /// ISO 8601 duration, e.g. `P1DT2H`
+    Duration,
+    /// WGS84 coordinate as `latitude,longitude`
+    GeoPoint,
+    /// A Resource that is embedded in its parent, instead of referred to by its URL.
+    /// Can be anonymous (without a subject).
+    NestedResource,
+    /// The same text in multiple languages, keyed by language tag
+    LangString,
 }
 
 pub fn match_datatype(string: &str) -> DataType {
@@ -32,6 +47,13 @@ pub fn match_datatype(string: &str) -> DataType {
         urls::SLUG => DataType::Slug,
         urls::STRING => DataType::String,
         urls::TIMESTAMP => DataType::Timestamp,
+        urls::URI => DataType::Uri,
+        urls::JSON => DataType::Json,
+        urls::DECIMAL => DataType::Decimal,
+        urls::DURATION => DataType::Duration,
+        urls::GEO_POINT => DataType::GeoPoint,
+        urls::NESTED_RESOURCE => DataType::NestedResource,
+        urls::LANG_STRING => DataType::LangString,
         unsupported_datatype => DataType::Unsupported(unsupported_datatype.into()),
     }
 }
@@ -51,6 +73,13 @@ impl std::str::FromStr for DataType {
             urls::SLUG => DataType::Slug,
             urls::STRING => DataType::String,
             urls::TIMESTAMP => DataType::Timestamp,
+            urls::URI => DataType::Uri,
+            urls::JSON => DataType::Json,
+            urls::DECIMAL => DataType::Decimal,
+            urls::DURATION => DataType::Duration,
+            urls::GEO_POINT => DataType::GeoPoint,
+            urls::NESTED_RESOURCE => DataType::NestedResource,
+            urls::LANG_STRING => DataType::LangString,
             unsupported_datatype => DataType::Unsupported(unsupported_datatype.into()),
         })
     }
@@ -70,6 +99,13 @@ impl fmt::Display for DataType {
             DataType::String => write!(f, "{}", urls::STRING),
             DataType::Timestamp => write!(f, "{}", urls::TIMESTAMP),
             DataType::Unsupported(url) => write!(f, "{}", url),
+            DataType::Uri => write!(f, "{}", urls::URI),
+            DataType::Json => write!(f, "{}", urls::JSON),
+            DataType::Decimal => write!(f, "{}", urls::DECIMAL),
+            DataType::Duration => write!(f, "{}", urls::DURATION),
+            DataType::GeoPoint => write!(f, "{}", urls::GEO_POINT),
+            DataType::NestedResource => write!(f, "{}", urls::NESTED_RESOURCE),
+            DataType::LangString => write!(f, "{}", urls::LANG_STRING),
         }
     }
 }
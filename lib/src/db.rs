@@ -23,6 +23,7 @@ use self::{
         atom_to_indexable_atoms, check_if_atom_matches_watched_query_filters, query_indexed,
         update_indexed_member, watch_collection, IndexAtom, QueryFilter, END_CHAR,
     },
+    rights_cache::RightsCache,
 };
 
 // A function called by the Store when a Commit is accepted
@@ -30,6 +31,7 @@ type HandleCommit = Box<dyn Fn(&CommitResponse) + Send + Sync>;
 
 mod migrations;
 mod query_index;
+mod rights_cache;
 #[cfg(test)]
 pub mod test;
 
@@ -61,6 +63,17 @@ pub struct Db {
     /// A list of all the Collections currently being used. Is used to update `members_index`.
     /// See [collections_index]
     watched_queries: sled::Tree,
+    /// Revoked [crate::tokens::CapabilityToken]s and [crate::sessions::Session]s. The key is the ID, the value its expiry timestamp.
+    revoked_tokens: sled::Tree,
+    /// Append-only [crate::audit::AuditEntry]s. The key is the timestamp followed by a unique ID, both big-endian, so entries are sorted by time.
+    audit_log: sled::Tree,
+    /// Outcomes of [crate::hierarchy::check_rights], invalidated when rights or parents change.
+    rights_cache: RightsCache,
+    /// Total `filesize` of the Files in every Drive, in bytes. The key is the subject of the Drive, the value a big-endian i64.
+    /// Counted once when it is first needed, and updated when Files are added, changed or destroyed.
+    drive_usage: sled::Tree,
+    /// Maximum amount of bytes for the Files in a Drive. Drives only show their usage if this is set.
+    drive_quota: Option<u64>,
     /// The address where the db will be hosted, e.g. http://localhost/
     server_url: String,
     /// Endpoints are checked whenever a resource is requested. They calculate (some properties of) the resource and return it.
@@ -79,6 +92,9 @@ impl Db {
         let reference_index = db.open_tree("reference_index")?;
         let members_index = db.open_tree("members_index")?;
         let watched_queries = db.open_tree("watched_queries")?;
+        let revoked_tokens = db.open_tree("revoked_tokens")?;
+        let audit_log = db.open_tree("audit_log")?;
+        let drive_usage = db.open_tree("drive_usage")?;
         let store = Db {
             db,
             default_agent: Arc::new(Mutex::new(None)),
@@ -87,6 +103,11 @@ impl Db {
             members_index,
             server_url,
             watched_queries,
+            revoked_tokens,
+            audit_log,
+            rights_cache: RightsCache::default(),
+            drive_usage,
+            drive_quota: None,
             endpoints: default_endpoints(),
             on_commit: None,
         };
@@ -125,6 +146,37 @@ impl Db {
         self.on_commit = Some(Arc::new(on_commit));
     }
 
+    /// Sets the maximum amount of bytes for the Files in a Drive.
+    /// When set, Drives show their [crate::urls::QUOTA_USAGE].
+    pub fn set_drive_quota(&mut self, quota: Option<u64>) {
+        self.drive_quota = quota;
+    }
+
+    /// Returns the total `filesize` of all Files in the Drive, in bytes.
+    /// The first time, this walks through all Files. After that, the counter is kept up to date by Commits.
+    pub fn get_drive_usage(&self, drive: &str) -> AtomicResult<i64> {
+        if let Some(usage) = self.drive_usage.get(drive.as_bytes())? {
+            return decode_usage(&usage);
+        }
+        let usage = crate::plugins::files::drive_usage(drive, self)?;
+        // Another thread may have counted it in the meantime, in which case that one is used
+        match self.drive_usage.compare_and_swap(
+            drive.as_bytes(),
+            None as Option<&[u8]>,
+            Some(&usage.to_be_bytes()),
+        )? {
+            Ok(()) => Ok(usage),
+            Err(e) => decode_usage(&e.current.ok_or("Drive usage was removed")?),
+        }
+    }
+
+    /// Removes the usage counters of all Drives, so they are counted again when they are needed.
+    /// Used for changes that the Commits of Files don't account for, such as moving a folder to another Drive.
+    pub fn reset_drive_usage(&self) -> AtomicResult<()> {
+        self.drive_usage.clear()?;
+        Ok(())
+    }
+
     /// Finds resource by Subject, return PropVals HashMap
     /// Deals with the binary API of Sled
     #[instrument(skip(self))]
@@ -156,11 +208,67 @@ impl Db {
         !self.reference_index.is_empty()
     }
 
+    /// Adds a [crate::tokens::CapabilityToken] to the revocation list.
+    pub fn revoke_token(&self, token: &crate::tokens::CapabilityToken) -> AtomicResult<()> {
+        self.revoke(&token.id, token.expires_at)
+    }
+
+    /// Adds a [crate::sessions::Session] to the revocation list, e.g. when the user logs out.
+    pub fn revoke_session(&self, session: &crate::sessions::Session) -> AtomicResult<()> {
+        self.revoke(&session.id, session.expires_at)
+    }
+
+    /// Adds the ID of a token or session to the revocation list.
+    /// Also cleans up revoked IDs that have expired, since these are rejected anyway.
+    fn revoke(&self, id: &str, expires_at: i64) -> AtomicResult<()> {
+        let now = crate::utils::now();
+        for item in self.revoked_tokens.iter() {
+            let (revoked_id, revoked_expires_at) = item?;
+            let revoked_expires_at = i64::from_be_bytes(
+                revoked_expires_at
+                    .as_ref()
+                    .try_into()
+                    .map_err(|_| "Corrupt revoked token")?,
+            );
+            if revoked_expires_at <= now {
+                self.revoked_tokens.remove(revoked_id)?;
+            }
+        }
+        self.revoked_tokens
+            .insert(id.as_bytes(), &expires_at.to_be_bytes())?;
+        Ok(())
+    }
+
+    /// Returns the entries of the audit log, oldest first.
+    /// `from` and `to` are inclusive Unix timestamps (ms).
+    pub fn get_audit_entries(
+        &self,
+        from: Option<i64>,
+        to: Option<i64>,
+        limit: usize,
+    ) -> AtomicResult<Vec<crate::audit::AuditEntry>> {
+        let start = from.unwrap_or(0).max(0).to_be_bytes();
+        let end = to.unwrap_or(i64::MAX).max(0).to_be_bytes();
+        let mut entries = Vec::new();
+        for item in self.audit_log.range(start..).take(limit) {
+            let (key, value) = item?;
+            if key[..8] > end[..] {
+                break;
+            }
+            let entry = bincode::deserialize(&value)
+                .map_err(|e| format!("Corrupt audit log entry. {}", e))?;
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
     /// Removes all values from the indexes.
     pub fn clear_index(&self) -> AtomicResult<()> {
         self.reference_index.clear()?;
         self.members_index.clear()?;
         self.watched_queries.clear()?;
+        // Rebuilding the index is also a good moment to count the Drives again
+        self.reset_drive_usage()?;
         Ok(())
     }
 }
@@ -231,7 +339,7 @@ impl Storelike for Db {
             resource.check_required_props(self)?;
         }
         if update_index {
-            if let Some(pv) = existing {
+            if let Some(pv) = &existing {
                 let subject = resource.get_subject();
                 for (prop, val) in pv.iter() {
                     // Possible performance hit - these clones can be replaced by modifying remove_atom_from_index
@@ -247,7 +355,19 @@ impl Storelike for Db {
                     .map_err(|e| format!("Failed to add atom to index {}. {}", a, e))?;
             }
         }
-        self.set_propvals(resource.get_subject(), resource.get_propvals())
+        self.set_propvals(resource.get_subject(), resource.get_propvals())?;
+        // Invalidated after writing, so a rights check that runs in the meantime can't cache the old rights
+        self.rights_cache.handle_change(
+            resource.get_subject(),
+            existing.as_ref(),
+            Some(resource.get_propvals()),
+        );
+        if let Some(pv) = &existing {
+            if moves_files(pv, resource.get_propvals()) {
+                self.reset_drive_usage()?;
+            }
+        }
+        Ok(())
     }
 
     #[instrument(skip(self))]
@@ -291,6 +411,79 @@ impl Storelike for Db {
         }
     }
 
+    fn get_resource_local(&self, subject: &str) -> AtomicResult<Resource> {
+        let propvals = self.get_propvals(subject)?;
+        Ok(crate::resources::Resource::from_propvals(
+            propvals,
+            subject.into(),
+        ))
+    }
+
+    fn get_cached_rights(
+        &self,
+        subject: &str,
+        agent: &str,
+        right: &str,
+    ) -> Option<AtomicResult<String>> {
+        self.rights_cache.get(subject, agent, right)
+    }
+
+    fn cache_rights(
+        &self,
+        subject: &str,
+        agent: &str,
+        right: &str,
+        outcome: &AtomicResult<String>,
+        dependencies: &[String],
+    ) {
+        self.rights_cache
+            .insert(subject, agent, right, outcome, dependencies)
+    }
+
+    fn get_groups(&self) -> AtomicResult<std::sync::Arc<std::collections::HashSet<String>>> {
+        if let Some(groups) = self.rights_cache.get_groups() {
+            return Ok(groups);
+        }
+        let groups = std::sync::Arc::new(crate::hierarchy::find_groups(self)?);
+        self.rights_cache.set_groups(groups.clone());
+        Ok(groups)
+    }
+
+    fn is_token_revoked(&self, token_id: &str) -> AtomicResult<bool> {
+        Ok(self.revoked_tokens.contains_key(token_id.as_bytes())?)
+    }
+
+    fn import(&self, string: &str) -> AtomicResult<usize> {
+        let vec = crate::parse::parse_json_ad_array(string, self, true)
+            .map_err(|e| format!("Unable to import JSON-AD. {}", e))?;
+        // Imported Files are not added using Commits, so the Drives have to be counted again
+        self.reset_drive_usage()?;
+        Ok(vec.len())
+    }
+
+    fn add_drive_usage(&self, drive: &str, delta: i64) -> AtomicResult<()> {
+        // Drives that haven't been counted yet will include the change when they are
+        self.drive_usage
+            .fetch_and_update(drive.as_bytes(), |usage| {
+                let usage = decode_usage(usage?).ok()?;
+                Some((usage + delta).to_be_bytes().to_vec())
+            })?;
+        Ok(())
+    }
+
+    fn add_audit_entry(&self, entry: &crate::audit::AuditEntry) {
+        let write = || -> AtomicResult<()> {
+            let mut key = entry.timestamp.to_be_bytes().to_vec();
+            key.extend_from_slice(&self.db.generate_id()?.to_be_bytes());
+            self.audit_log.insert(key, bincode::serialize(entry)?)?;
+            Ok(())
+        };
+        // The action itself should not fail because the log can't be written
+        if let Err(e) = write() {
+            tracing::error!("Failed to write audit log entry: {}", e);
+        }
+    }
+
     #[instrument(skip(self))]
     fn get_resource_extended(
         &self,
@@ -340,7 +533,17 @@ impl Storelike for Db {
         let mut resource = self.get_resource(&removed_query_params)?;
 
         if let Some(agent) = for_agent {
-            let _explanation = crate::hierarchy::check_read(self, &resource, agent)?;
+            // Collections use `skip_dynamic` to filter their members, so denials are expected there and not added to the audit log
+            let _explanation = if skip_dynamic {
+                crate::hierarchy::check_rights(
+                    self,
+                    &resource,
+                    agent,
+                    crate::hierarchy::Right::Read,
+                )?
+            } else {
+                crate::hierarchy::check_read(self, &resource, agent)?
+            };
         }
 
         // Whether the resource has dynamic properties
@@ -374,6 +577,14 @@ impl Storelike for Db {
                     has_dynamic = true;
                     if !skip_dynamic {
                         resource = crate::hierarchy::add_children(self, &mut resource)?;
+                        if self.drive_quota.is_some() {
+                            let usage = self.get_drive_usage(resource.get_subject())?;
+                            resource.set_propval(
+                                crate::urls::QUOTA_USAGE.into(),
+                                Value::Integer(usage),
+                                self,
+                            )?;
+                        }
                     }
                 }
                 crate::urls::CHATROOM => {
@@ -450,7 +661,8 @@ impl Storelike for Db {
                     Err(e) => match e.error_type {
                         crate::AtomicErrorType::NotFoundError => {}
                         crate::AtomicErrorType::UnauthorizedError => {}
-                        crate::AtomicErrorType::OtherError => {
+                        crate::AtomicErrorType::SchemaError
+                        | crate::AtomicErrorType::OtherError => {
                             return Err(
                                 format!("Error when getting resource in collection: {}", e).into()
                             )
@@ -547,6 +759,9 @@ impl Storelike for Db {
                 self.remove_atom_from_index(&remove_atom, &resource)?;
             }
             let _found = self.resources.remove(&subject.as_bytes())?;
+            // Invalidated after removing, so a rights check that runs in the meantime can't cache the old rights
+            self.rights_cache
+                .handle_change(subject, Some(resource.get_propvals()), None);
         } else {
             return Err(format!(
                 "Resource {} could not be deleted, because it was not found in the store.",
@@ -594,7 +809,7 @@ impl Storelike for Db {
 
         // If the value is a resourcearray, check if it is inside
         let val_equals = |val: &str| {
-            let q = q_value.unwrap().to_sortable_string();
+            let q = q_value.unwrap().to_string();
             val == q || {
                 if val.starts_with('[') {
                     match crate::parse::parse_json_array(val) {
@@ -667,6 +882,12 @@ impl Storelike for Db {
     }
 }
 
+fn decode_usage(bytes: &[u8]) -> AtomicResult<i64> {
+    Ok(i64::from_be_bytes(
+        bytes.try_into().map_err(|_| "Corrupt drive usage")?,
+    ))
+}
+
 #[instrument(skip(store))]
 fn add_atom_to_reference_index(index_atom: &IndexAtom, store: &Db) -> AtomicResult<()> {
     let _existing = store
@@ -688,6 +909,16 @@ fn key_for_reference_index(atom: &IndexAtom) -> String {
     format!("{}\n{}\n{}", atom.value, atom.property, atom.subject)
 }
 
+/// Whether a change to a Resource that is not a File can move the Files below it to another Drive.
+/// Changes to Files themselves are counted by [crate::plugins::files::after_apply_commit].
+fn moves_files(old: &PropVals, new: &PropVals) -> bool {
+    let changed =
+        |prop: &str| old.get(prop).map(|v| v.to_string()) != new.get(prop).map(|v| v.to_string());
+    !old.contains_key(crate::urls::FILESIZE)
+        && !new.contains_key(crate::urls::FILESIZE)
+        && (changed(crate::urls::PARENT) || changed(crate::urls::IS_A))
+}
+
 /// Parses a Value index key string, converts it into an atom. Note that the Value of the atom will allways be a single AtomicURL here.
 fn key_to_atom(key: &str) -> AtomicResult<Atom> {
     let mut parts = key.split('\n');
@@ -5,7 +5,7 @@
 use crate::{
     errors::AtomicResult,
     storelike::{Query, QueryResult},
-    values::query_value_compare,
+    values::{query_value_compare, SubResource},
     Atom, Db, Resource, Storelike, Value,
 };
 use serde::{Deserialize, Serialize};
@@ -113,7 +113,8 @@ pub fn query_indexed(store: &Db, q: &Query) -> AtomicResult<QueryResult> {
                     Err(e) => match e.error_type {
                         crate::AtomicErrorType::NotFoundError => {}
                         crate::AtomicErrorType::UnauthorizedError => {}
-                        crate::AtomicErrorType::OtherError => {
+                        crate::AtomicErrorType::SchemaError
+                        | crate::AtomicErrorType::OtherError => {
                             return Err(
                                 format!("Error when getting resource in collection: {}", e).into()
                             )
@@ -402,18 +403,26 @@ pub fn parse_collection_members_key(bytes: &[u8]) -> AtomicResult<(QueryFilter,
 /// Returns None for unsupported types.
 pub fn value_to_reference_index_string(value: &Value) -> Option<Vec<String>> {
     let vals = match value {
-        // This results in wrong indexing, as some subjects will be numbers.
-        Value::ResourceArray(_v) => value.to_subjects(None).unwrap_or_else(|_| vec![]),
+        // Anonymous Nested Resources have no subject that can be referred to, so they are skipped.
+        Value::ResourceArray(v) => v.iter().filter_map(subresource_to_subject).collect(),
         Value::AtomicUrl(v) => vec![v.into()],
-        // We don't index nested resources for now
-        Value::Resource(_r) => return None,
-        Value::NestedResource(_r) => return None,
+        Value::Resource(r) => vec![r.get_subject().into()],
+        Value::NestedResource(r) => vec![subresource_to_subject(r)?],
         // This might result in unnecessarily long strings, sometimes. We may want to shorten them later.
         val => vec![val.to_string()],
     };
     Some(vals)
 }
 
+/// Returns the subject of a linked or embedded Resource. Returns None for anonymous Nested Resources.
+fn subresource_to_subject(sub: &SubResource) -> Option<String> {
+    match sub {
+        SubResource::Subject(s) => Some(s.clone()),
+        SubResource::Resource(r) => Some(r.get_subject().clone()),
+        SubResource::Nested(_) => None,
+    }
+}
+
 /// Converts one Atom to a series of stringified values that can be indexed.
 #[tracing::instrument(skip(atom))]
 pub fn atom_to_indexable_atoms(atom: &Atom) -> AtomicResult<Vec<IndexAtom>> {
@@ -469,6 +478,22 @@ pub mod test {
         }
     }
 
+    #[test]
+    fn index_strings_skip_anonymous_nested() {
+        let mut nested = crate::resources::PropVals::new();
+        nested.insert(urls::NAME.into(), Value::String("anonymous".into()));
+        let array = Value::ResourceArray(vec![
+            "https://example.com/a".into(),
+            nested.clone().into(),
+            Resource::new("https://example.com/b".into()).into(),
+        ]);
+        assert_eq!(
+            value_to_reference_index_string(&array).unwrap(),
+            vec!["https://example.com/a", "https://example.com/b"]
+        );
+        assert!(value_to_reference_index_string(&Value::from(nested)).is_none());
+    }
+
     #[test]
     fn lexicographic_partial() {
         let q = QueryFilter {
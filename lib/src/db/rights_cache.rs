@@ -0,0 +1,144 @@
+//! Caches the outcome of [crate::hierarchy::check_rights] for Resources, so deep hierarchies and large Collections don't have to walk up all parents for every Resource.
+//! Entries are removed when one of the Resources they depend on changes its rights or parent.
+//! Also keeps the subjects of all Groups, which are needed for every rights check that encounters an Agent it doesn't know.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, RwLock},
+};
+
+use crate::{
+    errors::{AtomicError, AtomicErrorType, AtomicResult},
+    resources::PropVals,
+    urls,
+};
+
+/// The cache is cleared when it grows beyond this amount of entries.
+const MAX_ENTRIES: usize = 100_000;
+
+/// Changes to these properties affect the rights of a Resource and its children.
+const RIGHTS_PROPS: [&str; 8] = [
+    urls::PARENT,
+    urls::READ,
+    urls::WRITE,
+    urls::APPEND,
+    urls::DENY_READ,
+    urls::DENY_WRITE,
+    urls::DENY_APPEND,
+    urls::STOP_INHERITANCE,
+];
+
+/// Subject, Agent and Right URL
+type CacheKey = (String, String, String);
+
+#[derive(Default)]
+struct CacheInner {
+    /// The explanation if the right was granted, the error message if it was denied.
+    entries: HashMap<CacheKey, Result<String, String>>,
+    /// For every subject, the entries that were calculated using its rights.
+    dependents: HashMap<String, HashSet<CacheKey>>,
+    /// Subjects of all Groups in the store, if they have been looked up since the last change to a Group.
+    groups: Option<Arc<HashSet<String>>>,
+}
+
+#[derive(Clone, Default)]
+pub struct RightsCache {
+    inner: Arc<RwLock<CacheInner>>,
+}
+
+impl RightsCache {
+    pub fn get(&self, subject: &str, agent: &str, right: &str) -> Option<AtomicResult<String>> {
+        let inner = self.inner.read().unwrap();
+        let key = (subject.to_string(), agent.to_string(), right.to_string());
+        inner.entries.get(&key).map(|outcome| match outcome {
+            Ok(explanation) => Ok(explanation.clone()),
+            Err(message) => Err(AtomicError {
+                message: message.clone(),
+                error_type: AtomicErrorType::UnauthorizedError,
+            }),
+        })
+    }
+
+    /// Only stores granted rights and authorization errors, since other errors (e.g. a missing parent) might be temporary.
+    pub fn insert(
+        &self,
+        subject: &str,
+        agent: &str,
+        right: &str,
+        outcome: &AtomicResult<String>,
+        dependencies: &[String],
+    ) {
+        let cached = match outcome {
+            Ok(explanation) => Ok(explanation.clone()),
+            Err(e) if matches!(e.error_type, AtomicErrorType::UnauthorizedError) => {
+                Err(e.message.clone())
+            }
+            Err(_) => return,
+        };
+        let mut inner = self.inner.write().unwrap();
+        if inner.entries.len() >= MAX_ENTRIES {
+            *inner = CacheInner::default();
+        }
+        let key = (subject.to_string(), agent.to_string(), right.to_string());
+        for dependency in dependencies {
+            inner
+                .dependents
+                .entry(dependency.clone())
+                .or_default()
+                .insert(key.clone());
+        }
+        inner.entries.insert(key, cached);
+    }
+
+    /// Removes all entries that depend on the subject, and the entries that depend on those.
+    pub fn invalidate(&self, subject: &str) {
+        let mut inner = self.inner.write().unwrap();
+        let mut todo = vec![subject.to_string()];
+        let mut done = HashSet::new();
+        while let Some(current) = todo.pop() {
+            if !done.insert(current.clone()) {
+                continue;
+            }
+            if let Some(keys) = inner.dependents.remove(&current) {
+                for key in keys {
+                    inner.entries.remove(&key);
+                    todo.push(key.0);
+                }
+            }
+        }
+    }
+
+    pub fn get_groups(&self) -> Option<Arc<HashSet<String>>> {
+        self.inner.read().unwrap().groups.clone()
+    }
+
+    pub fn set_groups(&self, groups: Arc<HashSet<String>>) {
+        self.inner.write().unwrap().groups = Some(groups);
+    }
+
+    pub fn clear(&self) {
+        *self.inner.write().unwrap() = CacheInner::default();
+    }
+
+    /// Invalidates the cache if the new version of a Resource has different rights than the old one.
+    /// Pass `None` for new Resources or removed Resources.
+    pub fn handle_change(&self, subject: &str, old: Option<&PropVals>, new: Option<&PropVals>) {
+        let changed = |prop: &str| {
+            old.and_then(|pv| pv.get(prop)).map(|v| v.to_string())
+                != new.and_then(|pv| pv.get(prop)).map(|v| v.to_string())
+        };
+        let is_group = |propvals: Option<&PropVals>| {
+            propvals
+                .and_then(|pv| pv.get(urls::IS_A))
+                .and_then(|classes| classes.to_subjects(None).ok())
+                .is_some_and(|classes| classes.iter().any(|class| class == urls::GROUP))
+        };
+        // Groups can be used in the rights of any Resource, so we don't know which entries depend on them.
+        // Clearing also forgets the set of Groups.
+        if changed(urls::GROUP_MEMBERS) || is_group(old) != is_group(new) {
+            self.clear();
+        } else if old.is_none() || new.is_none() || RIGHTS_PROPS.iter().any(|p| changed(p)) {
+            self.invalidate(subject);
+        }
+    }
+}
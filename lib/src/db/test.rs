@@ -112,6 +112,34 @@ fn add_atom_to_index() {
     assert_eq!(found_external.len(), 1);
 }
 
+#[test]
+/// Decimals are stored in a sortable form in the query index, but TPF queries should compare their plain values
+fn tpf_decimal() {
+    let store = Db::init_temp("tpf_decimal").unwrap();
+    let subject = format!("{}/decimal", store.get_server_url());
+    let property = "https://example.com/weight";
+    let value = Value::Decimal("-12.5".into());
+    let mut resource = Resource::new(subject.clone());
+    resource.set_propval_unsafe(property.into(), value.clone());
+    store
+        .add_resource_opts(&resource, false, true, true)
+        .unwrap();
+
+    let found = store
+        .tpf(Some(&subject), Some(property), Some(&value), false)
+        .unwrap();
+    assert_eq!(found.len(), 1);
+    let found = store
+        .tpf(None, Some(property), Some(&value), false)
+        .unwrap();
+    assert_eq!(found.len(), 1);
+    let other = Value::Decimal("12.5".into());
+    let found = store
+        .tpf(Some(&subject), Some(property), Some(&other), false)
+        .unwrap();
+    assert!(found.is_empty());
+}
+
 #[test]
 /// Check if a resource is properly removed from the DB after a delete command.
 /// Also counts commits.
@@ -430,6 +458,599 @@ fn test_db_resources_all() {
     );
 }
 
+#[test]
+fn capability_tokens() {
+    use crate::{
+        hierarchy::{check_read, check_write, Right},
+        tokens::CapabilityToken,
+    };
+
+    let store = &Db::init_temp("capability_tokens").unwrap();
+    let agent = store.get_default_agent().unwrap();
+    let new_child = |subject: &str, parent: &str| {
+        let mut resource = Resource::new(subject.into());
+        resource
+            .set_propval(urls::PARENT.into(), Value::AtomicUrl(parent.into()), store)
+            .unwrap();
+        resource.save_locally(store).unwrap();
+        resource
+    };
+    let folder = new_child("https://localhost/folder", "https://localhost");
+    let nested = new_child("https://localhost/folder/nested", folder.get_subject());
+    let outside = new_child("https://localhost/outside", "https://localhost");
+
+    let expires_at = crate::utils::now() + 60_000;
+    let token = CapabilityToken::new(
+        &agent.subject,
+        folder.get_subject(),
+        &[Right::Read],
+        expires_at,
+    );
+    let signed = token.sign(&agent).unwrap();
+    check_read(store, &nested, &signed).expect("Token should grant read in its scope");
+    check_read(store, &outside, &signed).expect_err("Token should not work outside its scope");
+    check_write(store, &nested, &signed).expect_err("Token should only grant read");
+
+    // Circular hierarchies end the search for the scope
+    let circle_a = new_child("https://localhost/circle-a", "https://localhost/circle-b");
+    new_child("https://localhost/circle-b", circle_a.get_subject());
+    assert!(!token.in_scope(&circle_a, store));
+
+    // Tokens can't grant more than the issuer has
+    let stranger = store.create_agent(None).unwrap();
+    let stranger_token = CapabilityToken::new(
+        &stranger.subject,
+        folder.get_subject(),
+        &[Right::Write],
+        expires_at,
+    )
+    .sign(&stranger)
+    .unwrap();
+    check_write(store, &nested, &stranger_token).unwrap_err();
+
+    // Tampering with the payload invalidates the signature
+    let broader = CapabilityToken {
+        scope: "https://localhost".into(),
+        ..token.clone()
+    }
+    .sign(&agent)
+    .unwrap();
+    let (broader_payload, _) = broader.rsplit_once('.').unwrap();
+    let (_, signature) = signed.rsplit_once('.').unwrap();
+    let forged = format!("{}.{}", broader_payload, signature);
+    check_read(store, &outside, &forged).unwrap_err();
+
+    let expired = CapabilityToken::new(&agent.subject, folder.get_subject(), &[Right::Read], 0)
+        .sign(&agent)
+        .unwrap();
+    check_read(store, &nested, &expired).unwrap_err();
+
+    store.revoke_token(&token).unwrap();
+    check_read(store, &nested, &signed).expect_err("Revoked tokens should be rejected");
+}
+
+#[test]
+fn rights_cache_invalidation() {
+    use crate::hierarchy::check_read;
+
+    let store = &Db::init_temp("rights_cache").unwrap();
+    let reader = store.create_agent(None).unwrap();
+    let mut root = Resource::new("https://localhost/cache_root".into());
+    root.set_propval(
+        urls::READ.into(),
+        vec![reader.subject.as_str()].into(),
+        store,
+    )
+    .unwrap();
+    root.save_locally(store).unwrap();
+    let mut parent = root.get_subject().clone();
+    let mut leaf = root.clone();
+    for i in 0..3 {
+        leaf = Resource::new(format!("{}/{}", parent, i));
+        leaf.set_propval(urls::PARENT.into(), Value::AtomicUrl(parent), store)
+            .unwrap();
+        leaf.save_locally(store).unwrap();
+        parent = leaf.get_subject().clone();
+    }
+
+    check_read(store, &leaf, &reader.subject).unwrap();
+    // The second check uses the cached outcome of the parents
+    check_read(store, &leaf, &reader.subject).unwrap();
+
+    // Removing the right from the root should invalidate the cache for all its descendants
+    root.remove_propval(urls::READ);
+    root.save_locally(store).unwrap();
+    check_read(store, &leaf, &reader.subject).unwrap_err();
+
+    // And adding it again should too
+    root.set_propval(
+        urls::READ.into(),
+        vec![reader.subject.as_str()].into(),
+        store,
+    )
+    .unwrap();
+    root.save_locally(store).unwrap();
+    check_read(store, &leaf, &reader.subject).unwrap();
+}
+
+#[test]
+fn group_cache_invalidation() {
+    use crate::hierarchy::check_read;
+
+    let store = &Db::init_temp("group_cache").unwrap();
+    let reader = store.create_agent(None).unwrap();
+    let group_subject = "https://localhost/cache_group";
+    let mut resource = Resource::new("https://localhost/cache_grouped".into());
+    resource
+        .set_propval(urls::READ.into(), vec![group_subject].into(), store)
+        .unwrap();
+    resource.save_locally(store).unwrap();
+    // This check remembers the Groups in the store, which don't include the new Group yet
+    check_read(store, &resource, &reader.subject).unwrap_err();
+    assert!(!store.get_groups().unwrap().contains(group_subject));
+
+    let mut group = Resource::new(group_subject.into());
+    group
+        .set_propval(urls::IS_A.into(), vec![urls::GROUP].into(), store)
+        .unwrap();
+    group
+        .set_propval(
+            urls::GROUP_MEMBERS.into(),
+            vec![reader.subject.as_str()].into(),
+            store,
+        )
+        .unwrap();
+    group.save_locally(store).unwrap();
+    check_read(store, &resource, &reader.subject).unwrap();
+
+    // Removing the Group should forget it again
+    store.remove_resource(group_subject).unwrap();
+    assert!(!store.get_groups().unwrap().contains(group_subject));
+    check_read(store, &resource, &reader.subject).unwrap_err();
+}
+
+#[test]
+fn audit_log() {
+    use crate::audit::AuditAction;
+
+    let store = &Db::init_temp("audit_log").unwrap();
+    // Make sure the default Agent, created by `init_temp`, is logged before `start`
+    std::thread::sleep(std::time::Duration::from_millis(2));
+    let start = crate::utils::now();
+    let stranger = store.create_agent(None).unwrap();
+    let drive = store.get_resource(store.get_server_url()).unwrap();
+    crate::hierarchy::check_write(store, &drive, &stranger.subject).unwrap_err();
+    // Reads that are allowed are not recorded
+    crate::hierarchy::check_read(store, &drive, &stranger.subject).unwrap();
+
+    let entries = store.get_audit_entries(Some(start), None, 100).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].action, AuditAction::CreateAgent);
+    assert_eq!(entries[0].agent, stranger.subject);
+    assert_eq!(entries[1].action, AuditAction::Denied);
+    assert_eq!(entries[1].subject, store.get_server_url());
+    assert!(!entries[1].allowed);
+
+    // Time range filtering
+    assert!(store
+        .get_audit_entries(None, Some(start - 1), 100)
+        .unwrap()
+        .iter()
+        .all(|e| e.timestamp < start));
+    let later = store
+        .get_audit_entries(Some(crate::utils::now() + 1000), None, 100)
+        .unwrap();
+    assert!(later.is_empty());
+
+    let exported = crate::audit::entries_to_json_ad(&entries).unwrap();
+    assert!(exported.contains(urls::AUDIT_ACTION));
+}
+
+#[test]
+fn csv_export_and_import() {
+    let store = &Db::init_temp("csv_export_and_import").unwrap();
+    let subject = format!(
+        "{}/classes?page_size=200&include_external=true",
+        store.get_server_url()
+    );
+    let collection = store.get_resource_extended(&subject, false, None).unwrap();
+    let csv = crate::csv::collection_to_csv(&collection, store).unwrap();
+    let mut lines = csv.lines();
+    assert!(lines
+        .next()
+        .unwrap()
+        .starts_with("@id,shortname,description"));
+    assert!(csv.contains(&format!("{},agent,", urls::AGENT)));
+
+    let import =
+        "shortname,description,@id\r\nimported,\"Has a comma, and \"\"quotes\"\"\",ignored\r\n";
+    let agent = store.get_default_agent().unwrap().subject;
+    let created =
+        crate::csv::import_csv(import, urls::CLASS, store.get_server_url(), &agent, store).unwrap();
+    assert_eq!(created.len(), 1);
+    let resource = store.get_resource(created[0].get_subject()).unwrap();
+    assert_eq!(
+        resource.get(urls::DESCRIPTION).unwrap().to_string(),
+        "Has a comma, and \"quotes\""
+    );
+    assert_eq!(
+        resource.get(urls::PARENT).unwrap().to_string(),
+        store.get_server_url()
+    );
+    crate::csv::import_csv(
+        "unknown\r\nvalue",
+        urls::CLASS,
+        store.get_server_url(),
+        &agent,
+        store,
+    )
+    .unwrap_err();
+
+    // A row without the required description fails the import, before the valid rows are saved
+    let members_before = store
+        .tpf(
+            None,
+            Some(urls::SHORTNAME),
+            Some(&Value::Slug("valid".into())),
+            false,
+        )
+        .unwrap()
+        .len();
+    crate::csv::import_csv(
+        "shortname,description\r\nvalid,Fine\r\ninvalid,\r\n",
+        urls::CLASS,
+        store.get_server_url(),
+        &agent,
+        store,
+    )
+    .expect_err("Rows without required properties should fail");
+    assert_eq!(
+        store
+            .tpf(
+                None,
+                Some(urls::SHORTNAME),
+                Some(&Value::Slug("valid".into())),
+                false
+            )
+            .unwrap()
+            .len(),
+        members_before
+    );
+}
+
+#[test]
+fn invites() {
+    use crate::hierarchy::{check_append, check_read, check_write};
+
+    let store = &Db::init_temp("invites").unwrap();
+    let new_child = |subject: &str| {
+        let mut resource = Resource::new(subject.into());
+        resource
+            .set_propval(
+                urls::PARENT.into(),
+                Value::AtomicUrl(store.get_server_url().into()),
+                store,
+            )
+            .unwrap();
+        resource.save(store).unwrap();
+        resource
+    };
+    let folder = new_child("https://localhost/invite-folder");
+    let new_invite = |propvals: Vec<(&str, Value)>| {
+        let mut invite = Resource::new_instance(urls::INVITE, store).unwrap();
+        invite
+            .set_propval(
+                urls::PARENT.into(),
+                Value::AtomicUrl(store.get_server_url().into()),
+                store,
+            )
+            .unwrap();
+        invite
+            .set_propval(
+                urls::TARGET.into(),
+                Value::AtomicUrl(folder.get_subject().into()),
+                store,
+            )
+            .unwrap();
+        for (prop, val) in propvals {
+            invite.set_propval(prop.into(), val, store).unwrap();
+        }
+        invite.save(store).map(|_| invite)
+    };
+    let accept = |invite: &Resource, agent: &str| {
+        let subject = format!(
+            "{}?agent={}",
+            invite.get_subject(),
+            urlencoding::encode(agent)
+        );
+        store.get_resource_extended(&subject, false, None)
+    };
+
+    // Append-only invites, found by their code
+    let invite = new_invite(vec![
+        (urls::INVITE_ROLE, Value::String("append".into())),
+        (urls::INVITE_CODE, Value::String("TEAM-2024-ABCD".into())),
+        (urls::USAGES_LEFT, Value::Integer(1)),
+        (
+            urls::EXPIRES_AT,
+            Value::Timestamp(crate::utils::now() + 60_000),
+        ),
+    ])
+    .unwrap();
+    let found = store
+        .get_resource_extended(
+            "https://localhost/invite-code?code=team-2024-abcd",
+            false,
+            None,
+        )
+        .unwrap();
+    assert_eq!(
+        found.get(urls::DESTINATION).unwrap().to_string(),
+        invite.get_subject().as_str()
+    );
+    new_invite(vec![(
+        urls::INVITE_CODE,
+        Value::String("TEAM-2024-ABCD".into()),
+    )])
+    .expect_err("Codes should be unique");
+    new_invite(vec![(urls::INVITE_CODE, Value::String("short".into()))])
+        .expect_err("Codes should be long enough");
+    new_invite(vec![(urls::INVITE_ROLE, Value::String("owner".into()))])
+        .expect_err("Unknown roles should be rejected");
+
+    let teammate = store.create_agent(None).unwrap();
+    accept(&invite, &teammate.subject).unwrap();
+    let mut child = Resource::new("https://localhost/invite-folder/child".into());
+    child
+        .set_propval(
+            urls::PARENT.into(),
+            Value::AtomicUrl(folder.get_subject().into()),
+            store,
+        )
+        .unwrap();
+    let folder = store.get_resource(folder.get_subject()).unwrap();
+    check_read(store, &folder, &teammate.subject).unwrap();
+    check_append(store, &child, &teammate.subject).unwrap();
+    check_write(store, &folder, &teammate.subject).unwrap_err();
+
+    let accepted = store.get_resource(invite.get_subject()).unwrap();
+    assert_eq!(
+        accepted.get(urls::USAGES_LEFT).unwrap().to_int().unwrap(),
+        0
+    );
+    assert_eq!(
+        accepted
+            .get(urls::USED_BY)
+            .unwrap()
+            .to_subjects(None)
+            .unwrap(),
+        vec![teammate.subject.clone()]
+    );
+    match accepted.get(urls::INVITE_ACCEPTANCES).unwrap() {
+        Value::ResourceArray(acceptances) => assert_eq!(acceptances.len(), 1),
+        other => panic!("Unexpected acceptances {:?}", other),
+    }
+    let other = store.create_agent(None).unwrap();
+    accept(&invite, &other.subject).expect_err("No usages left");
+
+    // Group invites add the Agent to the members of the Group
+    let mut group = Resource::new_instance(urls::GROUP, store).unwrap();
+    group
+        .set_propval(
+            urls::PARENT.into(),
+            Value::AtomicUrl(store.get_server_url().into()),
+            store,
+        )
+        .unwrap();
+    group
+        .set_propval(
+            urls::GROUP_MEMBERS.into(),
+            Value::ResourceArray(Vec::new()),
+            store,
+        )
+        .unwrap();
+    group.save(store).unwrap();
+    let group_invite = new_invite(vec![(
+        urls::INVITE_GROUP,
+        Value::AtomicUrl(group.get_subject().into()),
+    )])
+    .unwrap();
+    accept(&group_invite, &other.subject).unwrap();
+    let group = store.get_resource(group.get_subject()).unwrap();
+    assert_eq!(
+        group
+            .get(urls::GROUP_MEMBERS)
+            .unwrap()
+            .to_subjects(None)
+            .unwrap(),
+        vec![other.subject.clone()]
+    );
+
+    // Revoked and expired invites can't be accepted
+    let mut revoked = new_invite(vec![]).unwrap();
+    revoked
+        .set_propval(urls::INVITE_REVOKED.into(), Value::Boolean(true), store)
+        .unwrap();
+    revoked.save(store).unwrap();
+    accept(&revoked, &other.subject).expect_err("Revoked invites should not work");
+    let expired = new_invite(vec![(urls::EXPIRES_AT, Value::Timestamp(1))]).unwrap();
+    accept(&expired, &other.subject).expect_err("Expired invites should not work");
+}
+
+#[test]
+fn drive_quota_usage() {
+    let mut store = Db::init_temp("drive_quota_usage").unwrap();
+    let drive = store.get_server_url().to_string();
+    let drive_usage = |store: &Db| {
+        store
+            .get_resource_extended(&drive, false, None)
+            .unwrap()
+            .get(urls::QUOTA_USAGE)
+            .map(|usage| usage.to_int().unwrap())
+            .ok()
+    };
+    // Without a quota, the usage is not shown
+    assert_eq!(drive_usage(&store), None);
+    store.set_drive_quota(Some(1000));
+    let store = &store;
+
+    let new_file = |subject: &str, parent: &str, size: i64| {
+        let mut file = Resource::new(subject.into());
+        file.set_class(urls::FILE, store).unwrap();
+        file.set_propval(urls::PARENT.into(), Value::AtomicUrl(parent.into()), store)
+            .unwrap();
+        file.set_propval(urls::FILESIZE.into(), Value::Integer(size), store)
+            .unwrap();
+        file.set_propval_string(
+            urls::DOWNLOAD_URL.into(),
+            &format!("{}/download", subject),
+            store,
+        )
+        .unwrap();
+        file.save_locally(store).unwrap();
+        file
+    };
+    let mut folder = Resource::new("https://localhost/quota-folder".into());
+    folder
+        .set_propval(urls::PARENT.into(), Value::AtomicUrl(drive.clone()), store)
+        .unwrap();
+    folder.save_locally(store).unwrap();
+    let mut file_a = new_file("https://localhost/files/a", &drive, 100);
+    new_file("https://localhost/files/b", folder.get_subject(), 50);
+    // Files outside of the Drive don't count
+    new_file("https://example.com/files/c", "https://example.com", 1000);
+
+    assert_eq!(
+        crate::plugins::files::find_drive("https://localhost/files/b", store)
+            .unwrap()
+            .as_deref(),
+        Some(drive.as_str())
+    );
+    assert_eq!(
+        crate::plugins::files::drive_usage(&drive, store).unwrap(),
+        150
+    );
+    assert_eq!(drive_usage(store), Some(150));
+
+    // After the first count, Commits update the counter
+    new_file("https://localhost/files/d", folder.get_subject(), 25);
+    assert_eq!(drive_usage(store), Some(175));
+    file_a.destroy(store).unwrap();
+    assert_eq!(drive_usage(store), Some(75));
+    assert_eq!(
+        crate::plugins::files::drive_usage(&drive, store).unwrap(),
+        75
+    );
+
+    // Moving a folder moves its Files to the other Drive
+    let mut other_drive = Resource::new("https://localhost/other-drive".into());
+    other_drive.set_class(urls::DRIVE, store).unwrap();
+    other_drive.save_locally(store).unwrap();
+    let other_usage = |store: &Db| store.get_drive_usage(other_drive.get_subject()).unwrap();
+    assert_eq!(other_usage(store), 0);
+    folder
+        .set_propval(
+            urls::PARENT.into(),
+            Value::AtomicUrl(other_drive.get_subject().into()),
+            store,
+        )
+        .unwrap();
+    folder.save_locally(store).unwrap();
+    assert_eq!(drive_usage(store), Some(0));
+    assert_eq!(other_usage(store), 75);
+
+    // Imported Files are counted too
+    let imported = format!(
+        r#"[{{"@id": "https://localhost/files/imported", "{}": "{}", "{}": 10}}]"#,
+        urls::PARENT,
+        drive,
+        urls::FILESIZE
+    );
+    store.import(&imported).unwrap();
+    assert_eq!(drive_usage(store), Some(10));
+}
+
+#[test]
+fn sessions() {
+    use crate::sessions::Session;
+
+    let store = &Db::init_temp("sessions").unwrap();
+    let agent = store.create_agent(None).unwrap();
+    let session = Session::new(&agent.subject, crate::utils::now() + 60_000);
+    let signed = session.sign(store).unwrap();
+    assert_eq!(Session::verify(&signed, store).unwrap(), session);
+
+    // Sessions can't be changed without the key of the server
+    let mut forged = session.clone();
+    forged.agent = "https://localhost/someone-else".into();
+    let forged_payload = forged.sign(store).unwrap();
+    let forged_payload = forged_payload.split('.').nth(1).unwrap();
+    let original_signature = signed.split('.').nth(2).unwrap();
+    Session::verify(
+        &format!(
+            "{}{}.{}",
+            crate::sessions::SESSION_PREFIX,
+            forged_payload,
+            original_signature
+        ),
+        store,
+    )
+    .unwrap_err();
+
+    let expired = Session::new(&agent.subject, crate::utils::now() - 1)
+        .sign(store)
+        .unwrap();
+    Session::verify(&expired, store).unwrap_err();
+
+    store.revoke_session(&session).unwrap();
+    Session::verify(&signed, store).expect_err("Revoked sessions should be rejected");
+}
+
+#[test]
+fn unique_within_parent() {
+    let store = &Db::init_temp("unique_within_parent").unwrap();
+    let property = crate::schema::Property {
+        class_type: None,
+        data_type: crate::datatype::DataType::Slug,
+        shortname: "unique-name".into(),
+        description: "Unique among siblings".into(),
+        subject: "https://localhost/properties/unique-name".into(),
+        allows_only: None,
+        constraints: crate::schema::PropertyConstraints {
+            unique_within_parent: true,
+            ..Default::default()
+        },
+    };
+    store.add_resource(&property.to_resource()).unwrap();
+    let new_child = |subject: &str, parent: &str, name: &str| {
+        let mut resource = Resource::new(subject.into());
+        resource
+            .set_propval(urls::PARENT.into(), Value::AtomicUrl(parent.into()), store)
+            .unwrap();
+        resource
+            .set_propval(property.subject.clone(), Value::Slug(name.into()), store)
+            .unwrap();
+        resource.save(store).map(|_| resource)
+    };
+    let parent = store.get_server_url();
+    let mut first = new_child("https://localhost/unique-1", parent, "same").unwrap();
+    let err = new_child("https://localhost/unique-2", parent, "same")
+        .expect_err("Siblings can not share the value");
+    assert!(err.to_string().contains(first.get_subject()));
+    new_child("https://localhost/unique-3", parent, "other").unwrap();
+    // Other parents are fine
+    new_child("https://localhost/unique-4", first.get_subject(), "same").unwrap();
+    // Saving the resource itself again does not conflict with its own value
+    first
+        .set_propval(
+            urls::DESCRIPTION.into(),
+            Value::Markdown("changed".into()),
+            store,
+        )
+        .unwrap();
+    first.save(store).unwrap();
+}
+
 #[test]
 /// Changing these values actually correctly updates the index.
 fn index_invalidate_cache() {
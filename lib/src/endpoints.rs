@@ -6,9 +6,14 @@
 use crate::{
     errors::AtomicResult,
     plugins::{
+        audit::audit_endpoint,
         files::upload_endpoint,
+        invite::invite_code_endpoint,
         path::path_endpoint,
+        permissions::permissions_endpoint,
         search::search_endpoint,
+        tokens::revoke_token_endpoint,
+        validate::validate_endpoint,
         versioning::{all_versions_endpoint, version_endpoint},
     },
     urls, Db, Resource, Storelike, Value,
@@ -58,5 +63,10 @@ pub fn default_endpoints() -> Vec<Endpoint> {
         path_endpoint(),
         search_endpoint(),
         upload_endpoint(),
+        validate_endpoint(),
+        revoke_token_endpoint(),
+        permissions_endpoint(),
+        audit_endpoint(),
+        invite_code_endpoint(),
     ]
 }
@@ -21,6 +21,8 @@ pub struct AtomicError {
 pub enum AtomicErrorType {
     NotFoundError,
     UnauthorizedError,
+    /// The Resource does not match its Schema. The message contains a JSON list of violations.
+    SchemaError,
     OtherError,
 }
 
@@ -66,6 +68,21 @@ impl AtomicError {
         }
     }
 
+    /// Creates an error with a JSON serialized `{"error": ..., "violations": [...]}` object as message, so clients can parse the [crate::validate::SchemaViolation]s.
+    pub fn schema_violations(
+        subject: &str,
+        violations: Vec<crate::validate::SchemaViolation>,
+    ) -> AtomicError {
+        let body = serde_json::json!({
+            "error": format!("Resource {} does not match its Schema.", subject),
+            "violations": violations,
+        });
+        AtomicError {
+            message: body.to_string(),
+            error_type: AtomicErrorType::SchemaError,
+        }
+    }
+
     pub fn other_error(message: String) -> AtomicError {
         AtomicError {
             message,
@@ -3,10 +3,14 @@
 //! See
 
 use core::fmt;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
 use crate::{errors::AtomicResult, urls, Resource, Storelike, Value};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Right {
     /// Full read access to the resource and its children.
     /// https://atomicdata.dev/properties/read
@@ -30,6 +34,17 @@ impl fmt::Display for Right {
     }
 }
 
+impl Right {
+    /// The Property that lists the Agents and Groups that are denied this right.
+    pub fn deny_property(&self) -> &str {
+        match self {
+            Right::Read => urls::DENY_READ,
+            Right::Write => urls::DENY_WRITE,
+            Right::Append => urls::DENY_APPEND,
+        }
+    }
+}
+
 /// Looks for children relations, adds to the resource. Performs a TPF query, might be expensive.
 pub fn add_children(store: &impl Storelike, resource: &mut Resource) -> AtomicResult<Resource> {
     let atoms = store.tpf(
@@ -54,7 +69,12 @@ pub fn check_write(
     resource: &Resource,
     for_agent: &str,
 ) -> AtomicResult<String> {
-    check_rights(store, resource, for_agent, Right::Write)
+    audit_denied(
+        store,
+        resource,
+        for_agent,
+        check_rights(store, resource, for_agent, Right::Write),
+    )
 }
 
 /// Does the Agent have the right to read / view the properties of the selected resource, or any of its parents?
@@ -65,7 +85,12 @@ pub fn check_read(
     resource: &Resource,
     for_agent: &str,
 ) -> AtomicResult<String> {
-    check_rights(store, resource, for_agent, Right::Read)
+    audit_denied(
+        store,
+        resource,
+        for_agent,
+        check_rights(store, resource, for_agent, Right::Read),
+    )
 }
 
 /// Does the Agent have the right to _append_ to its parent?
@@ -81,11 +106,120 @@ pub fn check_append(
     if let Ok(msg) = check_rights(store, &parent, for_agent, Right::Append) {
         Ok(msg)
     } else {
-        check_rights(store, resource, for_agent, Right::Write)
+        audit_denied(
+            store,
+            resource,
+            for_agent,
+            check_rights(store, resource, for_agent, Right::Write),
+        )
+    }
+}
+
+/// Adds failed rights checks to the audit log of the Store.
+/// Only the public check functions use this, internal lookups (e.g. listing the Agents with access) are not recorded.
+fn audit_denied(
+    store: &impl Storelike,
+    resource: &Resource,
+    for_agent: &str,
+    outcome: AtomicResult<String>,
+) -> AtomicResult<String> {
+    if let Err(e) = &outcome {
+        store.add_audit_entry(&crate::audit::AuditEntry::new(
+            crate::audit::AuditAction::Denied,
+            for_agent,
+            resource.get_subject(),
+            false,
+            Some(e.message.clone()),
+        ));
+    }
+    outcome
+}
+
+/// Returns the subjects of all Groups in the store, using the index.
+pub fn find_groups(store: &impl Storelike) -> AtomicResult<HashSet<String>> {
+    Ok(store
+        .tpf(
+            None,
+            Some(urls::IS_A),
+            Some(&Value::AtomicUrl(urls::GROUP.into())),
+            false,
+        )?
+        .into_iter()
+        .map(|atom| atom.subject)
+        .collect())
+}
+
+/// Finds out which Agents are members of a Group, including the members of nested Groups.
+/// Remembers the expanded Groups, since the same Groups are often used in many Resources of a hierarchy.
+#[derive(Default)]
+pub struct GroupResolver {
+    members: HashMap<String, HashSet<String>>,
+    /// Subjects of all Groups on this server. Found using the index, so Agents in rights arrays don't have to be loaded.
+    /// Comes from [Storelike::get_groups], which stores such as [crate::Db] keep in memory.
+    groups: Option<Arc<HashSet<String>>>,
+}
+
+impl GroupResolver {
+    /// Whether the Agent is a (nested) member of the Group.
+    /// Returns false if `group` is not a Group, or not stored on this server.
+    pub fn is_member(&mut self, store: &impl Storelike, group: &str, agent: &str) -> bool {
+        if !self.is_group(store, group) {
+            return false;
+        }
+        if !self.members.contains_key(group) {
+            let members = self.expand_group(store, group);
+            self.members.insert(group.into(), members);
+        }
+        self.members[group].contains(agent)
+    }
+
+    /// Whether the subject is a Group on this server.
+    pub fn is_group(&mut self, store: &impl Storelike, subject: &str) -> bool {
+        self.groups
+            .get_or_insert_with(|| store.get_groups().unwrap_or_default())
+            .contains(subject)
+    }
+
+    /// Returns the members of the Group and all its nested Groups.
+    /// Keeps track of visited Groups, so cycles (a Group that contains itself) are no problem.
+    fn expand_group(&mut self, store: &impl Storelike, group: &str) -> HashSet<String> {
+        let mut members = HashSet::new();
+        let mut visited = HashSet::new();
+        let mut todo = vec![group.to_string()];
+        while let Some(current) = todo.pop() {
+            if !visited.insert(current.clone()) || !self.is_group(store, &current) {
+                continue;
+            }
+            // Only local Resources, we don't want to fetch every external Agent in a rights array.
+            let resource = match store.get_resource_local(&current) {
+                Ok(r) => r,
+                Err(_) => continue,
+            };
+            if let Ok(group_members) = resource
+                .get(urls::GROUP_MEMBERS)
+                .and_then(|m| m.to_subjects(None))
+            {
+                for member in group_members {
+                    members.insert(member.clone());
+                    todo.push(member);
+                }
+            }
+        }
+        members
     }
 }
 
 /// Recursively checks a Resource and its Parents for rights.
+/// Agents can be granted rights directly, or through a Group that they are a member of.
+/// The nearest Resource that has something to say about the right decides, in this order:
+///
+/// 1. If the Agent is denied the right in the Resource (e.g. `denyRead`), the check fails.
+/// 2. If the Agent is granted the right in the Resource (e.g. `read`), the check succeeds.
+/// 3. If the Resource stops inheritance of the right (`stopInheritance`), the check fails.
+/// 4. Otherwise, the Parent is checked.
+///
+/// This means that a grant in a child overrides a deny in one of its parents.
+/// Stores that keep a rights cache (such as [crate::Db]) remember the outcome for parents.
 /// Throws if not allowed.
 /// Returns string with explanation if allowed.
 #[tracing::instrument(skip(store, resource))]
@@ -94,6 +228,22 @@ pub fn check_rights(
     resource: &Resource,
     for_agent: &str,
     right: Right,
+) -> AtomicResult<String> {
+    check_rights_with_groups(
+        store,
+        resource,
+        for_agent,
+        right,
+        &mut GroupResolver::default(),
+    )
+}
+
+fn check_rights_with_groups(
+    store: &impl Storelike,
+    resource: &Resource,
+    for_agent: &str,
+    right: Right,
+    groups: &mut GroupResolver,
 ) -> AtomicResult<String> {
     if resource.get_subject() == for_agent {
         return Ok("Agents can always edit themselves or their children.".into());
@@ -105,38 +255,49 @@ pub fn check_rights(
             Right::Read => {
                 // Commits can be read when their subject / target is readable.
                 let target = store.get_resource(&commit_subject.to_string())?;
-                check_rights(store, &target, for_agent, right)
+                check_rights_with_groups(store, &target, for_agent, right, groups)
             }
             Right::Write => Err("Commits cannot be edited.".into()),
             Right::Append => Err("Commits cannot have children, you cannot Append to them.".into()),
         };
     }
 
-    // Check if the resource's rights explicitly refers to the agent or the public agent
-    if let Ok(arr_val) = resource.get(&right.to_string()) {
-        for s in arr_val.to_subjects(None)? {
-            match s.as_str() {
-                urls::PUBLIC_AGENT => {
-                    return Ok(format!(
-                        "PublicAgent has been granted rights in {}",
-                        resource.get_subject()
-                    ))
-                }
-                agent => {
-                    if agent == for_agent {
-                        return Ok(format!(
-                            "Right has been explicitly set in {}",
-                            resource.get_subject()
-                        ));
-                    }
-                }
-            };
-        }
+    // Requests with a capability token are checked against the token and its issuer
+    if crate::tokens::is_token(for_agent) {
+        return crate::tokens::check_token_rights(store, resource, for_agent, right);
     }
 
-    // Try the parents recursively
+    if let Some((_property, outcome)) =
+        check_own_rights(store, resource, for_agent, &right, groups)?
+    {
+        return outcome;
+    }
+
+    // Try the parents recursively.
+    // Their outcome is cached, since many Resources share the same parents.
+    let right_url = right.to_string();
+    if let Ok(parent_subject) = resource.get(urls::PARENT) {
+        if let Some(cached) =
+            store.get_cached_rights(&parent_subject.to_string(), for_agent, &right_url)
+        {
+            return cached;
+        }
+    }
     if let Ok(parent) = resource.get_parent(store) {
-        check_rights(store, &parent, for_agent, right)
+        let outcome = check_rights_with_groups(store, &parent, for_agent, right, groups);
+        // The outcome for the parent depends on its own rights, and on the outcome for its own parent
+        let mut dependencies = vec![parent.get_subject().to_string()];
+        if let Ok(grandparent) = parent.get(urls::PARENT) {
+            dependencies.push(grandparent.to_string());
+        }
+        store.cache_rights(
+            parent.get_subject(),
+            for_agent,
+            &right_url,
+            &outcome,
+            &dependencies,
+        );
+        outcome
     } else {
         let for_string = if for_agent == urls::PUBLIC_AGENT {
             "the Public Agent".to_string()
@@ -151,6 +312,197 @@ pub fn check_rights(
     }
 }
 
+/// One of the Resources that [explain_rights] went through.
+#[derive(Clone, Debug)]
+pub struct RightsStep {
+    pub subject: String,
+    /// The Property of this Resource that decided the outcome, if it did.
+    pub property: Option<String>,
+}
+
+/// The outcome of [check_rights], including the Resources that led to it.
+pub struct RightsExplanation {
+    pub right: Right,
+    /// The explanation from [check_rights] if the right is granted, or the error if it is not.
+    pub outcome: AtomicResult<String>,
+    /// Starts with the checked Resource, ends with the one that decided (or the root of the hierarchy).
+    pub chain: Vec<RightsStep>,
+}
+
+/// Same as [check_rights], but also returns the chain of parents that was checked.
+/// Useful for finding out why an Agent does or does not have access to something.
+pub fn explain_rights(
+    store: &impl Storelike,
+    resource: &Resource,
+    for_agent: &str,
+    right: Right,
+) -> RightsExplanation {
+    let outcome = check_rights(store, resource, for_agent, right);
+    let mut chain: Vec<RightsStep> = Vec::new();
+    let mut groups = GroupResolver::default();
+    let mut current = Some(resource.clone());
+    while let Some(checked) = current.take() {
+        if chain
+            .iter()
+            .any(|step| &step.subject == checked.get_subject())
+        {
+            break;
+        }
+        let mut step = RightsStep {
+            subject: checked.get_subject().into(),
+            property: None,
+        };
+        // These are decided without looking at the rights properties
+        let special = checked.get_subject() == for_agent
+            || checked.get(urls::SUBJECT).is_ok()
+            || crate::tokens::is_token(for_agent);
+        if !special {
+            match check_own_rights(store, &checked, for_agent, &right, &mut groups) {
+                Ok(Some((property, _outcome))) => step.property = Some(property),
+                _ => current = checked.get_parent(store).ok(),
+            }
+        }
+        chain.push(step);
+    }
+    RightsExplanation {
+        right,
+        outcome,
+        chain,
+    }
+}
+
+/// Lists all Agents (including the Public Agent) that have at least one right for the Resource.
+/// Groups are replaced by their members.
+pub fn agents_with_access(
+    store: &impl Storelike,
+    resource: &Resource,
+) -> AtomicResult<Vec<String>> {
+    let all_rights = [Right::Read, Right::Write, Right::Append];
+    let mut groups = GroupResolver::default();
+    let mut candidates = std::collections::BTreeSet::new();
+    let mut visited = HashSet::new();
+    let mut current = Some(resource.clone());
+    while let Some(checked) = current.take() {
+        if !visited.insert(checked.get_subject().clone()) {
+            break;
+        }
+        for right in all_rights {
+            if let Ok(val) = checked.get(&right.to_string()) {
+                for subject in val.to_subjects(None)? {
+                    if groups.is_group(store, &subject) {
+                        candidates.extend(groups.expand_group(store, &subject));
+                    }
+                    candidates.insert(subject);
+                }
+            }
+        }
+        current = checked.get_parent(store).ok();
+    }
+    // Denials and stopped inheritance can take rights away, so check every candidate
+    let mut agents = Vec::new();
+    for candidate in candidates {
+        if groups.is_group(store, &candidate) {
+            continue;
+        }
+        if all_rights
+            .iter()
+            .any(|right| check_rights(store, resource, &candidate, *right).is_ok())
+        {
+            agents.push(candidate);
+        }
+    }
+    Ok(agents)
+}
+
+/// Checks what a single Resource says about the right, without looking at its parents.
+/// Returns the Property that decided and the outcome, or None if the parents should be checked.
+fn check_own_rights(
+    store: &impl Storelike,
+    resource: &Resource,
+    for_agent: &str,
+    right: &Right,
+    groups: &mut GroupResolver,
+) -> AtomicResult<Option<(String, AtomicResult<String>)>> {
+    // Denials take precedence over grants in the same resource
+    if let Ok(arr_val) = resource.get(right.deny_property()) {
+        for s in arr_val.to_subjects(None)? {
+            let reason = if s == urls::PUBLIC_AGENT {
+                Some("everyone".to_string())
+            } else if s == for_agent {
+                Some(s)
+            } else if groups.is_member(store, &s, for_agent) {
+                Some(format!("members of Group {}", s))
+            } else {
+                None
+            };
+            if let Some(reason) = reason {
+                return Ok(Some((
+                    right.deny_property().to_string(),
+                    Err(crate::errors::AtomicError::unauthorized(format!(
+                        "The {} right has been denied for {} in {}",
+                        right,
+                        reason,
+                        resource.get_subject()
+                    ))),
+                )));
+            }
+        }
+    }
+
+    // Check if the resource's rights explicitly refers to the agent or the public agent
+    if let Ok(arr_val) = resource.get(&right.to_string()) {
+        for s in arr_val.to_subjects(None)? {
+            match s.as_str() {
+                urls::PUBLIC_AGENT => {
+                    return Ok(Some((
+                        right.to_string(),
+                        Ok(format!(
+                            "PublicAgent has been granted rights in {}",
+                            resource.get_subject()
+                        )),
+                    )))
+                }
+                agent => {
+                    if agent == for_agent {
+                        return Ok(Some((
+                            right.to_string(),
+                            Ok(format!(
+                                "Right has been explicitly set in {}",
+                                resource.get_subject()
+                            )),
+                        )));
+                    }
+                    if groups.is_member(store, agent, for_agent) {
+                        return Ok(Some((
+                            right.to_string(),
+                            Ok(format!(
+                                "Right has been granted to Group {} in {}",
+                                agent,
+                                resource.get_subject()
+                            )),
+                        )));
+                    }
+                }
+            };
+        }
+    }
+
+    if let Ok(stopped) = resource.get(urls::STOP_INHERITANCE) {
+        if stopped.to_subjects(None)?.contains(&right.to_string()) {
+            return Ok(Some((
+                urls::STOP_INHERITANCE.to_string(),
+                Err(crate::errors::AtomicError::unauthorized(format!(
+                    "No {} right has been found for {} in {}, which does not inherit this right from its parents",
+                    right,
+                    for_agent,
+                    resource.get_subject()
+                ))),
+            )));
+        }
+    }
+    Ok(None)
+}
+
 #[cfg(test)]
 mod test {
     // use super::*;
@@ -182,6 +534,137 @@ mod test {
         // assert!(resource.get(property).unwrap().to_string() == value.to_string());
     }
 
+    #[test]
+    fn group_rights() {
+        let store = crate::Store::init().unwrap();
+        store.populate().unwrap();
+        let member = store.create_agent(Some("member")).unwrap();
+        let stranger = store.create_agent(Some("stranger")).unwrap();
+        let group_a = "http://localhost/groups/a";
+        let group_b = "http://localhost/groups/b";
+        let add_group = |subject: &str, members: Vec<&str>| {
+            let mut group = crate::Resource::new(subject.into());
+            group
+                .set_propval(
+                    crate::urls::IS_A.into(),
+                    vec![crate::urls::GROUP].into(),
+                    &store,
+                )
+                .unwrap();
+            group
+                .set_propval(crate::urls::GROUP_MEMBERS.into(), members.into(), &store)
+                .unwrap();
+            store.add_resource(&group).unwrap();
+        };
+        // The groups contain each other, which should not cause an infinite loop
+        add_group(group_a, vec![group_b]);
+        add_group(group_b, vec![group_a, member.subject.as_str()]);
+
+        let mut resource = crate::Resource::new("http://localhost/shared".into());
+        resource
+            .set_propval(crate::urls::READ.into(), vec![group_a].into(), &store)
+            .unwrap();
+        store.add_resource(&resource).unwrap();
+
+        let explanation = super::check_read(&store, &resource, &member.subject).unwrap();
+        assert!(explanation.contains(group_a), "{}", explanation);
+        super::check_read(&store, &resource, &stranger.subject).unwrap_err();
+        super::check_write(&store, &resource, &member.subject).unwrap_err();
+    }
+
+    #[test]
+    fn deny_and_stop_inheritance() {
+        use crate::urls;
+
+        let store = crate::Store::init().unwrap();
+        store.populate().unwrap();
+        let owner = store.create_agent(Some("owner")).unwrap();
+        let stranger = store.create_agent(Some("stranger")).unwrap();
+        let add = |subject: &str, parent: Option<&str>, props: Vec<(&str, Vec<&str>)>| {
+            let mut resource = crate::Resource::new(subject.into());
+            if let Some(parent) = parent {
+                resource
+                    .set_propval(urls::PARENT.into(), Value::AtomicUrl(parent.into()), &store)
+                    .unwrap();
+            }
+            for (prop, val) in props {
+                resource
+                    .set_propval(prop.into(), val.into(), &store)
+                    .unwrap();
+            }
+            store.add_resource(&resource).unwrap();
+            resource
+        };
+        let public = add(
+            "http://localhost/public",
+            None,
+            vec![(urls::READ, vec![urls::PUBLIC_AGENT])],
+        );
+        let private = add(
+            "http://localhost/public/private",
+            Some(public.get_subject()),
+            vec![
+                (urls::STOP_INHERITANCE, vec![urls::READ]),
+                (urls::READ, vec![owner.subject.as_str()]),
+            ],
+        );
+        let private_child = add(
+            "http://localhost/public/private/child",
+            Some(private.get_subject()),
+            vec![],
+        );
+        let denied = add(
+            "http://localhost/public/denied",
+            Some(public.get_subject()),
+            vec![(urls::DENY_READ, vec![stranger.subject.as_str()])],
+        );
+        let overridden = add(
+            "http://localhost/public/denied/overridden",
+            Some(denied.get_subject()),
+            vec![(urls::READ, vec![stranger.subject.as_str()])],
+        );
+
+        super::check_read(&store, &public, &stranger.subject).unwrap();
+        let explanation = super::check_read(&store, &private_child, &owner.subject).unwrap();
+        assert!(
+            explanation.contains(private.get_subject()),
+            "{}",
+            explanation
+        );
+        let err = super::check_read(&store, &private_child, &stranger.subject).unwrap_err();
+        assert!(err.to_string().contains(private.get_subject()), "{}", err);
+
+        let err = super::check_read(&store, &denied, &stranger.subject).unwrap_err();
+        assert!(err.to_string().contains(denied.get_subject()), "{}", err);
+        super::check_read(&store, &denied, &owner.subject).unwrap();
+        // A grant in a child overrides a deny in its parent
+        super::check_read(&store, &overridden, &stranger.subject).unwrap();
+
+        let explanation = super::explain_rights(
+            &store,
+            &private_child,
+            &stranger.subject,
+            super::Right::Read,
+        );
+        assert!(explanation.outcome.is_err());
+        let chain: Vec<(&str, Option<&str>)> = explanation
+            .chain
+            .iter()
+            .map(|step| (step.subject.as_str(), step.property.as_deref()))
+            .collect();
+        assert_eq!(
+            chain,
+            vec![
+                (private_child.get_subject().as_str(), None),
+                (private.get_subject().as_str(), Some(urls::STOP_INHERITANCE)),
+            ]
+        );
+        assert_eq!(
+            super::agents_with_access(&store, &private_child).unwrap(),
+            vec![owner.subject.clone()]
+        );
+    }
+
     #[test]
     fn display_right() {
         let read = super::Right::Read;
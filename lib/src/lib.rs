@@ -60,12 +60,14 @@ assert!(fetched_new_resource.get_shortname("description", &store).unwrap().to_st
 
 pub mod agents;
 pub mod atoms;
+pub mod audit;
 pub mod authentication;
 pub mod client;
 pub mod collections;
 pub mod commit;
 #[cfg(feature = "config")]
 pub mod config;
+pub mod csv;
 pub mod datatype;
 #[cfg(feature = "db")]
 pub mod db;
@@ -81,10 +83,12 @@ pub mod populate;
 pub mod resources;
 pub mod schema;
 pub mod serialize;
+pub mod sessions;
 pub mod store;
 pub mod storelike;
 #[cfg(test)]
 mod test_utils;
+pub mod tokens;
 pub mod urls;
 pub mod utils;
 pub mod validate;
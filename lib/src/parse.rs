@@ -1,8 +1,8 @@
 //! Parsing / deserialization / decoding
 
 use crate::{
-    errors::AtomicResult, resources::PropVals, urls, values::SubResource, Resource, Storelike,
-    Value,
+    datatype::DataType, errors::AtomicResult, resources::PropVals, urls, values::SubResource,
+    Resource, Storelike, Value,
 };
 
 pub const JSON_AD_MIME: &str = "application/ad+json";
@@ -121,19 +121,43 @@ pub fn parse_json_ad_map_to_propvals(
             };
             continue;
         }
+        // Only fetched once per key, since unknown Properties are fetched from the web.
+        // Booleans, arrays and objects don't need a known Property.
+        let datatype = store.get_property(&prop).map(|p| p.data_type);
+        let is_json = matches!(datatype, Ok(DataType::Json));
+        // Textual Properties can be translated using a LangString
+        let is_text = matches!(
+            datatype,
+            Ok(DataType::String | DataType::Markdown | DataType::LangString)
+        );
         let atomic_val = match val {
             serde_json::Value::Null => return Err("Null not allowed in JSON-AD".into()),
+            serde_json::Value::Bool(bool) if is_json => Value::Json(bool.to_string()),
             serde_json::Value::Bool(bool) => Value::Boolean(bool),
             serde_json::Value::Number(num) => {
-                let property = store.get_property(&prop)?;
                 // Also converts numbers to strings, not sure what to think about this.
                 // Does not result in invalid atomic data, but does allow for weird inputs
-                Value::new(&num.to_string(), &property.data_type)?
+                Value::new(&num.to_string(), &datatype?)?
             }
             serde_json::Value::String(str) => {
-                let property = store.get_property(&prop)?;
-                Value::new(&str.to_string(), &property.data_type)?
+                if is_json {
+                    Value::Json(serde_json::Value::String(str).to_string())
+                } else {
+                    Value::new(&str.to_string(), &datatype?)?
+                }
+            }
+            // Arrays and Objects are only Resources if the Property does not expect raw JSON
+            serde_json::Value::Array(arr) if is_json => {
+                Value::Json(serde_json::Value::Array(arr).to_string())
+            }
+            serde_json::Value::Object(map) if is_json => {
+                Value::Json(serde_json::Value::Object(map).to_string())
             }
+            // Objects for textual Properties are Language Maps, e.g. `{"en": "Hello", "nl": "Hallo"}`
+            serde_json::Value::Object(map) if is_text => Value::new(
+                &serde_json::Value::Object(map).to_string(),
+                &DataType::LangString,
+            )?,
             // In Atomic Data, all arrays are Resource Arrays which are serialized JSON things.
             // Maybe this step could be simplified? Just serialize to string?
             serde_json::Value::Array(arr) => {
@@ -307,4 +331,53 @@ mod test {
         ];
         assert_eq!(members, should_be);
     }
+
+    #[test]
+    fn parse_json_datatype_roundtrip() {
+        let store = crate::Store::init().unwrap();
+        store.populate().unwrap();
+        let property = crate::schema::Property {
+            class_type: None,
+            data_type: DataType::Json,
+            shortname: "settings".into(),
+            description: "Arbitrary JSON settings".into(),
+            subject: "https://localhost/properties/settings".into(),
+            allows_only: None,
+            constraints: Default::default(),
+        };
+        store.add_resource(&property.to_resource()).unwrap();
+
+        let json = r#"{
+            "@id": "https://localhost/thingWithJson",
+            "https://localhost/properties/settings": {"theme": "dark", "sizes": [1, 2]}
+          }"#;
+        let parsed = parse_json_ad_resource(json, &store).unwrap();
+        let value = parsed.get(&property.subject).unwrap();
+        assert_eq!(value.datatype(), DataType::Json);
+        let serialized: serde_json::Value =
+            serde_json::from_str(&parsed.to_json_ad().unwrap()).unwrap();
+        let input: serde_json::Value = serde_json::from_str(json).unwrap();
+        assert_eq!(serialized, input);
+    }
+
+    #[test]
+    fn parse_lang_string_roundtrip() {
+        let store = crate::Store::init().unwrap();
+        store.populate().unwrap();
+        let json = r#"{
+            "@id": "https://localhost/translated",
+            "https://atomicdata.dev/properties/name": {"en": "Hello", "nl": "Hallo"}
+          }"#;
+        let parsed = parse_json_ad_resource(json, &store).unwrap();
+        let name = parsed.get(urls::NAME).unwrap();
+        assert_eq!(name.datatype(), DataType::LangString);
+        let serialized: serde_json::Value =
+            serde_json::from_str(&parsed.to_json_ad().unwrap()).unwrap();
+        let input: serde_json::Value = serde_json::from_str(json).unwrap();
+        assert_eq!(serialized, input);
+        let json_ld: serde_json::Value =
+            serde_json::from_str(&parsed.to_json_ld(&store).unwrap()).unwrap();
+        assert_eq!(json_ld["@context"]["name"]["@container"], "@language");
+        assert_eq!(json_ld["name"]["nl"], "Hallo");
+    }
 }
@@ -0,0 +1,72 @@
+//! Shows the entries of the audit log, see [crate::audit].
+//! Only available to Agents that have write rights for the Drive.
+
+use crate::{
+    endpoints::Endpoint, errors::AtomicResult, urls, values::SubResource, Db, Resource, Storelike,
+    Value,
+};
+
+/// The amount of entries that are returned if no `limit` is passed.
+const DEFAULT_LIMIT: usize = 100;
+
+pub fn audit_endpoint() -> Endpoint {
+    Endpoint {
+        path: "/audit".to_string(),
+        params: [
+            urls::AUDIT_FROM.to_string(),
+            urls::AUDIT_TO.to_string(),
+            urls::AUDIT_LIMIT.to_string(),
+        ]
+        .into(),
+        description: "Lists the entries of the audit log, such as denied rights checks, accepted Invites, new Agents, uploads, downloads and destroyed Resources. Oldest entries come first. Filter by time using `from` and `to` (Unix timestamps in milliseconds). Requires write rights for the Drive.".to_string(),
+        shortname: "audit".to_string(),
+        handle: Some(handle_audit_request),
+    }
+}
+
+#[tracing::instrument(skip(store))]
+fn handle_audit_request(
+    url: url::Url,
+    store: &Db,
+    for_agent: Option<&str>,
+) -> AtomicResult<Resource> {
+    let mut from = None;
+    let mut to = None;
+    let mut limit = DEFAULT_LIMIT;
+    for (k, v) in url.query_pairs() {
+        match k.as_ref() {
+            "from" => {
+                from = Some(
+                    v.parse::<i64>()
+                        .map_err(|e| format!("Invalid `from`. {}", e))?,
+                )
+            }
+            "to" => {
+                to = Some(
+                    v.parse::<i64>()
+                        .map_err(|e| format!("Invalid `to`. {}", e))?,
+                )
+            }
+            "limit" => limit = v.parse().map_err(|e| format!("Invalid `limit`. {}", e))?,
+            _ => {}
+        };
+    }
+    if let Some(agent) = for_agent {
+        let drive = store.get_resource(store.get_server_url())?;
+        crate::hierarchy::check_write(store, &drive, agent)?;
+    }
+    let entries: Vec<SubResource> = store
+        .get_audit_entries(from, to, limit)?
+        .iter()
+        .map(|entry| SubResource::Nested(entry.to_propvals()))
+        .collect();
+
+    let mut resource = audit_endpoint().to_resource(store)?;
+    resource.set_subject(url.to_string());
+    resource.set_propval(
+        urls::AUDIT_ENTRIES.into(),
+        Value::ResourceArray(entries),
+        store,
+    )?;
+    Ok(resource)
+}
@@ -1,4 +1,6 @@
-use crate::{endpoints::Endpoint, urls};
+use std::collections::HashMap;
+
+use crate::{endpoints::Endpoint, errors::AtomicResult, urls, Resource, Storelike};
 
 pub fn upload_endpoint() -> Endpoint {
     Endpoint {
@@ -9,3 +11,186 @@ pub fn upload_endpoint() -> Endpoint {
         handle: None,
     }
 }
+
+/// Returns the subject of the Drive that contains the Resource (or the Resource itself, if it is a Drive), by walking up its parents.
+pub fn find_drive(subject: &str, store: &impl Storelike) -> AtomicResult<Option<String>> {
+    find_drive_cached(subject, store, &mut HashMap::new())
+}
+
+/// Remembers the Drive of every visited Resource, so siblings don't have to walk up the hierarchy again.
+pub fn find_drive_cached(
+    subject: &str,
+    store: &impl Storelike,
+    cache: &mut HashMap<String, Option<String>>,
+) -> AtomicResult<Option<String>> {
+    let mut visited = Vec::new();
+    let mut current = subject.to_string();
+    let drive = loop {
+        if let Some(drive) = cache.get(&current) {
+            break drive.clone();
+        }
+        // Prevents endless loops in circular hierarchies
+        if visited.contains(&current) {
+            break None;
+        }
+        // Drives are always local, so external parents don't have to be fetched
+        let resource = match store.get_resource_local(&current) {
+            Ok(resource) => resource,
+            Err(_) => break None,
+        };
+        let is_drive = resource
+            .get(urls::IS_A)
+            .and_then(|classes| classes.to_subjects(None))
+            .map(|classes| classes.iter().any(|c| c == urls::DRIVE))
+            .unwrap_or(false);
+        if is_drive {
+            break Some(current);
+        }
+        visited.push(current);
+        match resource.get(urls::PARENT) {
+            Ok(parent) => current = parent.to_string(),
+            Err(_) => break None,
+        }
+    };
+    for subject in visited {
+        cache.insert(subject, drive.clone());
+    }
+    Ok(drive)
+}
+
+/// Returns the total `filesize` of all Files in the Drive, in bytes.
+pub fn drive_usage(drive: &str, store: &impl Storelike) -> AtomicResult<i64> {
+    let mut cache = HashMap::new();
+    let mut usage = 0;
+    for atom in store.tpf(None, Some(urls::FILESIZE), None, false)? {
+        if find_drive_cached(&atom.subject, store, &mut cache)?.as_deref() == Some(drive) {
+            usage += atom.value.to_int()?;
+        }
+    }
+    Ok(usage)
+}
+
+/// Updates the usage counter of the Drive when a File is uploaded, changed, moved or destroyed.
+/// `resource_new` is None when the File is destroyed.
+pub fn after_apply_commit(
+    store: &impl Storelike,
+    resource_old: Option<&Resource>,
+    resource_new: Option<&Resource>,
+) -> AtomicResult<()> {
+    let old = match resource_old {
+        Some(resource) => file_drive_and_size(store, resource)?,
+        None => None,
+    };
+    let new = match resource_new {
+        Some(resource) => file_drive_and_size(store, resource)?,
+        None => None,
+    };
+    if old == new {
+        return Ok(());
+    }
+    if let Some((drive, size)) = old {
+        store.add_drive_usage(&drive, -size)?;
+    }
+    if let Some((drive, size)) = new {
+        store.add_drive_usage(&drive, size)?;
+    }
+    Ok(())
+}
+
+/// Returns the Drive and `filesize` of a File, or None for other Resources.
+/// Uses the parent, since destroyed Files are no longer in the Store.
+fn file_drive_and_size(
+    store: &impl Storelike,
+    resource: &Resource,
+) -> AtomicResult<Option<(String, i64)>> {
+    let size = match resource.get(urls::FILESIZE) {
+        Ok(size) => size.to_int()?,
+        Err(_) => return Ok(None),
+    };
+    let parent = match resource.get(urls::PARENT) {
+        Ok(parent) => parent.to_string(),
+        Err(_) => return Ok(None),
+    };
+    Ok(find_drive(&parent, store)?.map(|drive| (drive, size)))
+}
+
+/// Calculates the SHA-256 hash of a file, while it is being uploaded or read in chunks.
+/// Uploaded files are stored by their hash, so identical files are only stored once.
+pub struct FileHasher {
+    context: ring::digest::Context,
+}
+
+/// The result of a [FileHasher].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileHash {
+    /// Base64 encoded hash, used as the [urls::CHECKSUM] of the File.
+    pub checksum: String,
+    /// Hex encoded hash, used as the [urls::INTERNAL_ID] and filename of the stored blob.
+    pub blob_id: String,
+}
+
+impl FileHasher {
+    pub fn new() -> FileHasher {
+        FileHasher {
+            context: ring::digest::Context::new(&ring::digest::SHA256),
+        }
+    }
+
+    pub fn update(&mut self, chunk: &[u8]) {
+        self.context.update(chunk);
+    }
+
+    pub fn finish(self) -> FileHash {
+        let digest = self.context.finish();
+        FileHash {
+            checksum: base64::encode(digest.as_ref()),
+            blob_id: digest
+                .as_ref()
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect(),
+        }
+    }
+}
+
+impl Default for FileHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the [urls::INTERNAL_ID]s of all Files, which are the names of the stored blobs that are still in use, with their [urls::CHECKSUM].
+/// Files that were uploaded before checksums were added have no checksum.
+pub fn referenced_blobs(store: &impl Storelike) -> AtomicResult<HashMap<String, Option<String>>> {
+    let mut checksums: HashMap<String, String> = store
+        .tpf(None, Some(urls::CHECKSUM), None, false)?
+        .into_iter()
+        .map(|atom| (atom.subject, atom.value.to_string()))
+        .collect();
+    Ok(store
+        .tpf(None, Some(urls::INTERNAL_ID), None, false)?
+        .into_iter()
+        .map(|atom| (atom.value.to_string(), checksums.remove(&atom.subject)))
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn hash_in_chunks() {
+        let mut hasher = FileHasher::new();
+        hasher.update(b"hello ");
+        hasher.update(b"world");
+        let hash = hasher.finish();
+        assert_eq!(
+            hash.blob_id,
+            "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+        );
+        assert_eq!(
+            hash.checksum,
+            "uU0nuZNNPgilLlLX2n2r+sSE7+N6U4DukIj3rOLvzek="
+        );
+    }
+}
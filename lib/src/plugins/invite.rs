@@ -1,5 +1,14 @@
+//! Invites share a link (or a short code) that grants the visitor rights on a target Resource, or membership of a Group.
+//! Every acceptance is recorded on the Invite.
+
 use crate::{
-    agents::Agent, errors::AtomicResult, urls, utils::check_valid_url, Resource, Storelike, Value,
+    agents::Agent,
+    audit::{AuditAction, AuditEntry},
+    endpoints::Endpoint,
+    errors::{AtomicError, AtomicResult},
+    urls,
+    utils::check_valid_url,
+    Db, Resource, Storelike, Value,
 };
 
 /// If there is a valid Agent in the correct query param, and the invite is valid, update the rights and respond with a redirect to the target resource
@@ -33,12 +42,19 @@ pub fn construct_invite_redirect(
                 Ok(_found) => {}
                 Err(_) => {
                     new_agent.to_resource(store)?.save_locally(store)?;
+                    store.add_audit_entry(&AuditEntry::new(
+                        AuditAction::CreateAgent,
+                        &new_agent.subject,
+                        &new_agent.subject,
+                        true,
+                        Some(format!("Created by accepting Invite {}", requested_subject)),
+                    ));
                 }
             };
 
             // Always add write rights to the agent itself
             // A bit inefficient, since it re-fetches the agent from the store, but it's not that big of a cost
-            add_rights(&new_agent.subject, &new_agent.subject, true, store)?;
+            add_rights(&new_agent.subject, &new_agent.subject, urls::WRITE, store)?;
             new_agent.subject
         }
         (Some(_), Some(_)) => {
@@ -46,12 +62,17 @@ pub fn construct_invite_redirect(
         }
     };
 
-    // If there are write or read rights
-    let write = if let Ok(bool) = invite_resource.get(urls::WRITE_BOOL) {
-        bool.to_bool()?
-    } else {
-        false
-    };
+    if let Ok(revoked) = invite_resource.get(urls::INVITE_REVOKED) {
+        if revoked.to_bool()? {
+            return Err("This Invite has been revoked".into());
+        }
+    }
+
+    if let Ok(expires) = invite_resource.get(urls::EXPIRES_AT) {
+        if expires.to_int()? <= crate::utils::now() {
+            return Err("Invite is no longer valid".into());
+        }
+    }
 
     let target = &invite_resource
         .get(urls::TARGET)
@@ -64,39 +85,80 @@ pub fn construct_invite_redirect(
         })?
         .to_string();
 
+    let role = get_role(invite_resource)?;
+    let group = invite_resource
+        .get(urls::INVITE_GROUP)
+        .ok()
+        .map(|g| g.to_string());
+
+    // Since the requested subject might have query params, we don't want to overwrite that one - we want to overwrite the clean resource.
+    let mut url = url::Url::parse(&requested_subject)?;
+    url.set_query(None);
+    invite_resource.set_subject(url.to_string());
+
     // If any usages left value is present, make sure it's a positive number and decrement it by 1.
     if let Ok(usages_left) = invite_resource.get(urls::USAGES_LEFT) {
         let num = usages_left.to_int()?;
-        if num == 0 {
+        if num <= 0 {
             return Err("No usages left for this invite".into());
         }
-        // Since the requested subject might have query params, we don't want to overwrite that one - we want to overwrite the clean resource.
-        let mut url = url::Url::parse(&requested_subject)?;
-        url.set_query(None);
-        invite_resource.set_subject(url.to_string());
         invite_resource.set_propval(urls::USAGES_LEFT.into(), Value::Integer(num - 1), store)?;
-        invite_resource
-            .save_locally(store)
-            .map_err(|e| format!("Unable to save updated Invite. {}", e))?;
-    }
-
-    if let Ok(expires) = invite_resource.get(urls::EXPIRES_AT) {
-        if expires.to_int()? > crate::utils::now() {
-            return Err("Invite is no longer valid".into());
-        }
     }
 
-    // Make sure the creator of the invite is still allowed to Write the target
-    let invite_creator =
-        crate::plugins::versioning::get_initial_commit_for_resource(target, store)?.signer;
+    // Make sure the creator of the invite is still allowed to Write the target (or the group)
+    let invite_creator = crate::plugins::versioning::get_initial_commit_for_resource(
+        invite_resource.get_subject(),
+        store,
+    )?
+    .signer;
     crate::hierarchy::check_write(store, &store.get_resource(target)?, &invite_creator)
         .map_err(|e| format!("Invite creator is not allowed to write the target. {}", e))?;
 
-    add_rights(&agent, target, write, store)?;
-    if write {
-        // Also add read rights
-        add_rights(&agent, target, false, store)?;
-    }
+    let granted = match &group {
+        Some(group) => {
+            let mut group_resource = store.get_resource(group)?;
+            crate::hierarchy::check_write(store, &group_resource, &invite_creator)
+                .map_err(|e| format!("Invite creator is not allowed to write the group. {}", e))?;
+            group_resource.push_propval(urls::GROUP_MEMBERS, agent.clone().into(), true, store)?;
+            group_resource
+                .save_locally(store)
+                .map_err(|e| format!("Unable to save updated Group. {}", e))?;
+            format!("membership of Group {}", group)
+        }
+        None => {
+            add_rights(&agent, target, role, store)?;
+            if role != urls::READ {
+                // Also add read rights
+                add_rights(&agent, target, urls::READ, store)?;
+            }
+            format!("{} rights", role_name(role))
+        }
+    };
+
+    // Record who accepted the invite, and when
+    let mut acceptance = crate::resources::PropVals::new();
+    acceptance.insert(urls::INVITE_AGENT.into(), Value::AtomicUrl(agent.clone()));
+    acceptance.insert(
+        urls::CREATED_AT.into(),
+        Value::Timestamp(crate::utils::now()),
+    );
+    invite_resource.push_propval(urls::INVITE_ACCEPTANCES, acceptance.into(), false, store)?;
+    invite_resource.push_propval(urls::USED_BY, agent.clone().into(), true, store)?;
+    invite_resource
+        .save_locally(store)
+        .map_err(|e| format!("Unable to save updated Invite. {}", e))?;
+
+    store.add_audit_entry(&AuditEntry::new(
+        AuditAction::AcceptInvite,
+        &agent,
+        target,
+        true,
+        Some(format!(
+            "Accepted Invite {} with {}",
+            invite_resource.get_subject(),
+            granted
+        )),
+    ));
 
     // Construct the Redirect Resource, which might provide the Client with a Subject for his Agent.
     let mut redirect = Resource::new_instance(urls::REDIRECT, store)?;
@@ -115,7 +177,7 @@ pub fn construct_invite_redirect(
     Ok(redirect)
 }
 
-/// Adds the requested rights to the target resource.
+/// Adds the requested right (e.g. [urls::WRITE]) to the target resource.
 /// Overwrites the target resource to include the new rights.
 /// Checks if the Agent has a valid URL.
 /// Will not throw an error if the Agent already has the rights.
@@ -123,13 +185,12 @@ pub fn construct_invite_redirect(
 pub fn add_rights(
     agent: &str,
     target: &str,
-    write: bool,
+    right: &str,
     store: &impl Storelike,
 ) -> AtomicResult<()> {
     check_valid_url(agent)?;
     // Get the Resource that the user is being invited to
     let mut target = store.get_resource(target)?;
-    let right = if write { urls::WRITE } else { urls::READ };
     let mut rights_vector: Vec<String> = match target.get(right) {
         // Rights have been set, add to the list
         Ok(val) => {
@@ -158,7 +219,106 @@ pub fn add_rights(
     Ok(())
 }
 
-/// Check if the creator has rights to invite people (= write) to the target resource
+/// Returns the right that the Invite grants on its target.
+/// Uses the `role` if present, and falls back to the `write` boolean.
+fn get_role(invite: &Resource) -> AtomicResult<&'static str> {
+    if let Ok(role) = invite.get(urls::INVITE_ROLE) {
+        return match role.to_string().as_str() {
+            "read" => Ok(urls::READ),
+            "append" => Ok(urls::APPEND),
+            "write" => Ok(urls::WRITE),
+            other => Err(format!(
+                "Invalid Invite role `{}`, must be `read`, `append` or `write`",
+                other
+            )
+            .into()),
+        };
+    }
+    match invite.get(urls::WRITE_BOOL) {
+        Ok(write) if write.to_bool()? => Ok(urls::WRITE),
+        _ => Ok(urls::READ),
+    }
+}
+
+fn role_name(right: &str) -> &str {
+    right.rsplit('/').next().unwrap_or(right)
+}
+
+/// Normalizes a typed Invite code: uppercase, without surrounding whitespace.
+pub fn normalize_code(code: &str) -> String {
+    code.trim().to_uppercase()
+}
+
+/// Creates a random Invite code that is easy to type, e.g. `K7QF-2MZD`.
+pub fn generate_invite_code() -> String {
+    let random = crate::utils::random_string(8).to_uppercase();
+    format!("{}-{}", &random[..4], &random[4..])
+}
+
+/// Throws if the code is not made of uppercase letters, numbers and dashes, with at least 8 letters or numbers.
+fn check_code(code: &str) -> AtomicResult<()> {
+    let valid_chars = code
+        .chars()
+        .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '-');
+    let length = code.chars().filter(|c| *c != '-').count();
+    if !valid_chars || length < 8 {
+        return Err(format!(
+            "Invite code `{}` is invalid. Use at least 8 uppercase letters or numbers, optionally separated by dashes.",
+            code
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Returns the subject of the Invite with this code.
+pub fn find_invite_by_code(code: &str, store: &impl Storelike) -> AtomicResult<String> {
+    let code = Value::String(normalize_code(code));
+    let atoms = store.tpf(None, Some(urls::INVITE_CODE), Some(&code), false)?;
+    match atoms.first() {
+        Some(atom) => Ok(atom.subject.clone()),
+        None => Err(AtomicError::not_found(format!(
+            "No Invite found with code {}",
+            code
+        ))),
+    }
+}
+
+pub fn invite_code_endpoint() -> Endpoint {
+    Endpoint {
+        path: "/invite-code".to_string(),
+        params: [urls::INVITE_CODE.to_string()].into(),
+        description: "Finds the Invite that has a short code, and redirects to it. Codes are case insensitive.".to_string(),
+        shortname: "invite-code".to_string(),
+        handle: Some(handle_invite_code_request),
+    }
+}
+
+#[tracing::instrument(skip(store, url))]
+fn handle_invite_code_request(
+    url: url::Url,
+    store: &Db,
+    _for_agent: Option<&str>,
+) -> AtomicResult<Resource> {
+    let mut code = None;
+    for (k, v) in url.query_pairs() {
+        if let "code" | urls::INVITE_CODE = k.as_ref() {
+            code = Some(v.to_string())
+        };
+    }
+    let code = match code {
+        Some(code) => code,
+        None => return invite_code_endpoint().to_resource(store),
+    };
+    let invite = find_invite_by_code(&code, store)?;
+    let mut redirect = Resource::new_instance(urls::REDIRECT, store)?;
+    redirect.set_propval(urls::DESTINATION.into(), Value::AtomicUrl(invite), store)?;
+    redirect.set_subject(url.to_string());
+    Ok(redirect)
+}
+
+/// Check if the creator has rights to invite people (= write) to the target resource (and the group),
+/// and if the role and code are valid.
 pub fn before_apply_commit(
     store: &impl Storelike,
     commit: &crate::Commit,
@@ -169,5 +329,27 @@ pub fn before_apply_commit(
         .map_err(|_e| "Invite does not have required Target attribute")?;
     let target_resource = store.get_resource(&target.to_string())?;
     crate::hierarchy::check_write(store, &target_resource, &commit.signer)?;
+    get_role(resource_new)?;
+    if let Ok(group) = resource_new.get(urls::INVITE_GROUP) {
+        let group_resource = store.get_resource(&group.to_string())?;
+        crate::hierarchy::check_write(store, &group_resource, &commit.signer)
+            .map_err(|e| format!("Not allowed to invite people to this Group. {}", e))?;
+    }
+    if let Ok(code) = resource_new.get(urls::INVITE_CODE) {
+        let code = code.to_string();
+        check_code(&code)?;
+        let in_use = store
+            .tpf(
+                None,
+                Some(urls::INVITE_CODE),
+                Some(&Value::String(code.clone())),
+                false,
+            )?
+            .iter()
+            .any(|atom| &atom.subject != resource_new.get_subject());
+        if in_use {
+            return Err(format!("Invite code {} is already in use", code).into());
+        }
+    }
     Ok(())
 }
@@ -38,7 +38,11 @@ pub mod chatroom;
 pub mod invite;
 
 // Endpoints
+pub mod audit;
 pub mod files;
 pub mod path;
+pub mod permissions;
 pub mod search;
+pub mod tokens;
+pub mod validate;
 pub mod versioning;
@@ -0,0 +1,102 @@
+//! Shows the effective rights of an Agent for a Resource, and why it has them.
+//! Only available to Agents that have write rights for the Resource.
+
+use crate::{
+    endpoints::Endpoint,
+    errors::AtomicResult,
+    hierarchy::{agents_with_access, explain_rights, Right},
+    resources::PropVals,
+    urls,
+    values::SubResource,
+    Resource, Storelike, Value,
+};
+
+pub fn permissions_endpoint() -> Endpoint {
+    Endpoint {
+        path: "/permissions".to_string(),
+        params: [urls::SUBJECT.to_string(), urls::PERMISSIONS_AGENT.to_string()].into(),
+        description: "Shows the effective `read`, `write` and `append` rights of an Agent for a Resource, and which Resources in the hierarchy decided them. Also lists all Agents that have access to the Resource. Requires write rights for the Resource.".to_string(),
+        shortname: "permissions".to_string(),
+        handle: Some(handle_permissions_request),
+    }
+}
+
+#[tracing::instrument(skip(store))]
+fn handle_permissions_request(
+    url: url::Url,
+    store: &impl Storelike,
+    for_agent: Option<&str>,
+) -> AtomicResult<Resource> {
+    let mut target_subject = None;
+    let mut agent = None;
+    for (k, v) in url.query_pairs() {
+        match k.as_ref() {
+            "subject" => target_subject = Some(v.to_string()),
+            "agent" => agent = Some(v.to_string()),
+            _ => {}
+        };
+    }
+    let target_subject = match target_subject {
+        Some(subject) => subject,
+        None => return permissions_endpoint().to_resource(store),
+    };
+    let target = store.get_resource(&target_subject)?;
+    if let Some(requester) = for_agent {
+        crate::hierarchy::check_write(store, &target, requester)?;
+    }
+    let agent = agent
+        .or_else(|| for_agent.map(|a| a.to_string()))
+        .unwrap_or_else(|| urls::PUBLIC_AGENT.to_string());
+
+    let mut rights = Vec::new();
+    for right in [Right::Read, Right::Write, Right::Append] {
+        let explanation = explain_rights(store, &target, &agent, right);
+        let chain = explanation
+            .chain
+            .into_iter()
+            .map(|step| {
+                let mut propvals = PropVals::new();
+                propvals.insert(urls::ATOM_SUBJECT.into(), Value::AtomicUrl(step.subject));
+                if let Some(property) = step.property {
+                    propvals.insert(urls::ATOM_PROPERTY.into(), Value::AtomicUrl(property));
+                }
+                SubResource::Nested(propvals)
+            })
+            .collect();
+        let mut propvals = PropVals::new();
+        propvals.insert(
+            urls::PERMISSIONS_RIGHT.into(),
+            Value::AtomicUrl(right.to_string()),
+        );
+        propvals.insert(
+            urls::PERMISSIONS_ALLOWED.into(),
+            Value::Boolean(explanation.outcome.is_ok()),
+        );
+        let description = match explanation.outcome {
+            Ok(explanation) => explanation,
+            Err(e) => e.message,
+        };
+        propvals.insert(urls::DESCRIPTION.into(), Value::Markdown(description));
+        propvals.insert(urls::PERMISSIONS_CHAIN.into(), Value::ResourceArray(chain));
+        rights.push(SubResource::Nested(propvals));
+    }
+
+    let mut resource = permissions_endpoint().to_resource(store)?;
+    resource.set_subject(url.to_string());
+    resource.set_propval(
+        urls::PERMISSIONS_AGENT.into(),
+        Value::AtomicUrl(agent),
+        store,
+    )?;
+    resource.set_propval(
+        urls::PERMISSIONS_RIGHTS.into(),
+        Value::ResourceArray(rights),
+        store,
+    )?;
+    resource.set_propval(
+        urls::PERMISSIONS_AGENTS_WITH_ACCESS.into(),
+        agents_with_access(store, &target)?.into(),
+        store,
+    )?;
+    Ok(resource)
+}
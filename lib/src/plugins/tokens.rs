@@ -0,0 +1,28 @@
+//! Adds a [crate::tokens::CapabilityToken] to the revocation list of the Store.
+//! Only the Agent that issued the token can revoke it.
+
+use crate::{endpoints::Endpoint, errors::AtomicResult, tokens::CapabilityToken, Db};
+
+pub fn revoke_token_endpoint() -> Endpoint {
+    Endpoint {
+        path: "/revoke-token".to_string(),
+        params: Vec::new(),
+        description: "In `atomic-server`, a `/revoke-token` endpoint exists for revoking a capability token, which means it can no longer be used for authentication.\n\nSend an HTTP `POST` request to `/revoke-token` with the token as the body. The request must be signed by the Agent that issued the token.".to_string(),
+        shortname: "revoke-token".to_string(),
+        handle: None,
+    }
+}
+
+/// Verifies the serialized token, checks that the Agent issued it, and revokes it.
+#[tracing::instrument(skip(store, token))]
+pub fn revoke_token(token: &str, for_agent: &str, store: &Db) -> AtomicResult<CapabilityToken> {
+    let token = CapabilityToken::verify(token, store)?;
+    if for_agent != token.issuer {
+        return Err(crate::errors::AtomicError::unauthorized(format!(
+            "Only {} can revoke this token",
+            token.issuer
+        )));
+    }
+    store.revoke_token(&token)?;
+    Ok(token)
+}
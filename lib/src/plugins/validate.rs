@@ -0,0 +1,104 @@
+//! Runs [crate::validate::validate_store] and returns the report as a Resource.
+//! Only available to Agents that have write rights on the Drive.
+
+use crate::{
+    endpoints::Endpoint, errors::AtomicResult, resources::PropVals, urls, values::SubResource,
+    Resource, Storelike, Value,
+};
+
+pub fn validate_endpoint() -> Endpoint {
+    Endpoint {
+        path: "/validate".to_string(),
+        params: [].into(),
+        description: "Checks all Resources in this Store against their Classes and Properties, and returns a report with all errors. Requires write rights for the Drive. Can be slow for large stores.".to_string(),
+        shortname: "validate".to_string(),
+        handle: Some(handle_validate_request),
+    }
+}
+
+#[tracing::instrument(skip(store))]
+fn handle_validate_request(
+    url: url::Url,
+    store: &impl Storelike,
+    for_agent: Option<&str>,
+) -> AtomicResult<Resource> {
+    if let Some(agent) = for_agent {
+        let drive = store.get_resource(store.get_server_url())?;
+        crate::hierarchy::check_write(store, &drive, agent)?;
+    }
+    let report = store.validate();
+
+    let mut errors = Vec::new();
+    for resource_report in report.resources {
+        for error in resource_report.errors {
+            let mut propvals = PropVals::new();
+            propvals.insert(
+                urls::ATOM_SUBJECT.into(),
+                Value::AtomicUrl(resource_report.subject.clone()),
+            );
+            if let Some(property) = error.property {
+                propvals.insert(urls::ATOM_PROPERTY.into(), Value::AtomicUrl(property));
+            }
+            if let Some(class) = error.class {
+                propvals.insert(urls::CLASSTYPE_PROP.into(), Value::AtomicUrl(class));
+            }
+            let code = serde_json::to_value(&error.code)?
+                .as_str()
+                .unwrap_or_default()
+                .to_string();
+            propvals.insert(urls::VALIDATION_CODE.into(), Value::String(code));
+            propvals.insert(urls::DESCRIPTION.into(), Value::Markdown(error.message));
+            errors.push(SubResource::Nested(propvals));
+        }
+    }
+
+    let mut resource = validate_endpoint().to_resource(store)?;
+    resource.set_subject(url.to_string());
+    resource.set_propval(
+        urls::VALIDATION_RESOURCE_COUNT.into(),
+        Value::Integer(report.resource_count.try_into().unwrap_or(i64::MAX)),
+        store,
+    )?;
+    resource.set_propval(
+        urls::VALIDATION_ATOM_COUNT.into(),
+        Value::Integer(report.atom_count.try_into().unwrap_or(i64::MAX)),
+        store,
+    )?;
+    resource.set_propval(
+        urls::VALIDATION_ERRORS.into(),
+        Value::ResourceArray(errors),
+        store,
+    )?;
+    Ok(resource)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn requires_write_rights() {
+        let store = crate::test_utils::init_store();
+        let owner = store.get_default_agent().unwrap();
+        let stranger = store.create_agent(None).unwrap();
+        let mut drive = Resource::new(store.get_server_url().into());
+        drive.set_propval_unsafe(
+            urls::WRITE.into(),
+            Value::ResourceArray(vec![owner.subject.clone().into()]),
+        );
+        store.add_resource_opts(&drive, false, true, true).unwrap();
+        let url = url::Url::parse("http://localhost/validate").unwrap();
+
+        handle_validate_request(url.clone(), &store, Some(&stranger.subject))
+            .expect_err("Agents without write rights for the Drive can't validate");
+        let report = handle_validate_request(url, &store, Some(&owner.subject)).unwrap();
+        assert!(
+            report
+                .get(urls::VALIDATION_RESOURCE_COUNT)
+                .unwrap()
+                .to_int()
+                .unwrap()
+                > 5
+        );
+    }
+}
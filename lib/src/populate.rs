@@ -6,7 +6,7 @@
 use crate::{
     datatype::DataType,
     errors::AtomicResult,
-    schema::{Class, Property},
+    schema::{Class, Property, PropertyConstraints},
     urls, Storelike, Value,
 };
 
@@ -26,6 +26,7 @@ pub fn populate_base_models(store: &impl Storelike) -> AtomicResult<()> {
             description: "A short name of something. It can only contain letters, numbers and dashes `-`. Use dashes to denote spaces between words. Not case sensitive - lowercase only. Useful in programming contexts where the user should be able to type something short to identify a specific thing.".into(),
             subject: urls::SHORTNAME.into(),
             allows_only: None,
+            constraints: PropertyConstraints::default(),
         },
         Property {
             class_type: None,
@@ -34,6 +35,7 @@ pub fn populate_base_models(store: &impl Storelike) -> AtomicResult<()> {
             description: "A textual description of something. When making a description, make sure that the first few words tell the most important part. Give examples. Since the text supports markdown, you're free to use links and more.".into(),
             subject: urls::DESCRIPTION.into(),
             allows_only: None,
+            constraints: PropertyConstraints::default(),
         },
         Property {
             class_type: Some(urls::CLASS.into()),
@@ -42,6 +44,7 @@ pub fn populate_base_models(store: &impl Storelike) -> AtomicResult<()> {
             description: "A list of Classes of which the thing is an instance of. The Classes of a Resource determine which Properties are recommended and required.".into(),
             subject: urls::IS_A.into(),
             allows_only: None,
+            constraints: PropertyConstraints::default(),
         },
         Property {
             class_type: Some(urls::DATATYPE_CLASS.into()),
@@ -50,6 +53,7 @@ pub fn populate_base_models(store: &impl Storelike) -> AtomicResult<()> {
             description: "The Datatype of a property, such as String or Timestamp.".into(),
             subject: urls::DATATYPE_PROP.into(),
             allows_only: None,
+            constraints: PropertyConstraints::default(),
         },
         Property {
             class_type: Some(urls::CLASS.into()),
@@ -60,6 +64,7 @@ pub fn populate_base_models(store: &impl Storelike) -> AtomicResult<()> {
                     .into(),
             subject: urls::CLASSTYPE_PROP.into(),
             allows_only: None,
+            constraints: PropertyConstraints::default(),
         },
         Property {
             class_type: Some(urls::PROPERTY.into()),
@@ -68,6 +73,7 @@ pub fn populate_base_models(store: &impl Storelike) -> AtomicResult<()> {
             description: "The Properties that are not required, but recommended for this Class.".into(),
             subject: urls::RECOMMENDS.into(),
             allows_only: None,
+            constraints: PropertyConstraints::default(),
         },
         Property {
             class_type: Some(urls::PROPERTY.into()),
@@ -76,6 +82,7 @@ pub fn populate_base_models(store: &impl Storelike) -> AtomicResult<()> {
             description: "The Properties that are required for this Class.".into(),
             subject: urls::REQUIRES.into(),
             allows_only: None,
+            constraints: PropertyConstraints::default(),
         },
         Property {
             class_type: Some(urls::PROPERTY.into()),
@@ -84,6 +91,7 @@ pub fn populate_base_models(store: &impl Storelike) -> AtomicResult<()> {
             description: "The parent of a Resource sets the hierarchical structure of the Resource, and therefore also the rights / grants. It is used for both navigation, structure and authorization. Parents are the inverse of [children](https://atomicdata.dev/properties/children).".into(),
             subject: urls::PARENT.into(),
             allows_only: None,
+            constraints: PropertyConstraints::default(),
         },
         Property {
             class_type: Some(urls::PROPERTY.into()),
@@ -92,13 +100,14 @@ pub fn populate_base_models(store: &impl Storelike) -> AtomicResult<()> {
             description: "Restricts this Property to only the values inside this one. This essentially turns the Property into an `enum`.".into(),
             subject: urls::ALLOWS_ONLY.into(),
             allows_only: None,
+            constraints: PropertyConstraints::default(),
         }
     ];
 
     let classes = vec![
         Class {
             requires: vec![urls::SHORTNAME.into(), urls::DATATYPE_PROP.into(), urls::DESCRIPTION.into()],
-            recommends: vec![urls::CLASSTYPE_PROP.into(), urls::IS_DYNAMIC.into(), urls::IS_LOCKED.into(), urls::ALLOWS_ONLY.into()],
+            recommends: vec![urls::CLASSTYPE_PROP.into(), urls::IS_DYNAMIC.into(), urls::IS_LOCKED.into(), urls::ALLOWS_ONLY.into(), urls::MINIMUM.into(), urls::MAXIMUM.into(), urls::MIN_LENGTH.into(), urls::MAX_LENGTH.into(), urls::PATTERN.into(), urls::UNIQUE_WITHIN_PARENT.into()],
             shortname: "property".into(),
             description: "A Property is a single field in a Class. It's the thing that a property field in an Atom points to. An example is `birthdate`. An instance of Property requires various Properties, most notably a `datatype` (e.g. `string` or `integer`), a human readable `description` (such as the thing you're reading), and a `shortname`.".into(),
             subject: urls::PROPERTY.into(),
@@ -304,6 +304,7 @@ impl Resource {
         }
         let opts = CommitOpts {
             validate_schema: true,
+            validate_strict_schema: false,
             validate_signature: false,
             validate_timestamp: false,
             validate_rights: false,
@@ -331,6 +332,7 @@ impl Resource {
         let commit = commitbuilder.sign(&agent, store, self)?;
         let opts = CommitOpts {
             validate_schema: true,
+            validate_strict_schema: false,
             validate_signature: false,
             validate_timestamp: false,
             validate_rights: false,
@@ -390,7 +392,7 @@ impl Resource {
         store: &impl Storelike,
     ) -> AtomicResult<()> {
         let full_prop = store.get_property(&property)?;
-        if let Some(allowed) = full_prop.allows_only {
+        if let Some(allowed) = &full_prop.allows_only {
             if !allowed.contains(&value.to_string()) {
                 return Err(format!(
                     "Property '{}' does not allow value '{}'. Allowed: {:?}",
@@ -399,7 +401,8 @@ impl Resource {
                 .into());
             }
         }
-        if full_prop.data_type == value.datatype() {
+        full_prop.check_constraints(&value)?;
+        if value.matches_datatype(&full_prop.data_type) {
             self.set_propval_unsafe(property, value);
             Ok(())
         } else {
@@ -433,6 +436,7 @@ impl Resource {
     ) -> AtomicResult<()> {
         let fullprop = self.resolve_shortname_to_property(property, store)?;
         let fullval = Value::new(value, &fullprop.data_type)?;
+        fullprop.check_constraints(&fullval)?;
         self.set_propval_unsafe(fullprop.subject, fullval);
         Ok(())
     }
@@ -450,6 +454,16 @@ impl Resource {
         self.subject = url;
     }
 
+    /// Replaces all LangString values with the text in the best matching language.
+    /// Useful for serializations that can only show one language, such as plain JSON.
+    pub fn localize(&mut self, languages: &[String]) {
+        for value in self.propvals.values_mut() {
+            if let Some(text) = value.best_language_match(languages).map(String::from) {
+                *value = Value::String(text);
+            }
+        }
+    }
+
     /// Converts Resource to JSON-AD string.
     #[instrument(skip_all)]
     pub fn to_json_ad(&self) -> AtomicResult<String> {
@@ -484,6 +498,23 @@ impl Resource {
         serde_json::to_string_pretty(&obj).map_err(|_| "Could not serialize to JSON-LD".into())
     }
 
+    /// Converts Resource to compacted JSON-LD string, which refers to a shared @context document at `context_url`.
+    /// The `context` should be the one that is served there, see [crate::serialize::json_ld_context].
+    #[instrument(skip_all)]
+    pub fn to_json_ld_compacted(
+        &self,
+        context_url: &str,
+        context: &serde_json::Map<String, serde_json::Value>,
+    ) -> AtomicResult<String> {
+        let obj = crate::serialize::propvals_to_json_ld_compacted(
+            self.get_propvals(),
+            Some(self.get_subject().clone()),
+            context_url,
+            context,
+        )?;
+        serde_json::to_string_pretty(&obj).map_err(|_| "Could not serialize to JSON-LD".into())
+    }
+
     #[instrument(skip_all)]
     pub fn to_atoms(&self) -> AtomicResult<Vec<Atom>> {
         let mut atoms: Vec<Atom> = Vec::new();
@@ -533,6 +564,117 @@ mod test {
             .unwrap_err();
     }
 
+    #[test]
+    fn property_constraints() {
+        let store = init_store();
+        let property = crate::schema::Property {
+            class_type: None,
+            data_type: crate::datatype::DataType::String,
+            shortname: "code".into(),
+            description: "A short uppercase code".into(),
+            subject: "https://localhost/properties/code".into(),
+            allows_only: None,
+            constraints: crate::schema::PropertyConstraints {
+                max_length: Some(4),
+                pattern: Some("^[A-Z]+$".into()),
+                ..Default::default()
+            },
+        };
+        store.add_resource(&property.to_resource()).unwrap();
+        let mut resource = Resource::new("https://localhost/some-resource".into());
+        resource
+            .set_propval(
+                property.subject.clone(),
+                Value::String("ABC".into()),
+                &store,
+            )
+            .unwrap();
+        resource
+            .set_propval(
+                property.subject.clone(),
+                Value::String("ABCDE".into()),
+                &store,
+            )
+            .unwrap_err();
+        resource
+            .set_propval(
+                property.subject.clone(),
+                Value::String("abc".into()),
+                &store,
+            )
+            .unwrap_err();
+
+        let rating = crate::schema::Property {
+            data_type: crate::datatype::DataType::Integer,
+            shortname: "rating".into(),
+            description: "From one to five stars".into(),
+            subject: "https://localhost/properties/rating".into(),
+            constraints: crate::schema::PropertyConstraints {
+                minimum: Some(1.0),
+                maximum: Some(5.0),
+                ..Default::default()
+            },
+            ..property.clone()
+        };
+        store.add_resource(&rating.to_resource()).unwrap();
+        let set_rating = |resource: &mut Resource, n: i64| {
+            resource.set_propval(rating.subject.clone(), Value::Integer(n), &store)
+        };
+        set_rating(&mut resource, 1).unwrap();
+        set_rating(&mut resource, 5).unwrap();
+        set_rating(&mut resource, 0).unwrap_err();
+        set_rating(&mut resource, 6).unwrap_err();
+
+        let name = crate::schema::Property {
+            shortname: "name".into(),
+            description: "At least two characters".into(),
+            subject: "https://localhost/properties/name".into(),
+            constraints: crate::schema::PropertyConstraints {
+                min_length: Some(2),
+                ..Default::default()
+            },
+            ..property.clone()
+        };
+        store.add_resource(&name.to_resource()).unwrap();
+        resource
+            .set_propval(name.subject.clone(), Value::String("Jo".into()), &store)
+            .unwrap();
+        resource
+            .set_propval(name.subject.clone(), Value::String("J".into()), &store)
+            .unwrap_err();
+    }
+
+    #[test]
+    fn invalid_property_constraints() {
+        let mut resource = crate::schema::Property {
+            class_type: None,
+            data_type: crate::datatype::DataType::String,
+            shortname: "code".into(),
+            description: "Has invalid constraints".into(),
+            subject: "https://localhost/properties/code".into(),
+            allows_only: None,
+            constraints: crate::schema::PropertyConstraints {
+                max_length: Some(4),
+                ..Default::default()
+            },
+        }
+        .to_resource();
+        resource.set_propval_unsafe(urls::MIN_LENGTH.into(), Value::Integer(-1));
+        resource.set_propval_unsafe(urls::MAXIMUM.into(), Value::String("lots".into()));
+        // Invalid constraints are ignored when the Property is used
+        let property = crate::schema::Property::from_resource(resource.clone()).unwrap();
+        assert_eq!(property.constraints.min_length, None);
+        assert_eq!(property.constraints.maximum, None);
+        assert_eq!(property.constraints.max_length, Some(4));
+        // ...but rejected when the Property is committed
+        crate::schema::check_property_constraints(&resource).unwrap_err();
+        resource.remove_propval(urls::MIN_LENGTH);
+        resource.remove_propval(urls::MAXIMUM);
+        crate::schema::check_property_constraints(&resource).unwrap();
+        resource.set_propval_unsafe(urls::PATTERN.into(), Value::String("[a-".into()));
+        crate::schema::check_property_constraints(&resource).unwrap_err();
+    }
+
     #[test]
     fn check_required_props() {
         let store = init_store();
@@ -628,6 +770,7 @@ mod test {
                 &store,
                 &CommitOpts {
                     validate_schema: true,
+                    validate_strict_schema: false,
                     validate_signature: true,
                     validate_timestamp: true,
                     validate_rights: false,
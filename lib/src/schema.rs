@@ -2,6 +2,12 @@
 
 use crate::{datatype::DataType, errors::AtomicResult, urls, Resource, Value};
 use serde::{Deserialize, Serialize};
+use std::{cell::RefCell, collections::HashMap};
+
+thread_local! {
+    /// Compiled `pattern` constraints, by Property subject. Also stores the pattern, so changed patterns are compiled again.
+    static PATTERNS: RefCell<HashMap<String, (String, regex::Regex)>> = RefCell::new(HashMap::new());
+}
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Property {
@@ -18,6 +24,32 @@ pub struct Property {
     /// Restricts values to be only one of these Subjects.
     /// https://atomicdata.dev/properties/allowsOnly
     pub allows_only: Option<Vec<String>>,
+    /// Optional restrictions on the Values, such as a minimum or a regex pattern.
+    pub constraints: PropertyConstraints,
+}
+
+/// Restrictions on the Values of a [Property], on top of its Datatype.
+/// Checked by [Property::check_constraints], except for `unique_within_parent` which needs the Store.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PropertyConstraints {
+    /// Lowest allowed number (or timestamp).
+    /// https://atomicdata.dev/properties/minimum
+    pub minimum: Option<f64>,
+    /// Highest allowed number (or timestamp).
+    /// https://atomicdata.dev/properties/maximum
+    pub maximum: Option<f64>,
+    /// Minimum amount of characters for strings, or items for arrays.
+    /// https://atomicdata.dev/properties/minLength
+    pub min_length: Option<usize>,
+    /// Maximum amount of characters for strings, or items for arrays.
+    /// https://atomicdata.dev/properties/maxLength
+    pub max_length: Option<usize>,
+    /// Regular expression that string values must match.
+    /// https://atomicdata.dev/properties/pattern
+    pub pattern: Option<String>,
+    /// No two Resources with the same Parent can have the same Value for this Property.
+    /// https://atomicdata.dev/properties/uniqueWithinParent
+    pub unique_within_parent: bool,
 }
 
 impl PartialEq for Property {
@@ -26,6 +58,52 @@ impl PartialEq for Property {
     }
 }
 
+/// Parses a numeric constraint of a Property, such as its `minimum`.
+/// Returns None if the constraint is not set.
+fn parse_constraint<T: std::str::FromStr>(
+    resource: &Resource,
+    prop: &str,
+) -> Option<AtomicResult<T>> {
+    let value = resource.get(prop).ok()?;
+    Some(value.to_string().parse().map_err(|_| {
+        format!(
+            "Constraint {} of Property {} is not a valid number: {}",
+            prop,
+            resource.get_subject(),
+            value
+        )
+        .into()
+    }))
+}
+
+/// Like [parse_constraint], but ignores invalid constraints.
+/// An invalid constraint should not make every Resource that uses the Property invalid.
+fn valid_constraint<T: std::str::FromStr>(resource: &Resource, prop: &str) -> Option<T> {
+    match parse_constraint(resource, prop)? {
+        Ok(value) => Some(value),
+        Err(e) => {
+            tracing::warn!("Ignoring constraint. {}", e);
+            None
+        }
+    }
+}
+
+/// Checks whether the constraints of a Property resource are valid numbers and patterns.
+/// Used when a Property is committed, since invalid constraints are ignored when the Property is used.
+pub fn check_property_constraints(resource: &Resource) -> AtomicResult<()> {
+    for prop in [urls::MINIMUM, urls::MAXIMUM] {
+        parse_constraint::<f64>(resource, prop).transpose()?;
+    }
+    for prop in [urls::MIN_LENGTH, urls::MAX_LENGTH] {
+        parse_constraint::<usize>(resource, prop).transpose()?;
+    }
+    if let Ok(pattern) = resource.get(urls::PATTERN) {
+        regex::Regex::new(&pattern.to_string())
+            .map_err(|e| format!("Invalid pattern '{}': {}", pattern, e))?;
+    }
+    Ok(())
+}
+
 impl Property {
     /// Fetches a property by URL, returns a Property instance
     pub fn from_resource(resource: Resource) -> AtomicResult<Property> {
@@ -41,16 +119,124 @@ impl Property {
             Err(_) => None,
         };
 
+        let constraints = PropertyConstraints {
+            minimum: valid_constraint(&resource, urls::MINIMUM),
+            maximum: valid_constraint(&resource, urls::MAXIMUM),
+            min_length: valid_constraint(&resource, urls::MIN_LENGTH),
+            max_length: valid_constraint(&resource, urls::MAX_LENGTH),
+            pattern: resource.get(urls::PATTERN).ok().map(|v| v.to_string()),
+            unique_within_parent: matches!(
+                resource.get(urls::UNIQUE_WITHIN_PARENT),
+                Ok(Value::Boolean(true))
+            ),
+        };
+
         Ok(Property {
             class_type,
             data_type,
             shortname,
             description,
             allows_only,
+            constraints,
             subject: resource.get_subject().into(),
         })
     }
 
+    /// Checks whether the Value satisfies the [PropertyConstraints] of this Property.
+    /// Does not check `unique_within_parent`, since that requires the Store.
+    pub fn check_constraints(&self, value: &Value) -> AtomicResult<()> {
+        // Every translation has to satisfy the constraints
+        if let Value::LangString(map) = value {
+            for text in map.values() {
+                self.check_constraints(&Value::String(text.clone()))?;
+            }
+            return Ok(());
+        }
+        let c = &self.constraints;
+        let number = match value {
+            Value::Integer(i) => Some(*i as f64),
+            Value::Float(f) => Some(*f),
+            Value::Timestamp(t) => Some(*t as f64),
+            Value::Decimal(d) => d.parse().ok(),
+            _ => None,
+        };
+        if let Some(n) = number {
+            if let Some(min) = c.minimum {
+                if n < min {
+                    return Err(format!(
+                        "Value {} of Property {} is lower than the minimum of {}",
+                        value, self.subject, min
+                    )
+                    .into());
+                }
+            }
+            if let Some(max) = c.maximum {
+                if n > max {
+                    return Err(format!(
+                        "Value {} of Property {} is higher than the maximum of {}",
+                        value, self.subject, max
+                    )
+                    .into());
+                }
+            }
+        }
+        let length = match value {
+            Value::String(s) | Value::Markdown(s) | Value::Slug(s) => Some(s.chars().count()),
+            Value::ResourceArray(arr) => Some(arr.len()),
+            _ => None,
+        };
+        if let Some(len) = length {
+            if let Some(min) = c.min_length {
+                if len < min {
+                    return Err(format!(
+                        "Value of Property {} is too short: {} is less than the minimum length of {}",
+                        self.subject, len, min
+                    )
+                    .into());
+                }
+            }
+            if let Some(max) = c.max_length {
+                if len > max {
+                    return Err(format!(
+                        "Value of Property {} is too long: {} is more than the maximum length of {}",
+                        self.subject, len, max
+                    )
+                    .into());
+                }
+            }
+        }
+        if let Some(pattern) = &c.pattern {
+            if let Value::String(s) | Value::Markdown(s) | Value::Slug(s) = value {
+                if !self.pattern_matches(pattern, s)? {
+                    return Err(format!(
+                        "Value '{}' of Property {} does not match the pattern '{}'",
+                        s, self.subject, pattern
+                    )
+                    .into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Matches the string against the pattern of this Property.
+    /// The compiled Regex is cached per Property, since it is used every time a Value is set.
+    fn pattern_matches(&self, pattern: &str, s: &str) -> AtomicResult<bool> {
+        PATTERNS.with(|patterns| {
+            let mut patterns = patterns.borrow_mut();
+            if let Some((cached, re)) = patterns.get(&self.subject) {
+                if cached == pattern {
+                    return Ok(re.is_match(s));
+                }
+            }
+            let re = regex::Regex::new(pattern)
+                .map_err(|e| format!("Invalid pattern '{}' in {}: {}", pattern, self.subject, e))?;
+            let is_match = re.is_match(s);
+            patterns.insert(self.subject.clone(), (pattern.into(), re));
+            Ok(is_match)
+        })
+    }
+
     /// Convert to resource.
     pub fn to_resource(&self) -> Resource {
         let mut resource = Resource::new(self.subject.clone());
@@ -73,6 +259,25 @@ impl Property {
                 Value::AtomicUrl(classtype.clone()),
             );
         }
+        let c = &self.constraints;
+        if let Some(min) = c.minimum {
+            resource.set_propval_unsafe(urls::MINIMUM.into(), Value::Float(min));
+        }
+        if let Some(max) = c.maximum {
+            resource.set_propval_unsafe(urls::MAXIMUM.into(), Value::Float(max));
+        }
+        if let Some(min) = c.min_length {
+            resource.set_propval_unsafe(urls::MIN_LENGTH.into(), Value::Integer(min as i64));
+        }
+        if let Some(max) = c.max_length {
+            resource.set_propval_unsafe(urls::MAX_LENGTH.into(), Value::Integer(max as i64));
+        }
+        if let Some(pattern) = &c.pattern {
+            resource.set_propval_unsafe(urls::PATTERN.into(), Value::String(pattern.clone()));
+        }
+        if c.unique_within_parent {
+            resource.set_propval_unsafe(urls::UNIQUE_WITHIN_PARENT.into(), Value::Boolean(true));
+        }
 
         resource
     }
@@ -1,4 +1,4 @@
-//! Serialization / formatting / encoding (JSON, RDF, N-Triples)
+//! Serialization / formatting / encoding (JSON, RDF, N-Triples, Turtle, RDF/XML, TriG)
 
 use serde_json::Map;
 use serde_json::Value as SerdeValue;
@@ -8,6 +8,15 @@ use crate::{
     datatype::DataType, errors::AtomicResult, resources::PropVals, Resource, Storelike, Value,
 };
 
+mod prefixes;
+mod rdf_xml;
+mod trig;
+mod turtle;
+
+pub use rdf_xml::atoms_to_rdf_xml;
+pub use trig::resources_to_trig;
+pub use turtle::atoms_to_turtle;
+
 /// Serializes a vector or Resources to a JSON-AD string
 pub fn resources_to_json_ad(resources: &[Resource]) -> AtomicResult<String> {
     let mut vec: Vec<serde_json::Value> = Vec::new();
@@ -69,6 +78,18 @@ fn val_to_serde(value: Value) -> AtomicResult<SerdeValue> {
             crate::values::SubResource::Subject(s) => SerdeValue::String(s),
         },
         Value::Resource(_) => todo!(),
+        Value::Uri(val) => SerdeValue::String(val),
+        Value::Json(val) => serde_json::from_str(&val)?,
+        // Decimals are serialized as strings, so no precision is lost
+        Value::Decimal(val) => SerdeValue::String(val),
+        Value::Duration(val) => SerdeValue::String(val),
+        Value::GeoPoint(val) => SerdeValue::String(val.to_string()),
+        // A JSON-LD Language Map
+        Value::LangString(map) => SerdeValue::Object(
+            map.into_iter()
+                .map(|(lang, text)| (lang, SerdeValue::String(text)))
+                .collect(),
+        ),
     };
     Ok(json_val)
 }
@@ -110,45 +131,10 @@ pub fn propvals_to_json_ld(
         // The property is only needed in JSON-LD and JSON for shortnames
         let property = store.get_property(prop_url)?;
         if json_ld {
-            // In JSON-LD, the value of a Context Item can be a string or an object.
-            // This object can contain information about the translation or datatype of the value
-            let ctx_value: SerdeValue = match value.datatype() {
-                DataType::AtomicUrl => {
-                    let mut obj = Map::new();
-                    obj.insert("@id".into(), prop_url.as_str().into());
-                    obj.insert("@type".into(), "@id".into());
-                    obj.into()
-                }
-                DataType::Date => {
-                    let mut obj = Map::new();
-                    obj.insert("@id".into(), prop_url.as_str().into());
-                    obj.insert(
-                        "@type".into(),
-                        "http://www.w3.org/2001/XMLSchema#date".into(),
-                    );
-                    obj.into()
-                }
-                DataType::Integer => {
-                    let mut obj = Map::new();
-                    obj.insert("@id".into(), prop_url.as_str().into());
-                    // I'm not sure whether we should use XSD or Atomic Datatypes
-                    obj.insert(
-                        "@type".into(),
-                        "http://www.w3.org/2001/XMLSchema#integer".into(),
-                    );
-                    obj.into()
-                }
-                DataType::Markdown => prop_url.as_str().into(),
-                DataType::ResourceArray => {
-                    let mut obj = Map::new();
-                    obj.insert("@id".into(), prop_url.as_str().into());
-                    // Plain JSON-LD Arrays are not ordered. Here, they are converted into an RDF List.
-                    obj.insert("@container".into(), "@list".into());
-                    obj.into()
-                }
-                _other => prop_url.as_str().into(),
-            };
-            context.insert(property.shortname.as_str().into(), ctx_value);
+            context.insert(
+                property.shortname.as_str().into(),
+                json_ld_context_item(prop_url, &value.datatype()),
+            );
         }
         let key = property.shortname;
 
@@ -167,6 +153,144 @@ pub fn propvals_to_json_ld(
     Ok(obj)
 }
 
+/// Creates the definition of a Property in a JSON-LD @context.
+/// In JSON-LD, the value of a Context Item can be a string or an object.
+/// This object can contain information about the translation or datatype of the value
+fn json_ld_context_item(prop_url: &str, datatype: &DataType) -> SerdeValue {
+    match datatype {
+        DataType::AtomicUrl => {
+            let mut obj = Map::new();
+            obj.insert("@id".into(), prop_url.into());
+            obj.insert("@type".into(), "@id".into());
+            obj.into()
+        }
+        DataType::Date => {
+            let mut obj = Map::new();
+            obj.insert("@id".into(), prop_url.into());
+            obj.insert(
+                "@type".into(),
+                "http://www.w3.org/2001/XMLSchema#date".into(),
+            );
+            obj.into()
+        }
+        DataType::Integer => {
+            let mut obj = Map::new();
+            obj.insert("@id".into(), prop_url.into());
+            // I'm not sure whether we should use XSD or Atomic Datatypes
+            obj.insert(
+                "@type".into(),
+                "http://www.w3.org/2001/XMLSchema#integer".into(),
+            );
+            obj.into()
+        }
+        DataType::Markdown => prop_url.into(),
+        DataType::ResourceArray => {
+            let mut obj = Map::new();
+            obj.insert("@id".into(), prop_url.into());
+            // Plain JSON-LD Arrays are not ordered. Here, they are converted into an RDF List.
+            obj.insert("@container".into(), "@list".into());
+            obj.into()
+        }
+        DataType::LangString => {
+            let mut obj = Map::new();
+            obj.insert("@id".into(), prop_url.into());
+            obj.insert("@container".into(), "@language".into());
+            obj.into()
+        }
+        DataType::Json => {
+            let mut obj = Map::new();
+            obj.insert("@id".into(), prop_url.into());
+            // JSON literals are kept as-is in JSON-LD 1.1
+            obj.insert("@type".into(), "@json".into());
+            obj.into()
+        }
+        DataType::Uri | DataType::Decimal | DataType::Duration | DataType::GeoPoint => {
+            let mut obj = Map::new();
+            obj.insert("@id".into(), prop_url.into());
+            obj.insert("@type".into(), rdf_literal_datatype(datatype).into());
+            obj.into()
+        }
+        _other => prop_url.into(),
+    }
+}
+
+/// Creates a JSON-LD @context that maps the shortnames of all Properties in the Store to their URLs.
+/// Used as a shared context document, so compacted JSON-LD doesn't have to include its own.
+/// If multiple Properties share a shortname, the first one (sorted by subject) gets it.
+pub fn json_ld_context(store: &impl Storelike) -> AtomicResult<Map<String, SerdeValue>> {
+    let mut subjects: Vec<String> = store
+        .tpf(
+            None,
+            Some(crate::urls::IS_A),
+            Some(&Value::String(crate::urls::PROPERTY.into())),
+            true,
+        )?
+        .into_iter()
+        .map(|atom| atom.subject)
+        .collect();
+    subjects.sort();
+    subjects.dedup();
+    let mut context = Map::new();
+    // Some datatypes (e.g. `@json`) require JSON-LD 1.1
+    context.insert("@version".into(), 1.1.into());
+    for subject in subjects {
+        let property = match store.get_property(&subject) {
+            Ok(property) => property,
+            Err(_) => continue,
+        };
+        if !context.contains_key(&property.shortname) {
+            context.insert(
+                property.shortname.clone(),
+                json_ld_context_item(&subject, &property.data_type),
+            );
+        }
+    }
+    Ok(context)
+}
+
+/// Serializes a Resource to compacted JSON-LD, which refers to a shared @context document instead of including one.
+/// Properties are keyed by their shortname if the context maps it to the same URL, otherwise by their full URL.
+pub fn propvals_to_json_ld_compacted(
+    propvals: &PropVals,
+    subject: Option<String>,
+    context_url: &str,
+    context: &Map<String, SerdeValue>,
+) -> AtomicResult<serde_json::Value> {
+    // Property URL => term
+    let terms: std::collections::HashMap<&str, &str> = context
+        .iter()
+        .filter_map(|(term, item)| {
+            let id = item.get("@id").unwrap_or(item).as_str()?;
+            Some((id, term.as_str()))
+        })
+        .collect();
+    let mut root = Map::new();
+    for (prop_url, value) in propvals.iter() {
+        let key = terms
+            .get(prop_url.as_str())
+            .copied()
+            .unwrap_or(prop_url.as_str());
+        root.insert(key.to_string(), val_to_serde(value.clone())?);
+    }
+    if let Some(sub) = subject {
+        root.insert("@id".into(), SerdeValue::String(sub));
+    }
+    root.insert("@context".into(), context_url.into());
+    Ok(SerdeValue::Object(root))
+}
+
+/// Returns the URL of the datatype that is used for typed literals in RDF.
+/// Uses XSD (or RDF) datatypes where an equivalent exists, and the Atomic Datatype otherwise.
+pub fn rdf_literal_datatype(datatype: &DataType) -> String {
+    match datatype {
+        DataType::Uri => "http://www.w3.org/2001/XMLSchema#anyURI".into(),
+        DataType::Decimal => "http://www.w3.org/2001/XMLSchema#decimal".into(),
+        DataType::Duration => "http://www.w3.org/2001/XMLSchema#duration".into(),
+        DataType::Json => "http://www.w3.org/1999/02/22-rdf-syntax-ns#JSON".into(),
+        other => other.to_string(),
+    }
+}
+
 pub fn serialize_json_array(items: &[String]) -> AtomicResult<String> {
     let string = serde_json::to_string(items)?;
     Ok(string)
@@ -175,78 +299,131 @@ pub fn serialize_json_array(items: &[String]) -> AtomicResult<String> {
 #[cfg(feature = "rdf")]
 /// Serializes Atoms to Ntriples (which is also valid Turtle / Notation3).
 pub fn atoms_to_ntriples(atoms: Vec<crate::Atom>, store: &impl Storelike) -> AtomicResult<String> {
-    use rio_api::formatter::TriplesFormatter;
-    use rio_api::model::{Literal, NamedNode, Term, Triple};
     use rio_turtle::NTriplesFormatter;
 
     let mut formatter = NTriplesFormatter::new(Vec::default());
-    for atom in atoms {
-        let subject = NamedNode { iri: &atom.subject }.into();
-        let predicate = NamedNode {
-            iri: &atom.property,
-        };
-        let datatype = store.get_property(&atom.property)?.data_type;
-        let value = &atom.value.to_string();
-        let datatype_url = datatype.to_string();
-        let object: Term = match &datatype {
-            DataType::AtomicUrl => NamedNode { iri: value }.into(),
-            // Maybe these should be converted to RDF collections / lists?
-            // DataType::ResourceArray => {}
-            DataType::String => Literal::Simple { value }.into(),
-            _dt => Literal::Typed {
-                value,
-                datatype: NamedNode { iri: &datatype_url },
-            }
-            .into(),
-        };
-
-        formatter.format(&Triple {
-            subject,
-            predicate,
-            object,
-        })?
-    }
+    format_atoms(&mut formatter, atoms, store)?;
     let out = String::from_utf8(formatter.finish()?)?;
     Ok(out)
 }
 
 #[cfg(feature = "rdf")]
-/// Serializes Atoms to Ntriples (which is also valid Turtle / Notation3).
-pub fn atoms_to_turtle(atoms: Vec<crate::Atom>, store: &impl Storelike) -> AtomicResult<String> {
-    use rio_api::formatter::TriplesFormatter;
-    use rio_api::model::{Literal, NamedNode, Term, Triple};
-    use rio_turtle::TurtleFormatter;
-
-    let mut formatter = TurtleFormatter::new(Vec::default());
+/// Writes the Atoms as RDF triples to any rio formatter.
+fn format_atoms<F>(
+    formatter: &mut F,
+    atoms: Vec<crate::Atom>,
+    store: &impl Storelike,
+) -> AtomicResult<()>
+where
+    F: rio_api::formatter::TriplesFormatter,
+    crate::errors::AtomicError: From<F::Error>,
+{
+    use rio_api::model::NamedNode;
 
+    let mut blank_nodes = 0;
     for atom in atoms {
         let subject = NamedNode { iri: &atom.subject }.into();
-        let predicate = NamedNode {
-            iri: &atom.property,
-        };
-        let datatype = store.get_property(&atom.property)?.data_type;
-        let value = &atom.value.to_string();
-        let datatype_url = datatype.to_string();
-        let object: Term = match &datatype {
-            DataType::AtomicUrl => NamedNode { iri: value }.into(),
-            // Maybe these should be converted to RDF collections / lists?
-            // DataType::ResourceArray => {}
-            DataType::String => Literal::Simple { value }.into(),
-            _dt => Literal::Typed {
-                value,
-                datatype: NamedNode { iri: &datatype_url },
+        format_value(
+            formatter,
+            subject,
+            &atom.property,
+            &atom.value,
+            store,
+            &mut blank_nodes,
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "rdf")]
+/// Writes a single Property / Value combination as a triple.
+/// Anonymous Nested Resources become blank nodes, embedded named Resources are written as their own triples.
+fn format_value<F>(
+    formatter: &mut F,
+    subject: rio_api::model::Subject,
+    property: &str,
+    value: &Value,
+    store: &impl Storelike,
+    blank_nodes: &mut usize,
+) -> AtomicResult<()>
+where
+    F: rio_api::formatter::TriplesFormatter,
+    crate::errors::AtomicError: From<F::Error>,
+{
+    use crate::values::SubResource;
+    use rio_api::model::{BlankNode, Literal, NamedNode, Term, Triple};
+
+    let predicate = NamedNode { iri: property };
+    let embedded = match value {
+        Value::NestedResource(SubResource::Nested(propvals)) => {
+            *blank_nodes += 1;
+            let id = format!("n{}", blank_nodes);
+            let node = BlankNode { id: &id };
+            formatter.format(&Triple {
+                subject,
+                predicate,
+                object: node.into(),
+            })?;
+            for (prop, val) in propvals.iter() {
+                format_value(formatter, node.into(), prop, val, store, blank_nodes)?;
             }
-            .into(),
+            return Ok(());
+        }
+        Value::NestedResource(SubResource::Resource(r)) => Some(&**r),
+        Value::Resource(r) => Some(r),
+        _ => None,
+    };
+    if let Some(resource) = embedded {
+        let node = NamedNode {
+            iri: resource.get_subject(),
         };
-
         formatter.format(&Triple {
             subject,
             predicate,
-            object,
-        })?
+            object: node.into(),
+        })?;
+        for (prop, val) in resource.get_propvals().iter() {
+            format_value(formatter, node.into(), prop, val, store, blank_nodes)?;
+        }
+        return Ok(());
     }
-    let out = String::from_utf8(formatter.finish()?)?;
-    Ok(out)
+
+    // Every translation becomes a separate rdf:langString literal
+    if let Value::LangString(map) = value {
+        for (language, text) in map {
+            formatter.format(&Triple {
+                subject,
+                predicate,
+                object: Literal::LanguageTaggedString {
+                    value: text,
+                    language,
+                }
+                .into(),
+            })?;
+        }
+        return Ok(());
+    }
+
+    let datatype = store.get_property(property)?.data_type;
+    let value = &value.to_string();
+    let datatype_url = rdf_literal_datatype(&datatype);
+    let object: Term = match &datatype {
+        DataType::AtomicUrl => NamedNode { iri: value }.into(),
+        // Maybe these should be converted to RDF collections / lists?
+        // DataType::ResourceArray => {}
+        DataType::String => Literal::Simple { value }.into(),
+        _dt => Literal::Typed {
+            value,
+            datatype: NamedNode { iri: &datatype_url },
+        }
+        .into(),
+    };
+    formatter.format(&Triple {
+        subject,
+        predicate,
+        object,
+    })?;
+    Ok(())
 }
 
 /// Should list all the supported serialization formats
@@ -256,6 +433,10 @@ pub enum Format {
     JsonLd,
     NTriples,
     Pretty,
+    Turtle,
+    RdfXml,
+    /// Turtle with named graphs. Only available for multiple Resources, since it writes Commits as named graphs.
+    TriG,
 }
 
 #[cfg(test)]
@@ -394,6 +575,30 @@ mod test {
         assert_eq!(our_value, correct_value)
     }
 
+    #[test]
+    fn serialize_json_ld_compacted() {
+        let store = crate::Store::init().unwrap();
+        store.populate().unwrap();
+        let context = json_ld_context(&store).unwrap();
+        assert_eq!(
+            context.get("description").unwrap(),
+            &SerdeValue::String(crate::urls::DESCRIPTION.into())
+        );
+        let mut resource = Resource::new("https://localhost/compacted".into());
+        resource.set_propval_unsafe(crate::urls::DESCRIPTION.into(), Value::Markdown("d".into()));
+        resource.set_propval_unsafe(
+            "https://localhost/unknown".into(),
+            Value::String("u".into()),
+        );
+        let json = resource
+            .to_json_ld_compacted("https://localhost/context", &context)
+            .unwrap();
+        let value: SerdeValue = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["@context"], "https://localhost/context");
+        assert_eq!(value["description"], "d");
+        assert_eq!(value["https://localhost/unknown"], "u");
+    }
+
     #[test]
     #[cfg(feature = "rdf")]
     fn serialize_ntriples() {
@@ -413,4 +618,25 @@ mod test {
         // This could fail when the `description` resource changes
         assert!(serialized.lines().count() == 5);
     }
+
+    #[test]
+    #[cfg(feature = "rdf")]
+    fn serialize_nested_as_blank_nodes() {
+        let store = crate::Store::init().unwrap();
+        store.populate().unwrap();
+        let mut nested = PropVals::new();
+        nested.insert(
+            crate::urls::DESCRIPTION.into(),
+            Value::Markdown("nested description".into()),
+        );
+        let mut resource = Resource::new("https://localhost/with-nested".into());
+        resource.set_propval_unsafe(crate::urls::PARENT.into(), nested.into());
+        let serialized = atoms_to_ntriples(resource.to_atoms().unwrap(), &store).unwrap();
+        assert!(serialized.contains(
+            "<https://localhost/with-nested> <https://atomicdata.dev/properties/parent> _:n1 ."
+        ));
+        assert!(serialized.contains(
+            r#"_:n1 <https://atomicdata.dev/properties/description> "nested description""#
+        ));
+    }
 }
@@ -0,0 +1,123 @@
+//! Prefixes (namespaces) for RDF serializations that support shortened IRIs, such as Turtle and RDF/XML.
+
+use std::collections::{BTreeMap, HashSet};
+
+/// Prefixes for namespaces that are not derived from their URL.
+const KNOWN_PREFIXES: [(&str, &str); 4] = [
+    ("rdf", "http://www.w3.org/1999/02/22-rdf-syntax-ns#"),
+    ("rdfs", "http://www.w3.org/2000/01/rdf-schema#"),
+    ("xsd", "http://www.w3.org/2001/XMLSchema#"),
+    ("atomic", "https://atomicdata.dev/properties/"),
+];
+
+/// Keeps track of the namespaces that are used in a document, and their labels.
+#[derive(Default)]
+pub(super) struct Prefixes {
+    /// Namespace URL => prefix label
+    prefixes: BTreeMap<String, String>,
+    labels: HashSet<String>,
+}
+
+impl Prefixes {
+    /// Splits an IRI into a prefix label and a local name, e.g. `atomic` and `description`.
+    /// Returns None if the IRI does not end with a valid local name.
+    pub fn split(&mut self, iri: &str) -> Option<(String, String)> {
+        let split = iri.rfind(['/', '#'])? + 1;
+        let (namespace, local) = iri.split_at(split);
+        if is_local_name(local) && namespace.contains("://") {
+            Some((self.label_for(namespace), local.to_string()))
+        } else {
+            None
+        }
+    }
+
+    /// Like [Prefixes::split], but if the IRI does not end with a valid local name after its last `/` or `#`, it splits before the longest suffix that is valid.
+    /// These namespaces get a synthetic `nsN` label.
+    /// Used for RDF/XML, where every property needs a prefixed name.
+    /// Returns None if no part at the end of the IRI is a valid local name.
+    pub fn split_anywhere(&mut self, iri: &str) -> Option<(String, String)> {
+        if let Some(split) = self.split(iri) {
+            return Some(split);
+        }
+        let start = iri
+            .char_indices()
+            .map(|(i, _)| i)
+            .find(|i| is_local_name(&iri[*i..]))?;
+        let (namespace, local) = iri.split_at(start);
+        if !namespace.contains("://") {
+            return None;
+        }
+        let label = match self.prefixes.get(namespace) {
+            Some(label) => label.clone(),
+            None => self.insert(namespace, "ns".into()),
+        };
+        Some((label, local.to_string()))
+    }
+
+    /// Returns the prefix label of the namespace, and creates one if it doesn't exist.
+    /// Labels are derived from the last part of the namespace URL, e.g. `classes` for `https://atomicdata.dev/classes/`.
+    pub fn label_for(&mut self, namespace: &str) -> String {
+        if let Some(label) = self.prefixes.get(namespace) {
+            return label.clone();
+        }
+        let base = KNOWN_PREFIXES
+            .iter()
+            .find(|(_, ns)| *ns == namespace)
+            .map(|(label, _)| label.to_string())
+            .unwrap_or_else(|| derive_label(namespace));
+        self.insert(namespace, base)
+    }
+
+    /// Adds the namespace with a unique label, which is the base followed by a number if the base is already used.
+    fn insert(&mut self, namespace: &str, base: String) -> String {
+        let mut label = base.clone();
+        let mut counter = 1;
+        while self.labels.contains(&label) {
+            counter += 1;
+            label = format!("{}{}", base, counter);
+        }
+        self.labels.insert(label.clone());
+        self.prefixes.insert(namespace.to_string(), label.clone());
+        label
+    }
+
+    /// Returns all `(label, namespace)` pairs, sorted by label.
+    pub fn sorted(&self) -> Vec<(&str, &str)> {
+        let mut sorted: Vec<(&str, &str)> = self
+            .prefixes
+            .iter()
+            .map(|(namespace, label)| (label.as_str(), namespace.as_str()))
+            .collect();
+        sorted.sort();
+        sorted
+    }
+}
+
+/// Creates a prefix label from the last segment of a namespace URL, or its domain.
+fn derive_label(namespace: &str) -> String {
+    let trimmed = namespace
+        .split("://")
+        .nth(1)
+        .unwrap_or(namespace)
+        .trim_end_matches(['/', '#']);
+    let segment = trimmed.rsplit('/').next().unwrap_or(trimmed);
+    let label: String = segment
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-')
+        .collect();
+    match label.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => label,
+        _ => "ns".into(),
+    }
+}
+
+/// Whether the string can be used after the colon in a prefixed name.
+/// Only allows a safe subset of the Turtle grammar, which is also a valid XML name.
+fn is_local_name(local: &str) -> bool {
+    let mut chars = local.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
@@ -0,0 +1,284 @@
+//! RDF/XML serialization, for toolchains that don't support newer RDF formats.
+//! https://www.w3.org/TR/rdf-syntax-grammar/
+
+use std::collections::BTreeMap;
+
+use super::prefixes::Prefixes;
+use crate::{
+    datatype::DataType, errors::AtomicResult, resources::PropVals, values::SubResource, Atom,
+    Storelike, Value,
+};
+
+const RDF_NS: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+
+/// Serializes Atoms to RDF/XML.
+/// Atoms are grouped in an `rdf:Description` per subject. Nested Resources are written inside their parent.
+pub fn atoms_to_rdf_xml(atoms: Vec<Atom>, store: &impl Storelike) -> AtomicResult<String> {
+    let mut grouped: BTreeMap<String, Vec<(String, Value)>> = BTreeMap::new();
+    for atom in atoms {
+        grouped
+            .entry(atom.subject)
+            .or_default()
+            .push((atom.property, atom.value));
+    }
+    let mut writer = RdfXmlWriter::default();
+    // Makes sure `rdf` is always declared, and gets its common label
+    writer.prefixes.label_for(RDF_NS);
+    let mut body = String::new();
+    for (subject, propvals) in grouped.iter() {
+        writer.write_description(&mut body, Some(subject), propvals, store, 1)?;
+    }
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<rdf:RDF");
+    for (label, namespace) in writer.prefixes.sorted() {
+        out.push_str(&format!("\n    xmlns:{}=\"{}\"", label, escape(namespace)));
+    }
+    out.push_str(">\n");
+    out.push_str(&body);
+    out.push_str("</rdf:RDF>\n");
+    Ok(out)
+}
+
+#[derive(Default)]
+struct RdfXmlWriter {
+    prefixes: Prefixes,
+}
+
+impl RdfXmlWriter {
+    /// Writes an `rdf:Description` element. Anonymous (Nested) Resources have no subject.
+    fn write_description(
+        &mut self,
+        out: &mut String,
+        subject: Option<&str>,
+        propvals: &[(String, Value)],
+        store: &impl Storelike,
+        depth: usize,
+    ) -> AtomicResult<()> {
+        let indent = "  ".repeat(depth);
+        out.push_str(&indent);
+        out.push_str("<rdf:Description");
+        if let Some(subject) = subject {
+            out.push_str(&format!(" rdf:about=\"{}\"", escape(subject)));
+        }
+        out.push_str(">\n");
+        let mut sorted: Vec<&(String, Value)> = propvals.iter().collect();
+        sorted.sort_by(|a, b| a.0.cmp(&b.0));
+        for (property, value) in sorted {
+            self.write_property(out, property, value, store, depth + 1)?;
+        }
+        out.push_str(&indent);
+        out.push_str("</rdf:Description>\n");
+        Ok(())
+    }
+
+    fn write_property(
+        &mut self,
+        out: &mut String,
+        property: &str,
+        value: &Value,
+        store: &impl Storelike,
+        depth: usize,
+    ) -> AtomicResult<()> {
+        let indent = "  ".repeat(depth);
+        let tag = match self.qname(property) {
+            Some(tag) => tag,
+            None => {
+                tracing::warn!(
+                    "Skipping property {} in RDF/XML, since it doesn't end with a valid XML name",
+                    property
+                );
+                return Ok(());
+            }
+        };
+        match value {
+            Value::AtomicUrl(url) => {
+                out.push_str(&format!(
+                    "{}<{} rdf:resource=\"{}\"/>\n",
+                    indent,
+                    tag,
+                    escape(url)
+                ));
+            }
+            Value::NestedResource(sub) => {
+                out.push_str(&format!("{}<{}>\n", indent, tag));
+                self.write_sub_resource(out, sub, store, depth + 1)?;
+                out.push_str(&format!("{}</{}>\n", indent, tag));
+            }
+            Value::Resource(resource) => {
+                out.push_str(&format!("{}<{}>\n", indent, tag));
+                self.write_description(
+                    out,
+                    Some(resource.get_subject()),
+                    &to_vec(resource.get_propvals()),
+                    store,
+                    depth + 1,
+                )?;
+                out.push_str(&format!("{}</{}>\n", indent, tag));
+            }
+            // Arrays are ordered, so they become RDF Collections
+            Value::ResourceArray(items) => {
+                out.push_str(&format!(
+                    "{}<{} rdf:parseType=\"Collection\">\n",
+                    indent, tag
+                ));
+                for item in items {
+                    self.write_sub_resource(out, item, store, depth + 1)?;
+                }
+                out.push_str(&format!("{}</{}>\n", indent, tag));
+            }
+            // Every translation becomes a separate element
+            Value::LangString(map) => {
+                for (language, text) in map {
+                    out.push_str(&format!(
+                        "{}<{} xml:lang=\"{}\">{}</{}>\n",
+                        indent,
+                        tag,
+                        escape(language),
+                        escape(text),
+                        tag
+                    ));
+                }
+            }
+            other => {
+                let datatype = store
+                    .get_property(property)
+                    .map(|p| p.data_type)
+                    .unwrap_or_else(|_| other.datatype());
+                let datatype_attr = match datatype {
+                    DataType::String => "".into(),
+                    datatype => format!(
+                        " rdf:datatype=\"{}\"",
+                        escape(&super::rdf_literal_datatype(&datatype))
+                    ),
+                };
+                out.push_str(&format!(
+                    "{}<{}{}>{}</{}>\n",
+                    indent,
+                    tag,
+                    datatype_attr,
+                    escape(&other.to_string()),
+                    tag
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn write_sub_resource(
+        &mut self,
+        out: &mut String,
+        sub: &SubResource,
+        store: &impl Storelike,
+        depth: usize,
+    ) -> AtomicResult<()> {
+        match sub {
+            SubResource::Subject(subject) => {
+                out.push_str(&format!(
+                    "{}<rdf:Description rdf:about=\"{}\"/>\n",
+                    "  ".repeat(depth),
+                    escape(subject)
+                ));
+                Ok(())
+            }
+            SubResource::Resource(resource) => self.write_description(
+                out,
+                Some(resource.get_subject()),
+                &to_vec(resource.get_propvals()),
+                store,
+                depth,
+            ),
+            // Anonymous Nested Resources become blank nodes
+            SubResource::Nested(propvals) => {
+                self.write_description(out, None, &to_vec(propvals), store, depth)
+            }
+        }
+    }
+
+    /// Property elements need a qualified name, e.g. `atomic:description`.
+    /// Returns None if the property does not end with a valid XML name.
+    fn qname(&mut self, property: &str) -> Option<String> {
+        self.prefixes
+            .split_anywhere(property)
+            .map(|(label, local)| format!("{}:{}", label, local))
+    }
+}
+
+fn to_vec(propvals: &PropVals) -> Vec<(String, Value)> {
+    propvals
+        .iter()
+        .map(|(p, v)| (p.clone(), v.clone()))
+        .collect()
+}
+
+/// Escapes text for use in XML attributes and elements.
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::urls;
+
+    #[test]
+    fn serialize_rdf_xml() {
+        let store = crate::Store::init().unwrap();
+        store.populate().unwrap();
+        let mut nested = PropVals::new();
+        nested.insert(urls::NAME.into(), Value::String("<b> & co".into()));
+        let atoms = vec![
+            Atom::new(
+                "https://example.com/a".into(),
+                urls::IS_A.into(),
+                vec![urls::CLASS.to_string()].into(),
+            ),
+            Atom::new(
+                "https://example.com/a".into(),
+                urls::PARENT.into(),
+                nested.into(),
+            ),
+            Atom::new(
+                "https://example.com/a".into(),
+                urls::CREATED_AT.into(),
+                Value::Timestamp(1),
+            ),
+        ];
+        let xml = atoms_to_rdf_xml(atoms, &store).unwrap();
+        println!("{}", xml);
+        assert!(xml.contains("xmlns:atomic=\"https://atomicdata.dev/properties/\""));
+        assert!(xml.contains("<rdf:Description rdf:about=\"https://example.com/a\">"));
+        assert!(xml.contains(
+            "<atomic:createdAt rdf:datatype=\"https://atomicdata.dev/datatypes/timestamp\">1</atomic:createdAt>"
+        ));
+        assert!(xml.contains("<atomic:isA rdf:parseType=\"Collection\">"));
+        assert!(xml.contains("<atomic:name>&lt;b&gt; &amp; co</atomic:name>"));
+    }
+
+    #[test]
+    fn invalid_property_names() {
+        let store = crate::Store::init().unwrap();
+        let atom = |property: &str| {
+            Atom::new(
+                "https://example.com/a".into(),
+                property.into(),
+                Value::String("value".into()),
+            )
+        };
+        let atoms = vec![
+            atom("https://example.com/props/my%20prop"),
+            atom("https://example.com/props/123"),
+            atom(urls::NAME),
+        ];
+        let xml = atoms_to_rdf_xml(atoms, &store).unwrap();
+        println!("{}", xml);
+        assert!(xml.contains("xmlns:ns=\"https://example.com/props/my%20\""));
+        assert!(xml.contains("<ns:prop>value</ns:prop>"));
+        // Properties without any valid name at the end are skipped
+        assert!(!xml.contains("123"));
+        assert!(xml.contains("<atomic:name>value</atomic:name>"));
+    }
+}
@@ -0,0 +1,93 @@
+//! TriG serialization, which is Turtle with named graphs.
+//! Commits become named graphs, which describe the changes they made to their subject.
+//! https://www.w3.org/TR/trig/
+
+use super::turtle::TurtleWriter;
+use crate::{commit::Commit, errors::AtomicResult, urls, Atom, Resource, Storelike};
+
+/// Serializes Resources to TriG.
+/// Regular Resources (and the metadata of Commits) are written to the default graph.
+/// Every Commit gets a named graph (identified by the subject of the Commit) with the values it `set` and `push`ed.
+pub fn resources_to_trig(resources: &[Resource], store: &impl Storelike) -> AtomicResult<String> {
+    let mut writer = TurtleWriter::default();
+    let mut default_graph: Vec<Atom> = Vec::new();
+    let mut named_graphs: Vec<(String, Vec<Atom>)> = Vec::new();
+    for resource in resources {
+        let is_commit = resource.get_main_class().ok().as_deref() == Some(urls::COMMIT);
+        let commit = match is_commit {
+            true => Commit::from_resource(resource.clone()).ok(),
+            false => None,
+        };
+        let commit = match commit {
+            Some(commit) => commit,
+            None => {
+                default_graph.append(&mut resource.to_atoms()?);
+                continue;
+            }
+        };
+        // The changes are in the named graph, so they don't have to be repeated here
+        for atom in resource.to_atoms()? {
+            if atom.property != urls::SET && atom.property != urls::PUSH {
+                default_graph.push(atom);
+            }
+        }
+        let mut changes: Vec<Atom> = Vec::new();
+        for (property, value) in commit.set.iter().chain(commit.push.iter()).flatten() {
+            changes.push(Atom::new(
+                commit.subject.clone(),
+                property.clone(),
+                value.clone(),
+            ));
+        }
+        if !changes.is_empty() {
+            named_graphs.push((resource.get_subject().clone(), changes));
+        }
+    }
+
+    let mut body = String::new();
+    writer.write_atoms(&mut body, default_graph, store)?;
+    for (graph, atoms) in named_graphs {
+        let mut statements = String::new();
+        writer.write_atoms(&mut statements, atoms, store)?;
+        body.push_str(&writer.iri(&graph));
+        body.push_str(" {\n");
+        for line in statements.trim_end().lines() {
+            if !line.is_empty() {
+                body.push_str("    ");
+            }
+            body.push_str(line);
+            body.push('\n');
+        }
+        body.push_str("}\n\n");
+    }
+    Ok(format!("{}\n{}", writer.prefix_declarations(), body))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn serialize_trig() {
+        let store = crate::Store::init().unwrap();
+        store.populate().unwrap();
+        let agent = store.create_agent(Some("trig")).unwrap();
+        store.set_default_agent(agent);
+        let mut resource = Resource::new("https://localhost/trig".into());
+        resource
+            .set_propval_string(urls::NAME.into(), "TriG", &store)
+            .unwrap();
+        let commit = resource
+            .get_commit_builder()
+            .clone()
+            .sign(&store.get_default_agent().unwrap(), &store, &resource)
+            .unwrap();
+        let commit_resource = commit.into_resource(&store).unwrap();
+        let trig = resources_to_trig(&[resource, commit_resource], &store).unwrap();
+        println!("{}", trig);
+        assert!(trig.contains("localhost:trig atomic:name \"TriG\""));
+        // The Commit itself is in the default graph, its changes in a named graph
+        assert!(trig.contains("atomic:signer"));
+        assert!(trig.contains(" {\n    localhost:trig atomic:name \"TriG\" .\n}"));
+    }
+}
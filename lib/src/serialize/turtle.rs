@@ -0,0 +1,248 @@
+//! Turtle serialization, with `@prefix` declarations, grouped subjects and Nested Resources as blank nodes.
+//! https://www.w3.org/TR/turtle/
+
+use std::collections::{BTreeMap, HashSet};
+
+use super::prefixes::Prefixes;
+use crate::{
+    datatype::DataType, errors::AtomicResult, values::SubResource, Atom, Resource, Storelike, Value,
+};
+
+/// Serializes Atoms to Turtle.
+/// Atoms are grouped by subject, which are sorted.
+pub fn atoms_to_turtle(atoms: Vec<Atom>, store: &impl Storelike) -> AtomicResult<String> {
+    let mut writer = TurtleWriter::default();
+    let mut body = String::new();
+    writer.write_atoms(&mut body, atoms, store)?;
+    Ok(format!("{}\n{}", writer.prefix_declarations(), body))
+}
+
+/// Writes Turtle statements, and remembers which prefixes they use.
+/// Also used for TriG, which is Turtle with named graphs.
+#[derive(Default)]
+pub(super) struct TurtleWriter {
+    prefixes: Prefixes,
+    /// Named Resources that were embedded in a Value, which still need their own triples.
+    pending: Vec<Resource>,
+}
+
+impl TurtleWriter {
+    /// Writes the Atoms, grouped by subject, followed by any embedded Resources.
+    pub fn write_atoms(
+        &mut self,
+        out: &mut String,
+        atoms: Vec<Atom>,
+        store: &impl Storelike,
+    ) -> AtomicResult<()> {
+        let mut grouped: BTreeMap<String, Vec<(String, Value)>> = BTreeMap::new();
+        for atom in atoms {
+            grouped
+                .entry(atom.subject)
+                .or_default()
+                .push((atom.property, atom.value));
+        }
+        for (subject, propvals) in grouped.iter() {
+            self.write_subject(out, subject, propvals, store)?;
+        }
+        // Embedded named Resources are written after the ones that refer to them
+        let mut written: HashSet<String> = grouped.into_keys().collect();
+        while let Some(resource) = self.pending.pop() {
+            if !written.insert(resource.get_subject().into()) {
+                continue;
+            }
+            let propvals: Vec<(String, Value)> = resource
+                .get_propvals()
+                .iter()
+                .map(|(p, v)| (p.clone(), v.clone()))
+                .collect();
+            self.write_subject(out, resource.get_subject(), &propvals, store)?;
+        }
+        Ok(())
+    }
+
+    fn write_subject(
+        &mut self,
+        out: &mut String,
+        subject: &str,
+        propvals: &[(String, Value)],
+        store: &impl Storelike,
+    ) -> AtomicResult<()> {
+        out.push_str(&self.iri(subject));
+        out.push(' ');
+        self.write_predicates(out, propvals, store, 1)?;
+        out.push_str(" .\n\n");
+        Ok(())
+    }
+
+    /// Writes `predicate object ; predicate object`, one per line.
+    fn write_predicates(
+        &mut self,
+        out: &mut String,
+        propvals: &[(String, Value)],
+        store: &impl Storelike,
+        depth: usize,
+    ) -> AtomicResult<()> {
+        let indent = "    ".repeat(depth);
+        let mut sorted: Vec<&(String, Value)> = propvals.iter().collect();
+        sorted.sort_by(|a, b| a.0.cmp(&b.0));
+        for (i, (property, value)) in sorted.into_iter().enumerate() {
+            if i > 0 {
+                out.push_str(" ;\n");
+                out.push_str(&indent);
+            }
+            out.push_str(&self.iri(property));
+            out.push(' ');
+            let object = self.object(property, value, store, depth)?;
+            out.push_str(&object);
+        }
+        Ok(())
+    }
+
+    /// Serializes a Value to one or more (comma separated) Turtle objects.
+    fn object(
+        &mut self,
+        property: &str,
+        value: &Value,
+        store: &impl Storelike,
+        depth: usize,
+    ) -> AtomicResult<String> {
+        let object = match value {
+            Value::AtomicUrl(url) => self.iri(url),
+            Value::NestedResource(sub) => self.sub_resource(sub, store, depth)?,
+            Value::Resource(resource) => {
+                self.pending.push(resource.clone());
+                self.iri(resource.get_subject())
+            }
+            // Arrays are ordered, so they become RDF Collections
+            Value::ResourceArray(items) => {
+                let mut objects = Vec::new();
+                for item in items {
+                    objects.push(self.sub_resource(item, store, depth)?);
+                }
+                format!("( {} )", objects.join(" "))
+            }
+            Value::LangString(map) => map
+                .iter()
+                .map(|(language, text)| format!("{}@{}", quote(text), language))
+                .collect::<Vec<String>>()
+                .join(", "),
+            other => {
+                let datatype = store
+                    .get_property(property)
+                    .map(|p| p.data_type)
+                    .unwrap_or_else(|_| other.datatype());
+                match datatype {
+                    DataType::String => quote(&other.to_string()),
+                    datatype => format!(
+                        "{}^^{}",
+                        quote(&other.to_string()),
+                        self.iri(&super::rdf_literal_datatype(&datatype))
+                    ),
+                }
+            }
+        };
+        Ok(object)
+    }
+
+    fn sub_resource(
+        &mut self,
+        sub: &SubResource,
+        store: &impl Storelike,
+        depth: usize,
+    ) -> AtomicResult<String> {
+        let object = match sub {
+            SubResource::Subject(subject) => self.iri(subject),
+            SubResource::Resource(resource) => {
+                self.pending.push(*resource.clone());
+                self.iri(resource.get_subject())
+            }
+            // Anonymous Nested Resources become blank nodes
+            SubResource::Nested(propvals) => {
+                let propvals: Vec<(String, Value)> = propvals
+                    .iter()
+                    .map(|(p, v)| (p.clone(), v.clone()))
+                    .collect();
+                let mut out = String::from("[\n");
+                out.push_str(&"    ".repeat(depth + 1));
+                self.write_predicates(&mut out, &propvals, store, depth + 1)?;
+                out.push('\n');
+                out.push_str(&"    ".repeat(depth));
+                out.push(']');
+                out
+            }
+        };
+        Ok(object)
+    }
+
+    /// Returns a prefixed name (e.g. `atomic:description`) if possible, or the full IRI in angle brackets.
+    pub fn iri(&mut self, iri: &str) -> String {
+        match self.prefixes.split(iri) {
+            Some((label, local)) => format!("{}:{}", label, local),
+            None => format!("<{}>", iri.replace('>', "%3E")),
+        }
+    }
+
+    pub fn prefix_declarations(&self) -> String {
+        self.prefixes
+            .sorted()
+            .into_iter()
+            .map(|(label, namespace)| format!("@prefix {}: <{}> .\n", label, namespace))
+            .collect()
+    }
+}
+
+/// Creates a Turtle string literal.
+fn quote(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t");
+    format!("\"{}\"", escaped)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::urls;
+
+    #[test]
+    fn serialize_turtle() {
+        let store = crate::Store::init().unwrap();
+        store.populate().unwrap();
+        let agent = store.get_resource(urls::AGENT).unwrap();
+        let turtle = atoms_to_turtle(agent.to_atoms().unwrap(), &store).unwrap();
+        println!("{}", turtle);
+        assert!(turtle.contains("@prefix atomic: <https://atomicdata.dev/properties/> ."));
+        assert!(turtle.contains("@prefix classes: <https://atomicdata.dev/classes/> ."));
+        assert!(turtle.contains("\nclasses:Agent atomic:"));
+        assert!(turtle.contains("atomic:shortname \"agent\""));
+        assert!(turtle.contains("atomic:isA ( classes:Class )"));
+    }
+
+    #[test]
+    fn serialize_turtle_nested_and_typed() {
+        let store = crate::Store::init().unwrap();
+        store.populate().unwrap();
+        let mut nested = crate::resources::PropVals::new();
+        nested.insert(urls::NAME.into(), Value::String("Line \"1\"\n".into()));
+        let atoms = vec![
+            Atom::new(
+                "https://example.com/a".into(),
+                urls::CREATED_AT.into(),
+                Value::Timestamp(1),
+            ),
+            Atom::new(
+                "https://example.com/a".into(),
+                urls::ATTACHMENTS.into(),
+                Value::ResourceArray(vec![SubResource::Nested(nested)]),
+            ),
+        ];
+        let turtle = atoms_to_turtle(atoms, &store).unwrap();
+        println!("{}", turtle);
+        assert!(turtle.contains("atomic:createdAt \"1\"^^datatypes:timestamp"));
+        assert!(turtle.contains("atomic:name \"Line \\\"1\\\"\\n\""));
+        assert!(turtle.contains("( [\n"));
+    }
+}
@@ -0,0 +1,79 @@
+//! Sessions let browsers authenticate without signing every request, e.g. for images and downloads.
+//! An Agent proves that it owns its key once, after which the server hands out a signed, expiring session.
+//! The server signs sessions using its default Agent. Sessions can be revoked, like [crate::tokens::CapabilityToken]s.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{commit::sign_message, errors::AtomicResult, Storelike};
+
+/// All serialized sessions start with this.
+pub const SESSION_PREFIX: &str = "atomic-session.";
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Session {
+    /// Random identifier, used for revoking the session.
+    pub id: String,
+    /// Subject of the Agent that is authenticated by the session.
+    pub agent: String,
+    /// Unix timestamp (ms) of when the session was signed
+    pub issued_at: i64,
+    /// Unix timestamp (ms) after which the session is no longer accepted
+    pub expires_at: i64,
+}
+
+impl Session {
+    pub fn new(agent: &str, expires_at: i64) -> Session {
+        Session {
+            id: crate::utils::random_string(16),
+            agent: agent.into(),
+            issued_at: crate::utils::now(),
+            expires_at,
+        }
+    }
+
+    /// Signs the session with the default Agent of the Store, and serializes it.
+    pub fn sign(&self, store: &impl Storelike) -> AtomicResult<String> {
+        let signer = store.get_default_agent()?;
+        let private_key = signer
+            .private_key
+            .as_ref()
+            .ok_or("No private key in the default Agent, can't sign session")?;
+        let payload = base64::encode_config(serde_json::to_vec(self)?, base64::URL_SAFE_NO_PAD);
+        let signature = sign_message(&payload, private_key, &signer.public_key)?;
+        Ok(format!("{}{}.{}", SESSION_PREFIX, payload, signature))
+    }
+
+    /// Parses a serialized session and checks its signature, expiry and whether it has been revoked.
+    pub fn verify(session: &str, store: &impl Storelike) -> AtomicResult<Session> {
+        let (payload, signature) = session
+            .strip_prefix(SESSION_PREFIX)
+            .and_then(|s| s.split_once('.'))
+            .ok_or("Invalid session. Sessions look like `atomic-session.{payload}.{signature}`")?;
+        let bytes = base64::decode_config(payload, base64::URL_SAFE_NO_PAD)?;
+        let parsed: Session = serde_json::from_slice(&bytes)
+            .map_err(|e| format!("Invalid session payload. {}", e))?;
+        if parsed.expires_at <= crate::utils::now() {
+            return Err(crate::errors::AtomicError::unauthorized(
+                "Session has expired".into(),
+            ));
+        }
+        if store.is_token_revoked(&parsed.id)? {
+            return Err(crate::errors::AtomicError::unauthorized(
+                "Session has been revoked".into(),
+            ));
+        }
+        let signer = store.get_resource(&store.get_default_agent()?.subject)?;
+        crate::agents::verify_agent_signature(&signer, payload, signature, parsed.issued_at, true)
+            .map_err(|e| format!("Invalid session signature. {}", e))?;
+        Ok(parsed)
+    }
+}
+
+/// Reads the Agent of a serialized session, without checking its signature.
+/// Only use this for logging and rate limiting, never for authorization.
+pub fn session_agent(session: &str) -> Option<String> {
+    let payload = session.strip_prefix(SESSION_PREFIX)?.split_once('.')?.0;
+    let bytes = base64::decode_config(payload, base64::URL_SAFE_NO_PAD).ok()?;
+    let parsed: Session = serde_json::from_slice(&bytes).ok()?;
+    Some(parsed.agent)
+}
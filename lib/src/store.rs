@@ -5,7 +5,10 @@ use crate::{
     atoms::Atom,
     storelike::{ResourceCollection, Storelike},
 };
-use crate::{errors::AtomicResult, Resource};
+use crate::{
+    errors::{AtomicError, AtomicResult},
+    Resource,
+};
 use std::{collections::HashMap, sync::Arc, sync::Mutex};
 
 /// The in-memory store of data, containing the Resources, Properties and Classes
@@ -107,6 +110,15 @@ impl Storelike for Store {
         self.handle_not_found(subject, "Not found in HashMap.".into())
     }
 
+    fn get_resource_local(&self, subject: &str) -> AtomicResult<Resource> {
+        self.hashmap
+            .lock()
+            .unwrap()
+            .get(subject)
+            .cloned()
+            .ok_or_else(|| AtomicError::not_found(format!("Not found in HashMap: {}", subject)))
+    }
+
     fn remove_resource(&self, subject: &str) -> AtomicResult<()> {
         self.hashmap
             .lock()
@@ -171,6 +183,16 @@ mod test {
         resource.to_json_ad().unwrap();
     }
 
+    #[test]
+    fn export_turtle() {
+        let store = init_store();
+        let turtle = store
+            .export_format(true, &crate::serialize::Format::Turtle)
+            .unwrap();
+        assert!(turtle.starts_with("@prefix"));
+        assert!(turtle.contains("classes:Class "));
+    }
+
     #[test]
     fn tpf() {
         let store = init_store();
@@ -227,6 +249,28 @@ mod test {
         }
     }
 
+    #[test]
+    fn path_nested() {
+        let store = init_store();
+        let mut nested = crate::resources::PropVals::new();
+        nested.insert(urls::NAME.into(), Value::String("nested".into()));
+        let mut resource = Resource::new("https://localhost/with-nested".into());
+        resource.set_propval_unsafe(urls::PARENT.into(), nested.clone().into());
+        resource.set_propval_unsafe(urls::IS_A.into(), Value::ResourceArray(vec![nested.into()]));
+        store.add_resource(&resource).unwrap();
+        for path in [
+            "https://localhost/with-nested parent name",
+            "https://localhost/with-nested is-a 0 name",
+        ] {
+            match store.get_path(path, None, None).unwrap() {
+                crate::storelike::PathReturn::Subject(_) => panic!("Should be an Atom"),
+                crate::storelike::PathReturn::Atom(atom) => {
+                    assert_eq!(atom.value.to_string(), "nested");
+                }
+            }
+        }
+    }
+
     #[test]
     fn get_external_resource() {
         let store = Store::init().unwrap();
@@ -6,7 +6,7 @@ use crate::{
     errors::AtomicError,
     hierarchy,
     schema::{Class, Property},
-    values::query_value_compare,
+    values::{query_value_compare, SubResource},
 };
 use crate::{errors::AtomicResult, parse::parse_json_ad_array};
 use crate::{mapping::Mapping, values::Value, Atom, Resource};
@@ -102,13 +102,57 @@ pub trait Storelike: Sized {
     fn create_agent(&self, name: Option<&str>) -> AtomicResult<crate::agents::Agent> {
         let agent = Agent::new(name, self)?;
         self.add_resource(&agent.to_resource(self)?)?;
+        self.add_audit_entry(&crate::audit::AuditEntry::new(
+            crate::audit::AuditAction::CreateAgent,
+            &agent.subject,
+            &agent.subject,
+            true,
+            None,
+        ));
         Ok(agent)
     }
 
     /// Exports the store to a big JSON-AD file.
     /// Sorts the export by first exporting Property Resources, which makes importing faster and more dependent.
+    /// Use [Storelike::export_format] for other formats.
     fn export(&self, include_external: bool) -> AtomicResult<String> {
+        self.export_format(include_external, &crate::serialize::Format::JsonAd)
+    }
+
+    /// Exports the store to a big JSON-AD, Turtle, N-Triples, RDF/XML or TriG file.
+    /// TriG exports contain the changes of every Commit as a named graph.
+    /// Sorts JSON-AD exports by first exporting Property Resources, which makes importing faster and more dependent.
+    fn export_format(
+        &self,
+        include_external: bool,
+        format: &crate::serialize::Format,
+    ) -> AtomicResult<String> {
+        use crate::serialize::Format;
+
         let resources = self.all_resources(include_external);
+        if let Format::TriG = format {
+            return crate::serialize::resources_to_trig(&resources, self);
+        }
+        if !matches!(format, Format::JsonAd) {
+            let mut atoms: Vec<Atom> = Vec::new();
+            for r in resources.iter() {
+                atoms.append(&mut r.to_atoms()?);
+            }
+            return match format {
+                Format::Turtle => crate::serialize::atoms_to_turtle(atoms, self),
+                Format::RdfXml => crate::serialize::atoms_to_rdf_xml(atoms, self),
+                #[cfg(feature = "rdf")]
+                Format::NTriples => crate::serialize::atoms_to_ntriples(atoms, self),
+                #[cfg(not(feature = "rdf"))]
+                Format::NTriples => {
+                    Err("N-Triples exports require the `rdf` feature of atomic_lib".into())
+                }
+                _ => Err(
+                    "Exports are only available as JSON-AD, Turtle, N-Triples, RDF/XML or TriG"
+                        .into(),
+                ),
+            };
+        }
         let mut properties: Vec<Resource> = Vec::new();
         let mut other_resources: Vec<Resource> = Vec::new();
         for r in resources {
@@ -139,6 +183,12 @@ pub trait Storelike: Sized {
     /// If you're not sure what to use, use `get_resource_extended`.
     fn get_resource(&self, subject: &str) -> AtomicResult<Resource>;
 
+    /// Returns the Resource only if it is in this Store, so it never fetches it from the web.
+    /// Defaults to `get_resource`. Stores that fetch missing Resources should override this.
+    fn get_resource_local(&self, subject: &str) -> AtomicResult<Resource> {
+        self.get_resource(subject)
+    }
+
     /// Returns an existing resource, or creates a new one with the given Subject
     fn get_resource_new(&self, subject: &str) -> Resource {
         match self.get_resource(subject) {
@@ -207,6 +257,50 @@ pub trait Storelike: Sized {
         self.fetch_resource(subject)
     }
 
+    /// Returns the outcome of an earlier [crate::hierarchy::check_rights] for this subject, agent and right, if the Store caches these.
+    fn get_cached_rights(
+        &self,
+        _subject: &str,
+        _agent: &str,
+        _right: &str,
+    ) -> Option<AtomicResult<String>> {
+        None
+    }
+
+    /// Stores the outcome of [crate::hierarchy::check_rights] for this subject, agent and right.
+    /// The `dependencies` are the subjects whose rights were used, changes to these should invalidate the entry.
+    fn cache_rights(
+        &self,
+        _subject: &str,
+        _agent: &str,
+        _right: &str,
+        _outcome: &AtomicResult<String>,
+        _dependencies: &[String],
+    ) {
+    }
+
+    /// Returns the subjects of all Groups in this store, used by [crate::hierarchy::GroupResolver].
+    /// Stores can override this to cache the Groups.
+    fn get_groups(&self) -> AtomicResult<std::sync::Arc<std::collections::HashSet<String>>> {
+        Ok(std::sync::Arc::new(hierarchy::find_groups(self)?))
+    }
+
+    /// Checks the revocation list for a [crate::tokens::CapabilityToken] or [crate::sessions::Session].
+    /// Stores without a revocation list don't revoke tokens, they only expire.
+    fn is_token_revoked(&self, _token_id: &str) -> AtomicResult<bool> {
+        Ok(false)
+    }
+
+    /// Adds `delta` bytes to the usage counter of the Drive.
+    /// Stores that don't keep counters ignore it.
+    fn add_drive_usage(&self, _drive: &str, _delta: i64) -> AtomicResult<()> {
+        Ok(())
+    }
+
+    /// Appends an entry to the audit log.
+    /// Stores without an audit log ignore it.
+    fn add_audit_entry(&self, _entry: &crate::audit::AuditEntry) {}
+
     /// Imports a JSON-AD string, returns the amount of imported resources
     fn import(&self, string: &str) -> AtomicResult<usize> {
         let vec = parse_json_ad_array(string, self, true)
@@ -363,7 +457,7 @@ pub trait Storelike: Sized {
                                 )
                             }
                         };
-                        let url: String = vector
+                        let item = vector
                             .get(i as usize)
                             .ok_or(format!(
                                 "Too high index {} for array with length {}, max is {}",
@@ -371,9 +465,23 @@ pub trait Storelike: Sized {
                                 vector.len(),
                                 vector.len() - 1
                             ))?
-                            .to_string();
-                        subject = url;
-                        resource = self.get_resource_extended(&subject, false, for_agent)?;
+                            .clone();
+                        match item {
+                            SubResource::Subject(url) => {
+                                subject = url;
+                                resource =
+                                    self.get_resource_extended(&subject, false, for_agent)?;
+                            }
+                            SubResource::Resource(r) => {
+                                subject = r.get_subject().clone();
+                                resource = *r;
+                            }
+                            // Anonymous Nested Resources are identified by their path
+                            SubResource::Nested(propvals) => {
+                                subject = format!("{} {} {}", atom.subject, atom.property, i);
+                                resource = Resource::from_propvals(propvals, subject.clone());
+                            }
+                        }
                         current = PathReturn::Subject(subject.clone());
                         continue;
                     }
@@ -385,9 +493,22 @@ pub trait Storelike: Sized {
             // Since the selector isn't an array index, we can assume it's a property URL
             match current {
                 PathReturn::Subject(_) => {}
-                PathReturn::Atom(_) => {
-                    return Err("No more linked resources down this path.".into())
-                }
+                // Embedded Resources can be traversed without fetching anything
+                PathReturn::Atom(atom) => match atom.value {
+                    Value::NestedResource(SubResource::Nested(propvals)) => {
+                        subject = format!("{} {}", atom.subject, atom.property);
+                        resource = Resource::from_propvals(propvals, subject.clone());
+                    }
+                    Value::NestedResource(SubResource::Resource(r)) => {
+                        subject = r.get_subject().clone();
+                        resource = *r;
+                    }
+                    Value::Resource(r) => {
+                        subject = r.get_subject().clone();
+                        resource = r;
+                    }
+                    _ => return Err("No more linked resources down this path.".into()),
+                },
             }
             // Set the parent for the next loop equal to the next node.
             // TODO: skip this step if the current iteration is the last one
@@ -443,7 +564,7 @@ pub trait Storelike: Sized {
                 Err(e) => match e.error_type {
                     crate::AtomicErrorType::NotFoundError => {}
                     crate::AtomicErrorType::UnauthorizedError => {}
-                    crate::AtomicErrorType::OtherError => {
+                    crate::AtomicErrorType::SchemaError | crate::AtomicErrorType::OtherError => {
                         return Err(
                             format!("Error when getting resource in collection: {}", e).into()
                         )
@@ -0,0 +1,152 @@
+//! Capability Tokens are signed, expiring grants that an Agent can hand out to scripts and third party integrations.
+//! They are an alternative to signing every request with the `x-atomic-*` headers.
+//! A token can only grant a subset of the rights of the Agent that signed it, limited to a subtree of the hierarchy.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    agents::Agent, commit::sign_message, errors::AtomicResult, hierarchy::Right, Resource,
+    Storelike,
+};
+
+/// All serialized tokens start with this, which makes it possible to tell them apart from Agent subjects.
+pub const TOKEN_PREFIX: &str = "atomic-token.";
+
+/// The signed contents of a token.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct CapabilityToken {
+    /// Random identifier, used for revoking the token.
+    pub id: String,
+    /// Subject of the Agent that signed the token. Requests using the token are done on its behalf.
+    pub issuer: String,
+    /// The Resource (and its children) that the token grants access to.
+    pub scope: String,
+    /// URLs of the [Right]s that are granted, e.g. `https://atomicdata.dev/properties/read`
+    pub rights: Vec<String>,
+    /// Unix timestamp (ms) of when the token was signed
+    pub issued_at: i64,
+    /// Unix timestamp (ms) after which the token is no longer accepted
+    pub expires_at: i64,
+}
+
+impl CapabilityToken {
+    pub fn new(issuer: &str, scope: &str, rights: &[Right], expires_at: i64) -> CapabilityToken {
+        CapabilityToken {
+            id: crate::utils::random_string(16),
+            issuer: issuer.into(),
+            scope: scope.into(),
+            rights: rights.iter().map(|r| r.to_string()).collect(),
+            issued_at: crate::utils::now(),
+            expires_at,
+        }
+    }
+
+    /// Signs the token with the private key of the Agent, and serializes it.
+    /// The Agent must be the issuer.
+    pub fn sign(&self, agent: &Agent) -> AtomicResult<String> {
+        if agent.subject != self.issuer {
+            return Err(format!(
+                "Token can only be signed by its issuer {}, not {}",
+                self.issuer, agent.subject
+            )
+            .into());
+        }
+        let private_key = agent
+            .private_key
+            .as_ref()
+            .ok_or("No private key in Agent, can't sign token")?;
+        let payload = base64::encode_config(serde_json::to_vec(self)?, base64::URL_SAFE_NO_PAD);
+        let signature = sign_message(&payload, private_key, &agent.public_key)?;
+        Ok(format!("{}{}.{}", TOKEN_PREFIX, payload, signature))
+    }
+
+    /// Parses a serialized token and checks its signature, expiry and whether it has been revoked.
+    pub fn verify(token: &str, store: &impl Storelike) -> AtomicResult<CapabilityToken> {
+        let (payload, signature) = token
+            .strip_prefix(TOKEN_PREFIX)
+            .and_then(|t| t.split_once('.'))
+            .ok_or("Invalid token. Tokens look like `atomic-token.{payload}.{signature}`")?;
+        let bytes = base64::decode_config(payload, base64::URL_SAFE_NO_PAD)?;
+        let parsed: CapabilityToken =
+            serde_json::from_slice(&bytes).map_err(|e| format!("Invalid token payload. {}", e))?;
+        if parsed.expires_at <= crate::utils::now() {
+            return Err(crate::errors::AtomicError::unauthorized(format!(
+                "Token {} has expired",
+                parsed.id
+            )));
+        }
+        if store.is_token_revoked(&parsed.id)? {
+            return Err(crate::errors::AtomicError::unauthorized(format!(
+                "Token {} has been revoked",
+                parsed.id
+            )));
+        }
+        let issuer = store.get_resource(&parsed.issuer)?;
+        crate::agents::verify_agent_signature(&issuer, payload, signature, parsed.issued_at, true)
+            .map_err(|e| format!("Invalid token signature. {}", e))?;
+        Ok(parsed)
+    }
+
+    /// Whether the Resource is the scope of the token, or one of its descendants.
+    /// Keeps track of visited Resources, so circular hierarchies don't cause an endless loop.
+    pub fn in_scope(&self, resource: &Resource, store: &impl Storelike) -> bool {
+        let mut current = resource.clone();
+        let mut visited = std::collections::HashSet::new();
+        loop {
+            if current.get_subject() == &self.scope {
+                return true;
+            }
+            if !visited.insert(current.get_subject().clone()) {
+                return false;
+            }
+            match current.get_parent(store) {
+                Ok(parent) => current = parent,
+                Err(_) => return false,
+            }
+        }
+    }
+}
+
+/// Whether the `for_agent` string is a serialized token instead of an Agent subject.
+pub fn is_token(for_agent: &str) -> bool {
+    for_agent.starts_with(TOKEN_PREFIX)
+}
+
+/// Reads the issuer of a serialized token, without checking its signature.
+/// Only use this for logging, never for authorization.
+pub fn token_issuer(token: &str) -> Option<String> {
+    let payload = token.strip_prefix(TOKEN_PREFIX)?.split_once('.')?.0;
+    let bytes = base64::decode_config(payload, base64::URL_SAFE_NO_PAD).ok()?;
+    let parsed: CapabilityToken = serde_json::from_slice(&bytes).ok()?;
+    Some(parsed.issuer)
+}
+
+/// Checks the rights of a request that uses a token.
+/// The token needs to grant the right for this Resource, and its issuer needs to have the right too.
+pub fn check_token_rights(
+    store: &impl Storelike,
+    resource: &Resource,
+    token: &str,
+    right: Right,
+) -> AtomicResult<String> {
+    let token = CapabilityToken::verify(token, store)?;
+    if !token.rights.contains(&right.to_string()) {
+        return Err(crate::errors::AtomicError::unauthorized(format!(
+            "Token {} does not grant the {} right",
+            token.id, right
+        )));
+    }
+    if !token.in_scope(resource, store) {
+        return Err(crate::errors::AtomicError::unauthorized(format!(
+            "{} is outside the scope of token {}, which is limited to {}",
+            resource.get_subject(),
+            token.id,
+            token.scope
+        )));
+    }
+    let explanation = crate::hierarchy::check_rights(store, resource, &token.issuer, right)?;
+    Ok(format!(
+        "Token {} of {}: {}",
+        token.id, token.issuer, explanation
+    ))
+}
@@ -6,9 +6,11 @@ pub const PROPERTY: &str = "https://atomicdata.dev/classes/Property";
 pub const DATATYPE_CLASS: &str = "https://atomicdata.dev/classes/Datatype";
 pub const COMMIT: &str = "https://atomicdata.dev/classes/Commit";
 pub const AGENT: &str = "https://atomicdata.dev/classes/Agent";
+pub const AGENT_KEY: &str = "https://atomicdata.dev/classes/AgentKey";
 pub const COLLECTION: &str = "https://atomicdata.dev/classes/Collection";
 pub const ENDPOINT: &str = "https://atomicdata.dev/classes/Endpoint";
 pub const DRIVE: &str = "https://atomicdata.dev/classes/Drive";
+pub const GROUP: &str = "https://atomicdata.dev/classes/Group";
 pub const INVITE: &str = "https://atomicdata.dev/classes/Invite";
 pub const REDIRECT: &str = "https://atomicdata.dev/classes/Redirect";
 pub const ATOM: &str = "https://atomicdata.dev/classes/Atom";
@@ -28,6 +30,12 @@ pub const IS_LOCKED: &str = "https://atomicdata.dev/properties/isLocked";
 pub const DATATYPE_PROP: &str = "https://atomicdata.dev/properties/datatype";
 pub const CLASSTYPE_PROP: &str = "https://atomicdata.dev/properties/classtype";
 pub const ALLOWS_ONLY: &str = "https://atomicdata.dev/properties/allowsOnly";
+pub const MINIMUM: &str = "https://atomicdata.dev/properties/minimum";
+pub const MAXIMUM: &str = "https://atomicdata.dev/properties/maximum";
+pub const MIN_LENGTH: &str = "https://atomicdata.dev/properties/minLength";
+pub const MAX_LENGTH: &str = "https://atomicdata.dev/properties/maxLength";
+pub const PATTERN: &str = "https://atomicdata.dev/properties/pattern";
+pub const UNIQUE_WITHIN_PARENT: &str = "https://atomicdata.dev/properties/uniqueWithinParent";
 // ... for Classes
 pub const REQUIRES: &str = "https://atomicdata.dev/properties/requires";
 pub const RECOMMENDS: &str = "https://atomicdata.dev/properties/recommends";
@@ -46,6 +54,11 @@ pub const LAST_COMMIT: &str = "https://atomicdata.dev/properties/lastCommit";
 pub const PUBLIC_KEY: &str = "https://atomicdata.dev/properties/publicKey";
 pub const NAME: &str = "https://atomicdata.dev/properties/name";
 pub const DRIVES: &str = "https://atomicdata.dev/properties/drives";
+pub const AGENT_KEYS: &str = "https://atomicdata.dev/properties/agentKeys";
+pub const VALID_FROM: &str = "https://atomicdata.dev/properties/validFrom";
+pub const VALID_UNTIL: &str = "https://atomicdata.dev/properties/validUntil";
+pub const REVOKED_AT: &str = "https://atomicdata.dev/properties/revokedAt";
+pub const TOKEN: &str = "https://atomicdata.dev/properties/token";
 // ... for Collections
 pub const COLLECTION_PROPERTY: &str = "https://atomicdata.dev/properties/collection/property";
 pub const COLLECTION_VALUE: &str = "https://atomicdata.dev/properties/collection/value";
@@ -69,22 +82,54 @@ pub const PATH: &str = "https://atomicdata.dev/properties/path";
 pub const SEARCH_QUERY: &str = "https://atomicdata.dev/properties/search/query";
 pub const SEARCH_LIMIT: &str = "https://atomicdata.dev/properties/search/limit";
 pub const SEARCH_PROPERTY: &str = "https://atomicdata.dev/properties/search/property";
+// ... for Validation
+pub const VALIDATION_RESOURCE_COUNT: &str =
+    "https://atomicdata.dev/properties/validation/resourceCount";
+pub const VALIDATION_ATOM_COUNT: &str = "https://atomicdata.dev/properties/validation/atomCount";
+pub const VALIDATION_ERRORS: &str = "https://atomicdata.dev/properties/validation/errors";
+pub const VALIDATION_CODE: &str = "https://atomicdata.dev/properties/validation/code";
 // ... for Hierarchy / Drive
 pub const PARENT: &str = "https://atomicdata.dev/properties/parent";
 pub const READ: &str = "https://atomicdata.dev/properties/read";
 pub const WRITE: &str = "https://atomicdata.dev/properties/write";
 pub const APPEND: &str = "https://atomicdata.dev/properties/append";
 pub const CHILDREN: &str = "https://atomicdata.dev/properties/children";
+pub const GROUP_MEMBERS: &str = "https://atomicdata.dev/properties/members";
+pub const DENY_READ: &str = "https://atomicdata.dev/properties/denyRead";
+pub const DENY_WRITE: &str = "https://atomicdata.dev/properties/denyWrite";
+pub const DENY_APPEND: &str = "https://atomicdata.dev/properties/denyAppend";
+pub const STOP_INHERITANCE: &str = "https://atomicdata.dev/properties/stopInheritance";
+pub const PERMISSIONS_AGENT: &str = "https://atomicdata.dev/properties/permissions/agent";
+pub const PERMISSIONS_RIGHTS: &str = "https://atomicdata.dev/properties/permissions/rights";
+pub const PERMISSIONS_RIGHT: &str = "https://atomicdata.dev/properties/permissions/right";
+pub const PERMISSIONS_ALLOWED: &str = "https://atomicdata.dev/properties/permissions/allowed";
+pub const PERMISSIONS_CHAIN: &str = "https://atomicdata.dev/properties/permissions/chain";
+pub const PERMISSIONS_AGENTS_WITH_ACCESS: &str =
+    "https://atomicdata.dev/properties/permissions/agentsWithAccess";
+// ... for the Audit log
+pub const AUDIT_ENTRIES: &str = "https://atomicdata.dev/properties/audit/entries";
+pub const AUDIT_AGENT: &str = "https://atomicdata.dev/properties/audit/agent";
+pub const AUDIT_SUBJECT: &str = "https://atomicdata.dev/properties/audit/subject";
+pub const AUDIT_ACTION: &str = "https://atomicdata.dev/properties/audit/action";
+pub const AUDIT_ALLOWED: &str = "https://atomicdata.dev/properties/audit/allowed";
+pub const AUDIT_FROM: &str = "https://atomicdata.dev/properties/audit/from";
+pub const AUDIT_TO: &str = "https://atomicdata.dev/properties/audit/to";
+pub const AUDIT_LIMIT: &str = "https://atomicdata.dev/properties/audit/limit";
 // ... for Inivtations
 pub const DESTINATION: &str = "https://atomicdata.dev/properties/destination";
 pub const TARGET: &str = "https://atomicdata.dev/properties/invite/target";
 pub const USAGES_LEFT: &str = "https://atomicdata.dev/properties/invite/usagesLeft";
-pub const USED_BY: &str = "https://atomicdata.dev/properties/invite/usedBy";
+pub const USED_BY: &str = "https://atomicdata.dev/properties/invite/users";
 pub const WRITE_BOOL: &str = "https://atomicdata.dev/properties/invite/write";
 pub const INVITE_PUBKEY: &str = "https://atomicdata.dev/properties/invite/publicKey";
 pub const INVITE_AGENT: &str = "https://atomicdata.dev/properties/invite/agent";
 pub const REDIRECT_AGENT: &str = "https://atomicdata.dev/properties/invite/redirectAgent";
 pub const EXPIRES_AT: &str = "https://atomicdata.dev/properties/invite/expiresAt";
+pub const INVITE_ROLE: &str = "https://atomicdata.dev/properties/invite/role";
+pub const INVITE_GROUP: &str = "https://atomicdata.dev/properties/invite/group";
+pub const INVITE_REVOKED: &str = "https://atomicdata.dev/properties/invite/revoked";
+pub const INVITE_ACCEPTANCES: &str = "https://atomicdata.dev/properties/invite/acceptances";
+pub const INVITE_CODE: &str = "https://atomicdata.dev/properties/invite/code";
 // ... for Atoms
 pub const ATOM_SUBJECT: &str = "https://atomicdata.dev/properties/atom/subject";
 pub const ATOM_PROPERTY: &str = "https://atomicdata.dev/properties/atom/property";
@@ -96,6 +141,8 @@ pub const FILESIZE: &str = "https://atomicdata.dev/properties/filesize";
 pub const MIMETYPE: &str = "https://atomicdata.dev/properties/mimetype";
 pub const INTERNAL_ID: &str = "https://atomicdata.dev/properties/internalId";
 pub const DOWNLOAD_URL: &str = "https://atomicdata.dev/properties/downloadURL";
+pub const QUOTA_USAGE: &str = "https://atomicdata.dev/properties/quotaUsage";
+pub const SEARCH_LANGUAGE: &str = "https://atomicdata.dev/properties/searchLanguage";
 pub const ATTACHMENTS: &str = "https://atomicdata.dev/properties/attachments";
 // ... for ChatRooms and Messages
 pub const MESSAGES: &str = "https://atomicdata.dev/properties/messages";
@@ -112,6 +159,13 @@ pub const RESOURCE_ARRAY: &str = "https://atomicdata.dev/datatypes/resourceArray
 pub const BOOLEAN: &str = "https://atomicdata.dev/datatypes/boolean";
 pub const DATE: &str = "https://atomicdata.dev/datatypes/date";
 pub const TIMESTAMP: &str = "https://atomicdata.dev/datatypes/timestamp";
+pub const URI: &str = "https://atomicdata.dev/datatypes/uri";
+pub const JSON: &str = "https://atomicdata.dev/datatypes/json";
+pub const DECIMAL: &str = "https://atomicdata.dev/datatypes/decimal";
+pub const DURATION: &str = "https://atomicdata.dev/datatypes/duration";
+pub const GEO_POINT: &str = "https://atomicdata.dev/datatypes/geoPoint";
+pub const NESTED_RESOURCE: &str = "https://atomicdata.dev/datatypes/nestedResource";
+pub const LANG_STRING: &str = "https://atomicdata.dev/datatypes/langString";
 
 // Methods
 pub const INSERT: &str = "https://atomicdata.dev/methods/insert";
@@ -1,156 +1,481 @@
 //! Validate the Store and create a ValidationReport.
-//! Might be deprecated soon, as Validation hasn't been necessary since parsing has built-in data validation.
+//! Also contains [validate_resource_schema], which checks a single Resource against its Classes.
 
-/// Checks Atomic Data in the store for validity.
-/// Returns an Error if it is not valid.
+use serde::Serialize;
+
+use crate::{errors::AtomicResult, values::SubResource, Resource, Storelike, Value};
+
+/// Checks all Atomic Data in the store for validity, and returns a [ValidationReport].
+/// Does not stop at the first error, and does not print anything.
 ///
 /// Validates:
 ///
 /// - [X] If the Values can be parsed using their Datatype (e.g. if Integers are integers)
 /// - [X] If all required fields of the class are present
-/// - [X] If the URLs are publicly accessible
-/// - [ ] ..and return the right type of data?
-/// - [X] Returns a report, instead of throwing an error
-#[allow(dead_code, unreachable_code)]
-pub fn validate_store(
-    store: &impl crate::Storelike,
-    fetch_items: bool,
-) -> crate::validate::ValidationReport {
-    type Error = String;
-    let mut resource_count: u8 = 0;
-    let mut atom_count: u8 = 0;
-    let mut unfetchable: Vec<(String, Error)> = Vec::new();
-    let mut invalid_value: Vec<(crate::Atom, Error)> = Vec::new();
-    let mut unfetchable_props: Vec<(String, Error)> = Vec::new();
-    let mut unfetchable_classes: Vec<(String, Error)> = Vec::new();
-    // subject, property, class
-    let mut missing_props: Vec<(String, String, String)> = Vec::new();
+/// - [X] If the Properties and Classes can be retrieved
+/// - [X] If linked Resources match the `classtype` of their Property
+/// - [X] If the URLs are publicly accessible (if `fetch_items` is true)
+pub fn validate_store(store: &impl crate::Storelike, fetch_items: bool) -> ValidationReport {
+    let mut report = ValidationReport::default();
     for resource in store.all_resources(true) {
         let subject = resource.get_subject();
-        let propvals = resource.get_propvals();
-        println!("Subject: {:?}", subject);
-        println!("Resource: {:?}", propvals);
-        resource_count += 1;
+        report.resource_count += 1;
+        report.atom_count += resource.get_propvals().len();
 
+        let mut errors = Vec::new();
         if fetch_items {
-            match crate::client::fetch_resource(subject, store, store.get_default_agent().ok()) {
-                Ok(_) => {}
-                Err(e) => unfetchable.push((subject.clone(), e.to_string())),
+            if let Err(e) =
+                crate::client::fetch_resource(subject, store, store.get_default_agent().ok())
+            {
+                errors.push(SchemaViolation::new(
+                    ViolationCode::Unfetchable,
+                    None,
+                    None,
+                    format!("Resource could not be fetched: {}", e),
+                ));
             }
         }
+        match validate_resource_schema(&resource, store) {
+            Ok(violations) => errors.extend(violations),
+            Err(e) => errors.push(SchemaViolation::new(
+                ViolationCode::Unfetchable,
+                None,
+                None,
+                format!("Resource could not be validated: {}", e),
+            )),
+        }
+        if !errors.is_empty() {
+            report.resources.push(ResourceReport {
+                subject: subject.clone(),
+                errors,
+            });
+        }
+    }
+    report
+}
+
+/// A single way in which a Resource does not match its Schema.
+#[derive(Clone, Debug, Serialize)]
+pub struct SchemaViolation {
+    pub code: ViolationCode,
+    /// The Property that has a problem, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub property: Option<String>,
+    /// The Class that sets the violated requirement, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub class: Option<String>,
+    /// Human readable explanation
+    pub message: String,
+}
 
-        let mut found_props: Vec<String> = Vec::new();
+/// Machine readable kinds of [SchemaViolation]s
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ViolationCode {
+    /// A Property that is in the `requires` of one of the Classes is missing
+    MissingRequiredProperty,
+    /// A Value does not match the Datatype of its Property
+    InvalidDatatype,
+    /// A Value is not one of the `allowsOnly` values of its Property
+    NotAllowedValue,
+    /// A linked Resource is not an instance of the `classtype` of its Property
+    WrongClassType,
+    /// A linked Resource could not be retrieved, so its Class could not be checked
+    UnavailableLinkedResource,
+    /// A Property could not be retrieved
+    UnknownProperty,
+    /// A Class from `is-a` could not be retrieved
+    UnknownClass,
+    /// A Value does not satisfy the constraints of its Property, such as `minimum` or `pattern`
+    ConstraintViolation,
+    /// Another Resource with the same Parent has the same Value for a `uniqueWithinParent` Property
+    NotUnique,
+    /// The Resource itself could not be fetched or validated
+    Unfetchable,
+}
+
+impl std::fmt::Display for SchemaViolation {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(fmt, "{:?}: {}", self.code, self.message)
+    }
+}
+
+impl SchemaViolation {
+    fn new(
+        code: ViolationCode,
+        property: Option<&str>,
+        class: Option<&str>,
+        message: String,
+    ) -> SchemaViolation {
+        SchemaViolation {
+            code,
+            property: property.map(|p| p.to_string()),
+            class: class.map(|c| c.to_string()),
+            message,
+        }
+    }
+}
 
-        for (prop_url, value) in propvals {
-            atom_count += 1;
+/// Checks a Resource against all of its Classes (`is-a`) and the Properties it uses.
+/// Returns every violation that is found, so an empty Vec means the Resource is valid.
+///
+/// Validates:
+///
+/// - If all `requires` Properties of all Classes are present
+/// - If the Values match the Datatype of their Property
+/// - If the Values are in the `allowsOnly` list of their Property
+/// - If linked Resources are instances of the `classtype` of their Property
+pub fn validate_resource_schema(
+    resource: &Resource,
+    store: &impl Storelike,
+) -> AtomicResult<Vec<SchemaViolation>> {
+    let mut violations = Vec::new();
 
-            let property = match store.get_property(prop_url) {
-                Ok(prop) => prop,
+    if let Ok(classes) = resource.get(crate::urls::IS_A) {
+        for class_subject in classes.to_subjects(None)? {
+            let class = match store.get_class(&class_subject) {
+                Ok(class) => class,
                 Err(e) => {
-                    unfetchable_props.push((prop_url.clone(), e.to_string()));
-                    break;
+                    violations.push(SchemaViolation::new(
+                        ViolationCode::UnknownClass,
+                        None,
+                        Some(&class_subject),
+                        format!("Class {} could not be retrieved: {}", class_subject, e),
+                    ));
+                    continue;
                 }
             };
-
-            // Maybe this is no longer needed, because no store uses strings anymore
-            match crate::Value::new(&value.to_string(), &property.data_type) {
-                Ok(_) => {}
-                Err(e) => invalid_value.push((
-                    crate::Atom::new(subject.clone(), prop_url.clone(), value.clone()),
-                    e.to_string(),
-                )),
-            };
-            found_props.push(prop_url.clone());
+            for required in &class.requires {
+                if resource.get(required).is_err() {
+                    violations.push(SchemaViolation::new(
+                        ViolationCode::MissingRequiredProperty,
+                        Some(required),
+                        Some(&class.subject),
+                        format!(
+                            "Property {} is missing, but is required by Class {}",
+                            required, class.subject
+                        ),
+                    ));
+                }
+            }
         }
-        let classes = match store.get_classes_for_subject(subject) {
-            Ok(classes) => classes,
+    }
+
+    for (prop_url, value) in resource.get_propvals() {
+        let property = match store.get_property(prop_url) {
+            Ok(prop) => prop,
             Err(e) => {
-                unfetchable_classes.push((subject.clone(), e.to_string()));
-                break;
+                violations.push(SchemaViolation::new(
+                    ViolationCode::UnknownProperty,
+                    Some(prop_url),
+                    None,
+                    format!("Property {} could not be retrieved: {}", prop_url, e),
+                ));
+                continue;
             }
         };
-        for class in classes {
-            println!("Class: {:?}", class.shortname);
-            println!("Found: {:?}", found_props);
-            for required_prop_subject in class.requires {
-                match store.get_property(&required_prop_subject) {
-                    Ok(required_prop) => {
-                        println!("Required: {:?}", required_prop.shortname);
-                        if !found_props.contains(&required_prop.subject) {
-                            missing_props.push((
-                                subject.clone(),
-                                required_prop.subject.clone(),
-                                class.subject.clone(),
+
+        if let crate::datatype::DataType::Unsupported(_) = property.data_type {
+            // We can't say anything about datatypes we don't know
+        } else if !value.matches_datatype(&property.data_type) {
+            violations.push(SchemaViolation::new(
+                ViolationCode::InvalidDatatype,
+                Some(prop_url),
+                None,
+                format!(
+                    "Value '{}' has datatype {}, but Property {} requires {}",
+                    value,
+                    value.datatype(),
+                    prop_url,
+                    property.data_type
+                ),
+            ));
+            continue;
+        } else if !matches!(
+            value,
+            Value::ResourceArray(_) | Value::NestedResource(_) | Value::Resource(_)
+        ) {
+            if let Err(e) = Value::new(&value.to_string(), &property.data_type) {
+                violations.push(SchemaViolation::new(
+                    ViolationCode::InvalidDatatype,
+                    Some(prop_url),
+                    None,
+                    format!("Value '{}' is not valid for {}: {}", value, prop_url, e),
+                ));
+                continue;
+            }
+        }
+
+        if let Err(e) = property.check_constraints(value) {
+            violations.push(SchemaViolation::new(
+                ViolationCode::ConstraintViolation,
+                Some(prop_url),
+                None,
+                e.to_string(),
+            ));
+        }
+
+        if let Some(allowed) = &property.allows_only {
+            let given = match value {
+                Value::AtomicUrl(_) | Value::ResourceArray(_) => {
+                    value.to_subjects(None).unwrap_or_default()
+                }
+                other => vec![other.to_string()],
+            };
+            for item in given {
+                if !allowed.contains(&item) {
+                    violations.push(SchemaViolation::new(
+                        ViolationCode::NotAllowedValue,
+                        Some(prop_url),
+                        None,
+                        format!(
+                            "Value '{}' is not allowed for Property {}. Allowed values: {}",
+                            item,
+                            prop_url,
+                            allowed.join(", ")
+                        ),
+                    ));
+                }
+            }
+        }
+
+        if let Some(class_type) = &property.class_type {
+            for linked_classes in linked_classes(value, store) {
+                match linked_classes {
+                    Ok((subject, classes)) => {
+                        // Groups can be granted rights wherever Agents can
+                        let group_as_agent = class_type == crate::urls::AGENT
+                            && classes.iter().any(|c| c == crate::urls::GROUP);
+                        if !classes.contains(class_type) && !group_as_agent {
+                            violations.push(SchemaViolation::new(
+                                ViolationCode::WrongClassType,
+                                Some(prop_url),
+                                Some(class_type),
+                                format!(
+                                    "Resource {} is not an instance of {}, which is required by Property {}",
+                                    subject, class_type, prop_url
+                                ),
                             ));
                         }
                     }
-                    Err(e) => unfetchable.push((required_prop_subject, e.to_string())),
+                    Err((subject, e)) => violations.push(SchemaViolation::new(
+                        ViolationCode::UnavailableLinkedResource,
+                        Some(prop_url),
+                        Some(class_type),
+                        format!("Resource {} could not be retrieved: {}", subject, e),
+                    )),
                 }
             }
         }
-        println!("{:?} Valid", subject);
     }
-    crate::validate::ValidationReport {
-        unfetchable,
-        unfetchable_classes,
-        unfetchable_props,
-        invalid_value,
-        resource_count,
-        atom_count,
+
+    violations.extend(check_unique_within_parent(resource, store)?);
+
+    Ok(violations)
+}
+
+/// Checks the `uniqueWithinParent` Properties of the Resource:
+/// no sibling (a Resource with the same Parent) may have the same Value.
+pub fn check_unique_within_parent(
+    resource: &Resource,
+    store: &impl Storelike,
+) -> AtomicResult<Vec<SchemaViolation>> {
+    check_unique_props_within_parent(resource, resource.get_propvals().keys(), store)
+}
+
+/// Like [check_unique_within_parent], but only checks the given Properties.
+/// Used when applying Commits, so only the Properties that change are looked up.
+pub fn check_unique_props_within_parent<'a>(
+    resource: &Resource,
+    props: impl Iterator<Item = &'a String>,
+    store: &impl Storelike,
+) -> AtomicResult<Vec<SchemaViolation>> {
+    let mut violations = Vec::new();
+    let parent = match resource.get(crate::urls::PARENT) {
+        Ok(parent) => parent.to_string(),
+        Err(_) => return Ok(violations),
+    };
+    for prop_url in props {
+        let value = match resource.get(prop_url) {
+            Ok(value) => value,
+            Err(_) => continue,
+        };
+        let unique = store
+            .get_property(prop_url)
+            .map(|p| p.constraints.unique_within_parent)
+            .unwrap_or(false);
+        if !unique {
+            continue;
+        }
+        for atom in store.tpf(None, Some(prop_url), Some(value), false)? {
+            if atom.subject == *resource.get_subject() {
+                continue;
+            }
+            let sibling_parent = store
+                .get_resource(&atom.subject)
+                .and_then(|r| r.get(crate::urls::PARENT).map(|p| p.to_string()));
+            if sibling_parent.ok().as_ref() == Some(&parent) {
+                violations.push(SchemaViolation::new(
+                    ViolationCode::NotUnique,
+                    Some(prop_url),
+                    None,
+                    format!(
+                        "Value '{}' for {} is already used by {}, which has the same parent",
+                        value, prop_url, atom.subject
+                    ),
+                ));
+            }
+        }
+    }
+    Ok(violations)
+}
+
+/// The subject (or a description) and the classes of a linked Resource, or its subject and the error that occurred when loading it.
+type LinkedClasses = Result<(String, Vec<String>), (String, String)>;
+
+/// Returns the subject (or a description) and the classes of every Resource that the Value links to.
+/// Only uses Resources that are in the Store, since validating should not fetch every linked Resource from the web.
+/// External Resources that are not in the Store are skipped.
+fn linked_classes(value: &Value, store: &impl Storelike) -> Vec<LinkedClasses> {
+    fn classes_of(propvals: &crate::resources::PropVals) -> Vec<String> {
+        propvals
+            .get(crate::urls::IS_A)
+            .and_then(|v| v.to_subjects(None).ok())
+            .unwrap_or_default()
+    }
+    let from_subject = |subject: &str| match store.get_resource_local(subject) {
+        Ok(r) => Some(Ok((subject.to_string(), classes_of(r.get_propvals())))),
+        Err(_) if !subject.starts_with(store.get_server_url()) => None,
+        Err(e) => Some(Err((subject.to_string(), e.to_string()))),
+    };
+    let from_sub_resource = |sub: &SubResource| match sub {
+        SubResource::Subject(s) => from_subject(s),
+        SubResource::Resource(r) => {
+            Some(Ok((r.get_subject().clone(), classes_of(r.get_propvals()))))
+        }
+        SubResource::Nested(pv) => Some(Ok(("Nested Resource".into(), classes_of(pv)))),
+    };
+    match value {
+        Value::AtomicUrl(s) => from_subject(s).into_iter().collect(),
+        Value::Resource(r) => vec![Ok((r.get_subject().clone(), classes_of(r.get_propvals())))],
+        Value::NestedResource(sub) => from_sub_resource(sub).into_iter().collect(),
+        Value::ResourceArray(items) => items.iter().filter_map(from_sub_resource).collect(),
+        _other => Vec::new(),
     }
 }
 
+/// The result of [validate_store]. Serializable, so it can be used in scripts and jobs.
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ValidationReport {
-    pub resource_count: u8,
-    pub atom_count: u8,
-    pub unfetchable: Vec<(String, String)>,
-    pub invalid_value: Vec<(crate::Atom, String)>,
-    pub unfetchable_props: Vec<(String, String)>,
-    pub unfetchable_classes: Vec<(String, String)>,
+    pub resource_count: usize,
+    pub atom_count: usize,
+    /// Only the Resources that have one or more errors
+    pub resources: Vec<ResourceReport>,
+}
+
+/// All errors found for a single Resource
+#[derive(Clone, Debug, Serialize)]
+pub struct ResourceReport {
+    pub subject: String,
+    pub errors: Vec<SchemaViolation>,
 }
 
 impl ValidationReport {
     pub fn is_valid(&self) -> bool {
-        self.unfetchable.is_empty()
-            && self.unfetchable_classes.is_empty()
-            && self.unfetchable_props.is_empty()
-            && self.invalid_value.is_empty()
+        self.resources.is_empty()
+    }
+
+    /// Total amount of errors, for all Resources
+    pub fn error_count(&self) -> usize {
+        self.resources.iter().map(|r| r.errors.len()).sum()
     }
 }
 
 impl std::fmt::Display for ValidationReport {
     fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
         if self.is_valid() {
-            fmt.write_str("Valid!")?;
-            return Ok(());
-        }
-        for (subject, error) in &self.unfetchable {
-            fmt.write_str(&*format!("Cannot fetch Resource {}: {} \n", subject, error))?;
+            return write!(
+                fmt,
+                "Valid! Checked {} resources and {} atoms.",
+                self.resource_count, self.atom_count
+            );
         }
-        for (subject, error) in &self.unfetchable_classes {
-            fmt.write_str(&*format!("Cannot fetch Class {}: {} \n", subject, error))?;
-        }
-        for (subject, error) in &self.unfetchable_props {
-            fmt.write_str(&*format!("Cannot fetch Property {}: {} \n", subject, error))?;
-        }
-        for (atom, error) in &self.invalid_value {
-            fmt.write_str(&*format!("Invalid value {:?}: {} \n", atom, error))?;
+        for resource in &self.resources {
+            writeln!(fmt, "{}", resource.subject)?;
+            for error in &resource.errors {
+                writeln!(fmt, "  {}", error)?;
+            }
         }
-        Ok(())
+        write!(
+            fmt,
+            "Found {} errors in {} of {} resources.",
+            self.error_count(),
+            self.resources.len(),
+            self.resource_count
+        )
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{Store, Storelike};
+    use super::*;
+    use crate::{urls, Store};
 
     #[test]
     fn validate_populated() {
         let store = Store::init().unwrap();
         store.populate().unwrap();
-        // let report = store.validate();
-        // assert!(report.atom_count > 30);
-        // assert!(report.resource_count > 5);
-        // assert!(report.is_valid());
+        let report = store.validate();
+        assert!(report.atom_count > 30);
+        assert!(report.resource_count > 5);
+        serde_json::to_string(&report).unwrap();
+
+        // A Class without its required shortname
+        let subject = "http://localhost/class-without-shortname";
+        let mut broken = Resource::new(subject.into());
+        broken.set_propval_unsafe(
+            urls::IS_A.into(),
+            Value::ResourceArray(vec![urls::CLASS.into()]),
+        );
+        broken.set_propval_unsafe(
+            urls::DESCRIPTION.into(),
+            Value::Markdown("Missing a shortname".into()),
+        );
+        store.add_resource_opts(&broken, false, true, true).unwrap();
+        let report = store.validate();
+        assert!(!report.is_valid());
+        let broken_report = report
+            .resources
+            .iter()
+            .find(|r| r.subject == subject)
+            .expect("Report should contain the broken Resource");
+        assert!(broken_report
+            .errors
+            .iter()
+            .any(|e| e.code == ViolationCode::MissingRequiredProperty
+                && e.property.as_deref() == Some(urls::SHORTNAME)));
+    }
+
+    #[test]
+    fn schema_violations() {
+        let store = crate::test_utils::init_store();
+        let mut resource = Resource::new_instance(urls::CLASS, &store).unwrap();
+        resource
+            .set_propval_shortname("shortname", "some-class", &store)
+            .unwrap();
+        resource.set_propval_unsafe(urls::RECOMMENDS.into(), Value::Integer(1));
+        let violations = validate_resource_schema(&resource, &store).unwrap();
+        let codes: Vec<ViolationCode> = violations.iter().map(|v| v.code.clone()).collect();
+        assert!(codes.contains(&ViolationCode::MissingRequiredProperty));
+        assert!(codes.contains(&ViolationCode::InvalidDatatype));
+
+        resource.remove_propval(urls::RECOMMENDS);
+        resource
+            .set_propval_shortname("description", "Now it's valid", &store)
+            .unwrap();
+        assert!(validate_resource_schema(&resource, &store)
+            .unwrap()
+            .is_empty());
     }
 }
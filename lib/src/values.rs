@@ -6,6 +6,7 @@ use crate::{
 };
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// An individual Value in an Atom.
 /// Note that creating values using `Value::from` might result in the wrong Datatype, as the from conversion makes assumptions (e.g. integers are Integers, not Timestamps).
@@ -26,6 +27,17 @@ pub enum Value {
     Resource(Resource),
     Boolean(bool),
     Unsupported(UnsupportedValue),
+    // New variants are added at the end, since Values are stored using bincode, which relies on the variant index.
+    Uri(String),
+    /// Serialized JSON. Not stored as a `serde_json::Value`, because bincode does not support it.
+    Json(String),
+    /// Arbitrary precision decimal, e.g. `-12.50`
+    Decimal(String),
+    /// ISO 8601 duration, e.g. `P1Y2M10DT2H30M`
+    Duration(String),
+    GeoPoint(GeoPoint),
+    /// The same text in multiple languages, keyed by language tag (e.g. `en`, `nl-BE`)
+    LangString(BTreeMap<String, String>),
 }
 
 /// A resource in a JSON-AD body can be any of these
@@ -47,10 +59,27 @@ pub struct UnsupportedValue {
     pub datatype: String,
 }
 
+/// A WGS84 coordinate. Serialized as `latitude,longitude`, e.g. `52.0907,5.1214`
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct GeoPoint {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
 /// Only alphanumeric characters, no spaces
 pub const SLUG_REGEX: &str = r"^[a-z0-9]+(?:-[a-z0-9]+)*$";
 /// YYYY-MM-DD
 pub const DATE_REGEX: &str = r"^\d{4}\-(0[1-9]|1[012])\-(0[1-9]|[12][0-9]|3[01])$";
+/// Decimal number without exponent, like `xsd:decimal`. e.g. `-12.50`
+pub const DECIMAL_REGEX: &str = r"^[+-]?(\d+(\.\d*)?|\.\d+)$";
+/// ISO 8601 duration, e.g. `P3Y6M4DT12H30M5S`. Fractions are only allowed in the smallest unit, but we're not that strict.
+pub const DURATION_REGEX: &str = r"^-?P(?:(\d+(?:[.,]\d+)?)Y)?(?:(\d+(?:[.,]\d+)?)M)?(?:(\d+(?:[.,]\d+)?)W)?(?:(\d+(?:[.,]\d+)?)D)?(?:T(?:(\d+(?:[.,]\d+)?)H)?(?:(\d+(?:[.,]\d+)?)M)?(?:(\d+(?:[.,]\d+)?)S)?)?$";
+/// Language tags, like `en` or `nl-BE`. A simplified version of BCP 47.
+pub const LANGUAGE_TAG_REGEX: &str = r"^[a-zA-Z]{2,3}(-[a-zA-Z0-9]{1,8})*$";
+/// Used for LangStrings if none of the requested languages are available
+pub const DEFAULT_LANGUAGE: &str = "en";
+/// Amount of digits that the integer part of numbers is padded to in sortable strings
+const SORTABLE_NUMBER_WIDTH: usize = 40;
 
 impl Value {
     /// Returns the datatype for the value
@@ -65,14 +94,32 @@ impl Value {
             Value::Slug(_) => DataType::Slug,
             Value::String(_) => DataType::String,
             Value::Timestamp(_) => DataType::Timestamp,
-            // TODO: these datatypes are not the same
-            Value::NestedResource(_) => DataType::AtomicUrl,
-            Value::Resource(_) => DataType::AtomicUrl,
+            // A nested reference to a subject is just a link
+            Value::NestedResource(SubResource::Subject(_)) => DataType::AtomicUrl,
+            Value::NestedResource(_) => DataType::NestedResource,
+            Value::Resource(_) => DataType::NestedResource,
             Value::Boolean(_) => DataType::Boolean,
             Value::Unsupported(s) => DataType::Unsupported(s.datatype.clone()),
+            Value::Uri(_) => DataType::Uri,
+            Value::Json(_) => DataType::Json,
+            Value::Decimal(_) => DataType::Decimal,
+            Value::Duration(_) => DataType::Duration,
+            Value::GeoPoint(_) => DataType::GeoPoint,
+            Value::LangString(_) => DataType::LangString,
         }
     }
 
+    /// Checks whether this Value can be used for a Property with the given DataType.
+    /// Properties with the AtomicUrl datatype also accept Nested Resources, since JSON-AD allows embedding linked Resources.
+    /// Properties with a String or Markdown datatype also accept LangStrings, so they can be translated.
+    pub fn matches_datatype(&self, datatype: &DataType) -> bool {
+        let own = self.datatype();
+        own == *datatype
+            || (*datatype == DataType::AtomicUrl && own == DataType::NestedResource)
+            || (matches!(datatype, DataType::String | DataType::Markdown)
+                && own == DataType::LangString)
+    }
+
     /// Creates a new Value from an explicit DataType.
     /// Fails if the input string does not convert.
     pub fn new(value: &str, datatype: &DataType) -> AtomicResult<Value> {
@@ -143,6 +190,49 @@ impl Value {
                 };
                 Ok(Value::Boolean(bool))
             }
+            DataType::Uri => {
+                url::Url::parse(value).map_err(|e| format!("Not a valid URI: {}. {}", value, e))?;
+                Ok(Value::Uri(value.into()))
+            }
+            DataType::Json => {
+                let json: serde_json::Value = serde_json::from_str(value)
+                    .map_err(|e| format!("Not valid JSON: {}. {}", value, e))?;
+                Ok(Value::Json(json.to_string()))
+            }
+            DataType::Decimal => {
+                let re = Regex::new(DECIMAL_REGEX).unwrap();
+                if re.is_match(value) {
+                    return Ok(Value::Decimal(value.into()));
+                }
+                Err(format!(
+                    "Not a valid decimal: {}. Use digits and an optional dot, e.g. -12.50",
+                    value
+                )
+                .into())
+            }
+            DataType::Duration => {
+                duration_to_seconds(value)?;
+                Ok(Value::Duration(value.into()))
+            }
+            DataType::GeoPoint => Ok(Value::GeoPoint(value.parse()?)),
+            DataType::LangString => {
+                let map: BTreeMap<String, String> = serde_json::from_str(value).map_err(|e| {
+                    format!(
+                        "Not a valid LangString: {}. Should be a JSON object with language tags as keys and strings as values, e.g. {{\"en\": \"Hello\"}}. {}",
+                        value, e
+                    )
+                })?;
+                let re = Regex::new(LANGUAGE_TAG_REGEX).unwrap();
+                if let Some(tag) = map.keys().find(|tag| !re.is_match(tag)) {
+                    return Err(format!("Not a valid language tag: {}", tag).into());
+                }
+                Ok(Value::LangString(map))
+            }
+            DataType::NestedResource => Err(format!(
+                "Can't create a Nested Resource from the string '{}'. Use a JSON-AD object instead.",
+                value
+            )
+            .into()),
         }
     }
 
@@ -215,15 +305,156 @@ impl Value {
         Err(format!("Value {} is not a Nested Resource", self).into())
     }
 
+    /// Returns the translation that best matches the given languages, if this is a LangString.
+    /// `languages` are ordered by preference, e.g. from an `Accept-Language` header.
+    /// Also matches on the primary language (`nl-BE` matches `nl`).
+    /// Falls back to [DEFAULT_LANGUAGE], and then to the first available language.
+    pub fn best_language_match(&self, languages: &[String]) -> Option<&str> {
+        let map = match self {
+            Value::LangString(map) => map,
+            _ => return None,
+        };
+        let primary = |tag: &str| tag.split('-').next().unwrap_or_default().to_lowercase();
+        for language in languages {
+            if language == "*" {
+                break;
+            }
+            let exact = map
+                .iter()
+                .find(|(tag, _)| tag.eq_ignore_ascii_case(language));
+            let partial = || {
+                map.iter()
+                    .find(|(tag, _)| primary(tag.as_str()) == primary(language.as_str()))
+            };
+            if let Some((_, text)) = exact.or_else(partial) {
+                return Some(text);
+            }
+        }
+        map.get(DEFAULT_LANGUAGE)
+            .or_else(|| map.values().next())
+            .map(|s| s.as_str())
+    }
+
     /// Returns a Lexicographically sortable string representation of the value
     pub fn to_sortable_string(&self) -> String {
         match self {
             Value::ResourceArray(arr) => arr.len().to_string(),
+            Value::LangString(_) => self.best_language_match(&[]).unwrap_or_default().into(),
+            Value::Decimal(d) => sortable_decimal(d),
+            Value::Duration(d) => match duration_to_seconds(d) {
+                Ok(seconds) => sortable_decimal(&seconds.to_string()),
+                Err(_) => d.clone(),
+            },
+            Value::GeoPoint(p) => format!(
+                "{},{}",
+                sortable_decimal(&p.latitude.to_string()),
+                sortable_decimal(&p.longitude.to_string())
+            ),
             other => other.to_string(),
         }
     }
 }
 
+/// Converts an ISO 8601 duration to seconds.
+/// Years are counted as 365 days, months as 30 days.
+pub fn duration_to_seconds(duration: &str) -> AtomicResult<f64> {
+    let re = Regex::new(DURATION_REGEX).unwrap();
+    let invalid = || {
+        format!(
+            "Not a valid ISO 8601 duration: {}. e.g. P1DT2H30M",
+            duration
+        )
+    };
+    let captures = re.captures(duration).ok_or_else(invalid)?;
+    // `P` and `PT` without any components are not allowed
+    if captures.iter().skip(1).all(|c| c.is_none()) || duration.ends_with('T') {
+        return Err(invalid().into());
+    }
+    const UNIT_SECONDS: [f64; 7] = [
+        365.0 * 86400.0,
+        30.0 * 86400.0,
+        7.0 * 86400.0,
+        86400.0,
+        3600.0,
+        60.0,
+        1.0,
+    ];
+    let mut seconds = 0.0;
+    for (i, unit) in UNIT_SECONDS.iter().enumerate() {
+        if let Some(amount) = captures.get(i + 1) {
+            let amount: f64 = amount.as_str().replace(',', ".").parse()?;
+            seconds += amount * unit;
+        }
+    }
+    if duration.starts_with('-') {
+        seconds = -seconds;
+    }
+    Ok(seconds)
+}
+
+/// Converts a decimal string (e.g. `-12.5`) to a string that sorts lexicographically in numeric order.
+/// The integer part is zero-padded, and the digits of negative numbers are inverted.
+fn sortable_decimal(decimal: &str) -> String {
+    let (negative, unsigned) = match decimal.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, decimal.strip_prefix('+').unwrap_or(decimal)),
+    };
+    let (int_part, frac_part) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+    let int_part = int_part.trim_start_matches('0');
+    let frac_part = frac_part.trim_end_matches('0');
+    let is_zero = int_part.is_empty() && frac_part.is_empty();
+    let mut padded = format!("{:0>width$}", int_part, width = SORTABLE_NUMBER_WIDTH);
+    if !frac_part.is_empty() {
+        padded.push('.');
+        padded.push_str(frac_part);
+    }
+    if !negative || is_zero {
+        return format!("1{}", padded);
+    }
+    let inverted: String = padded
+        .chars()
+        .map(|c| match c.to_digit(10) {
+            Some(d) => std::char::from_digit(9 - d, 10).unwrap_or(c),
+            None => c,
+        })
+        .collect();
+    // The terminator makes sure that -1.5 sorts before -1
+    format!("0{}~", inverted)
+}
+
+impl std::str::FromStr for GeoPoint {
+    type Err = crate::errors::AtomicError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Not a valid geo point: {}. Use `latitude,longitude`, e.g. 52.0907,5.1214",
+                s
+            )
+        };
+        let (lat, lon) = s.split_once(',').ok_or_else(invalid)?;
+        let latitude: f64 = lat.trim().parse().map_err(|_| invalid())?;
+        let longitude: f64 = lon.trim().parse().map_err(|_| invalid())?;
+        if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+            return Err(format!(
+                "Geo point {} is out of range. Latitude must be between -90 and 90, longitude between -180 and 180.",
+                s
+            )
+            .into());
+        }
+        Ok(GeoPoint {
+            latitude,
+            longitude,
+        })
+    }
+}
+
+impl fmt::Display for GeoPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{}", self.latitude, self.longitude)
+    }
+}
+
 /// Check if the value `q_val` is present in `val`
 pub fn query_value_compare(val: &Value, q_val: &Value) -> bool {
     let query_value = q_val.to_string();
@@ -232,6 +463,7 @@ pub fn query_value_compare(val: &Value, q_val: &Value) -> bool {
             let subs = val.to_subjects(None).unwrap_or_default();
             subs.iter().any(|v| v == &query_value)
         }
+        Value::LangString(map) => map.values().any(|v| v == &query_value),
         other => other.to_string() == query_value,
     }
 }
@@ -355,9 +587,19 @@ impl fmt::Display for Value {
                 r.to_json_ad()
                     .unwrap_or_else(|_e| format!("Could not serialize resource: {:?}", r))
             ),
-            Value::NestedResource(n) => write!(f, "{:?}", n),
+            Value::NestedResource(n) => write!(f, "{}", n),
             Value::Boolean(b) => write!(f, "{}", b),
             Value::Unsupported(u) => write!(f, "{}", u.value),
+            Value::Uri(s) => write!(f, "{}", s),
+            Value::Json(s) => write!(f, "{}", s),
+            Value::Decimal(s) => write!(f, "{}", s),
+            Value::Duration(s) => write!(f, "{}", s),
+            Value::GeoPoint(p) => write!(f, "{}", p),
+            Value::LangString(map) => write!(
+                f,
+                "{}",
+                serde_json::to_string(map).unwrap_or_else(|_| format!("{:?}", map))
+            ),
         }
     }
 }
@@ -439,6 +681,76 @@ mod test {
         Value::new("120-02-02", &DataType::Date).unwrap_err();
         Value::new("12000-02-02", &DataType::Date).unwrap_err();
         Value::new("a", &DataType::Float).unwrap_err();
+        Value::new("no scheme", &DataType::Uri).unwrap_err();
+        Value::new("{no json", &DataType::Json).unwrap_err();
+        Value::new("1.2.3", &DataType::Decimal).unwrap_err();
+        Value::new("1e10", &DataType::Decimal).unwrap_err();
+        Value::new("P", &DataType::Duration).unwrap_err();
+        Value::new("P1DT", &DataType::Duration).unwrap_err();
+        Value::new("1 day", &DataType::Duration).unwrap_err();
+        Value::new("91,5", &DataType::GeoPoint).unwrap_err();
+        Value::new("52.1", &DataType::GeoPoint).unwrap_err();
+    }
+
+    #[test]
+    fn new_datatypes() {
+        let uri = Value::new("mailto:joep@example.com", &DataType::Uri).unwrap();
+        assert_eq!(uri.datatype(), DataType::Uri);
+        let json = Value::new(r#"{ "a": [1, true] }"#, &DataType::Json).unwrap();
+        assert_eq!(json.to_string(), r#"{"a":[1,true]}"#);
+        let decimal = Value::new("-12.50", &DataType::Decimal).unwrap();
+        assert_eq!(decimal.to_string(), "-12.50");
+        assert_eq!(duration_to_seconds("P1DT2H30M").unwrap(), 95400.0);
+        assert_eq!(duration_to_seconds("PT0,5S").unwrap(), 0.5);
+        assert_eq!(duration_to_seconds("-P1W").unwrap(), -604800.0);
+        let point = Value::new("52.0907, 5.1214", &DataType::GeoPoint).unwrap();
+        assert_eq!(point.to_string(), "52.0907,5.1214");
+    }
+
+    #[test]
+    fn lang_strings() {
+        let val = Value::new(r#"{"en": "Hello", "nl": "Hallo"}"#, &DataType::LangString).unwrap();
+        assert!(val.matches_datatype(&DataType::String));
+        assert!(val.matches_datatype(&DataType::Markdown));
+        let langs = |l: &[&str]| l.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+        assert_eq!(val.best_language_match(&langs(&["nl"])), Some("Hallo"));
+        assert_eq!(
+            val.best_language_match(&langs(&["nl-BE", "en"])),
+            Some("Hallo")
+        );
+        assert_eq!(
+            val.best_language_match(&langs(&["de", "EN"])),
+            Some("Hello")
+        );
+        assert_eq!(val.best_language_match(&langs(&["fr"])), Some("Hello"));
+        Value::new(r#"{"not a tag": "Hello"}"#, &DataType::LangString).unwrap_err();
+        Value::new(r#"{"en": 1}"#, &DataType::LangString).unwrap_err();
+    }
+
+    #[test]
+    fn sortable_numbers() {
+        let sorted = [
+            "-100", "-1.5", "-1", "-0.25", "0", "0.5", "1", "1.05", "1.5", "10", "200",
+        ];
+        let keys: Vec<String> = sorted
+            .iter()
+            .map(|d| Value::Decimal(d.to_string()).to_sortable_string())
+            .collect();
+        for pair in keys.windows(2) {
+            assert!(
+                pair[0] < pair[1],
+                "{} should sort before {}",
+                pair[0],
+                pair[1]
+            );
+        }
+        assert_eq!(
+            Value::Decimal("-0.0".into()).to_sortable_string(),
+            Value::Decimal("0".into()).to_sortable_string()
+        );
+        let short = Value::Duration("PT90M".into()).to_sortable_string();
+        let long = Value::Duration("P1D".into()).to_sortable_string();
+        assert!(short < long);
     }
 
     #[test]
@@ -460,6 +772,22 @@ mod test {
         assert_eq!(converted.to_string(), "8");
     }
 
+    #[test]
+    fn nested_resource_datatypes() {
+        let mut propvals = PropVals::new();
+        propvals.insert(crate::urls::NAME.into(), Value::String("nested".into()));
+        let anonymous = Value::from(propvals);
+        assert_eq!(anonymous.datatype(), DataType::NestedResource);
+        assert!(anonymous.matches_datatype(&DataType::NestedResource));
+        assert!(anonymous.matches_datatype(&DataType::AtomicUrl));
+        assert!(!anonymous.matches_datatype(&DataType::String));
+        assert!(anonymous.to_string().contains("nested"));
+        let link = Value::NestedResource(SubResource::Subject("https://example.com".into()));
+        assert_eq!(link.datatype(), DataType::AtomicUrl);
+        assert!(!link.matches_datatype(&DataType::NestedResource));
+        Value::new("https://example.com", &DataType::NestedResource).unwrap_err();
+    }
+
     #[test]
     fn value_to_subjects() {
         let subject_string = String::from("https://example.com/subject_string");
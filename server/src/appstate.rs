@@ -1,6 +1,11 @@
 //! App state, which is accessible from handlers
 use crate::{
-    commit_monitor::CommitMonitor, config::Config, errors::AtomicServerResult, search::SearchState,
+    blobs::VerifiedBlobs,
+    commit_monitor::CommitMonitor,
+    config::Config,
+    errors::AtomicServerResult,
+    rate_limit::{Limits, RateLimiter},
+    search::SearchState,
 };
 use atomic_lib::{
     agents::{generate_public_key, Agent},
@@ -23,6 +28,10 @@ pub struct AppState {
     /// The Actix Address of the CommitMonitor, which should receive updates when a commit is applied
     pub commit_monitor: actix::Addr<CommitMonitor>,
     pub search_state: SearchState,
+    /// Keeps track of the requests of every client, see [crate::rate_limit]
+    pub rate_limiter: RateLimiter,
+    /// Uploaded files that match their checksum, see [crate::blobs]
+    pub verified_blobs: VerifiedBlobs,
 }
 
 /// Creates the AppState (the server's context available in Handlers).
@@ -68,6 +77,7 @@ pub fn init(config: Config) -> AtomicServerResult<AppState> {
     tracing::info!("Starting search service");
     let search_state =
         SearchState::new(&config).map_err(|e| format!("Failed to start search service: {}", e))?;
+    search_state.load_drive_languages(&store)?;
 
     // Initialize commit monitor, which watches commits and sends these to the commit_monitor actor
     tracing::info!("Starting commit monitor");
@@ -86,12 +96,17 @@ pub fn init(config: Config) -> AtomicServerResult<AppState> {
         });
     };
     store.set_handle_commit(Box::new(send_commit));
+    store.set_drive_quota(crate::handlers::upload::megabytes(config.opts.drive_quota));
+
+    let rate_limiter = RateLimiter::new(Limits::from_opts(&config.opts));
 
     Ok(AppState {
         store,
         config,
         commit_monitor,
         search_state,
+        rate_limiter,
+        verified_blobs: VerifiedBlobs::default(),
     })
 }
 
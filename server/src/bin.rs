@@ -3,6 +3,7 @@ use std::{fs::File, io::Write};
 
 mod actor_messages;
 mod appstate;
+mod blobs;
 mod commit_monitor;
 pub mod config;
 mod content_types;
@@ -14,6 +15,7 @@ mod https;
 mod jsonerrors;
 #[cfg(feature = "process-management")]
 mod process;
+mod rate_limit;
 mod routes;
 pub mod serve;
 mod timer;
@@ -21,6 +23,7 @@ mod timer;
 mod search;
 #[cfg(test)]
 mod tests;
+mod text_extraction;
 mod trace;
 
 #[actix_web::main]
@@ -35,14 +38,21 @@ async fn main() -> errors::AtomicServerResult<()> {
                 Some(p) => std::path::Path::new(&p).to_path_buf(),
                 None => {
                     let date = chrono::Local::now().to_rfc3339();
-                    let pathstr = format!("backups/{}.json", date);
+                    let pathstr = format!("backups/{}.{}", date, e.format.extension());
                     let mut pt = config.config_dir.clone();
                     pt.push(&pathstr);
                     pt
                 }
             };
             let appstate = appstate::init(config.clone())?;
-            let outstr = appstate.store.export(!e.only_internal)?;
+            let outstr = if e.audit {
+                let entries = appstate.store.get_audit_entries(None, None, usize::MAX)?;
+                atomic_lib::audit::entries_to_json_ad(&entries)?
+            } else {
+                appstate
+                    .store
+                    .export_format(!e.only_internal, &e.format.to_format())?
+            };
             std::fs::create_dir_all(path.parent().unwrap())
                 .map_err(|e| format!("Failed to create directory {:?}. {}", path, e))?;
             let mut file = File::create(&path)
@@ -61,6 +71,44 @@ async fn main() -> errors::AtomicServerResult<()> {
             println!("Sucesfully imported {:?} to store.", o.path);
             Ok(())
         }
+        Some(config::Command::Validate(v)) => {
+            let appstate = appstate::init(config.clone())?;
+            let report = atomic_lib::validate::validate_store(&appstate.store, v.fetch);
+            let json = serde_json::to_string_pretty(&report)?;
+            match &v.path {
+                Some(path) => {
+                    std::fs::write(path, json)
+                        .map_err(|e| format!("Failed to write report to {:?}. {}", path, e))?;
+                }
+                None => println!("{}", json),
+            }
+            if report.is_valid() {
+                Ok(())
+            } else {
+                Err(format!(
+                    "Validation failed: found {} errors in {} resources",
+                    report.error_count(),
+                    report.resources.len()
+                )
+                .into())
+            }
+        }
+        Some(config::Command::CollectGarbage) => {
+            let appstate = appstate::init(config.clone())?;
+            let report = blobs::collect_garbage(
+                &config.uploads_path,
+                &appstate.store,
+                &appstate.verified_blobs,
+            )?;
+            println!(
+                "Removed {} unused uploaded files ({} bytes)",
+                report.removed, report.bytes
+            );
+            for path in &report.corrupted {
+                println!("Corrupted file, its checksum does not match: {:?}", path);
+            }
+            Ok(())
+        }
         Some(config::Command::ShowConfig) => {
             println!("{:#?}", config);
             Ok(())
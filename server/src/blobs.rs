@@ -0,0 +1,276 @@
+//! Content-addressed storage for uploaded files.
+//! Uploads are hashed while they are streamed to a temporary file, and then moved to a path that is named after their SHA-256 hash.
+//! This means identical files are only stored once, even if they are used by multiple File resources.
+//! Files that were uploaded before this used `{timestamp}-{filename}` as their name. These keep working, since the name is stored in the `internal-id` of the File.
+
+use std::{
+    collections::HashMap,
+    fs::DirEntry,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use atomic_lib::plugins::files::FileHasher;
+
+use crate::errors::AtomicServerResult;
+
+/// Prefix of files that are still being uploaded.
+const TEMP_PREFIX: &str = "upload-";
+/// Files that are younger than this are never removed by the garbage collector, since their File resource might not have been saved yet.
+const GRACE_PERIOD: Duration = Duration::from_secs(60 * 60);
+/// How often the server removes blobs that are no longer used.
+const GC_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Returns a new, unique path for an upload that is in progress.
+pub fn temp_path(uploads_path: &Path) -> PathBuf {
+    uploads_path.join(format!(
+        "{}{}",
+        TEMP_PREFIX,
+        atomic_lib::utils::random_string(16)
+    ))
+}
+
+/// Moves a finished upload to the path of its blob.
+/// If an identical blob already exists, it is replaced, which also keeps it from being garbage collected.
+/// Returns whether the blob is new.
+pub fn store_blob(uploads_path: &Path, temp: &Path, blob_id: &str) -> AtomicServerResult<bool> {
+    let blob = uploads_path.join(blob_id);
+    let is_new = !blob.exists();
+    std::fs::rename(temp, &blob)?;
+    Ok(is_new)
+}
+
+/// Hashes a stored blob, and returns whether it matches the checksum of its File.
+/// This reads the whole file, so downloads use [VerifiedBlobs] instead.
+pub fn verify_blob(path: &Path, checksum: &str) -> AtomicServerResult<bool> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = FileHasher::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finish().checksum == checksum)
+}
+
+/// Remembers which blobs matched their checksum, and when they were modified at that time.
+/// This lets downloads check the integrity of blobs, without hashing them every time.
+/// Cheap to clone, the list is shared.
+#[derive(Clone, Default)]
+pub struct VerifiedBlobs {
+    verified: Arc<Mutex<HashMap<PathBuf, (SystemTime, String)>>>,
+}
+
+impl VerifiedBlobs {
+    /// Returns whether the blob matches the checksum.
+    /// Only hashes the blob if it has not been verified since it was last modified.
+    pub fn verify(&self, path: &Path, checksum: &str) -> AtomicServerResult<bool> {
+        let modified = std::fs::metadata(path)?.modified()?;
+        if self.is_verified(path, modified, checksum) {
+            return Ok(true);
+        }
+        let valid = verify_blob(path, checksum)?;
+        self.record(path, modified, checksum, valid);
+        Ok(valid)
+    }
+
+    fn is_verified(&self, path: &Path, modified: SystemTime, checksum: &str) -> bool {
+        let verified = self.verified.lock().unwrap_or_else(|e| e.into_inner());
+        matches!(verified.get(path), Some((m, c)) if *m == modified && c == checksum)
+    }
+
+    /// Stores the outcome of [verify_blob].
+    fn record(&self, path: &Path, modified: SystemTime, checksum: &str, valid: bool) {
+        let mut verified = self.verified.lock().unwrap_or_else(|e| e.into_inner());
+        if valid {
+            verified.insert(path.into(), (modified, checksum.into()));
+        } else {
+            verified.remove(path);
+        }
+    }
+}
+
+/// What the garbage collector removed, and which blobs it found to be corrupted.
+#[derive(Debug, Default)]
+pub struct GarbageReport {
+    pub removed: usize,
+    pub bytes: u64,
+    pub corrupted: Vec<PathBuf>,
+}
+
+/// Removes the blobs that are not used by any File resource, and uploads that never finished.
+/// The blobs that are still used are checked against the checksum of their File, and logged if they are corrupted.
+/// Errors for a single file are logged, and don't stop the rest of the files from being checked.
+pub fn collect_garbage(
+    uploads_path: &Path,
+    store: &impl atomic_lib::Storelike,
+    verified: &VerifiedBlobs,
+) -> AtomicServerResult<GarbageReport> {
+    let mut report = GarbageReport::default();
+    if !uploads_path.exists() {
+        return Ok(report);
+    }
+    let referenced = atomic_lib::plugins::files::referenced_blobs(store)?;
+    for entry in std::fs::read_dir(uploads_path)? {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                tracing::error!("Failed to read the uploads directory: {}", e);
+                continue;
+            }
+        };
+        if let Err(e) = collect_entry(&entry, &referenced, verified, &mut report) {
+            tracing::error!("Failed to check uploaded file {:?}: {}", entry.path(), e);
+        }
+    }
+    tracing::info!(
+        "Removed {} unused uploaded files ({} bytes)",
+        report.removed,
+        report.bytes
+    );
+    Ok(report)
+}
+
+/// Checks a single file in the uploads directory for [collect_garbage].
+fn collect_entry(
+    entry: &DirEntry,
+    referenced: &HashMap<String, Option<String>>,
+    verified: &VerifiedBlobs,
+    report: &mut GarbageReport,
+) -> AtomicServerResult<()> {
+    let metadata = entry.metadata()?;
+    let name = entry.file_name().to_string_lossy().to_string();
+    if !metadata.is_file() {
+        return Ok(());
+    }
+    if let Some(checksum) = referenced.get(&name) {
+        // Files uploaded before checksums were added can't be verified
+        if let Some(checksum) = checksum {
+            // Always hashed, since corruption on disk does not change the modification time
+            let valid = verify_blob(&entry.path(), checksum)?;
+            verified.record(&entry.path(), metadata.modified()?, checksum, valid);
+            if !valid {
+                tracing::error!(
+                    "Checksum of {:?} does not match, the file is corrupted",
+                    entry.path()
+                );
+                report.corrupted.push(entry.path());
+            }
+        }
+        return Ok(());
+    }
+    let age = SystemTime::now()
+        .duration_since(metadata.modified()?)
+        .unwrap_or_default();
+    if age < GRACE_PERIOD {
+        return Ok(());
+    }
+    std::fs::remove_file(entry.path())?;
+    report.removed += 1;
+    report.bytes += metadata.len();
+    Ok(())
+}
+
+/// Periodically runs [collect_garbage] in the background.
+pub fn spawn_garbage_collector(appstate: &crate::appstate::AppState) {
+    let appstate = appstate.clone();
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(GC_INTERVAL);
+        // The first tick completes immediately, but hashing every upload should not slow down starting the server
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            if let Err(e) = collect_garbage(
+                &appstate.config.uploads_path,
+                &appstate.store,
+                &appstate.verified_blobs,
+            ) {
+                tracing::error!("Failed to remove unused uploaded files: {}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use atomic_lib::Storelike;
+
+    #[test]
+    fn identical_uploads_are_stored_once() {
+        let uploads_path = PathBuf::from(format!(
+            "./.temp/{}/uploads",
+            atomic_lib::utils::random_string(10)
+        ));
+        std::fs::create_dir_all(&uploads_path).unwrap();
+        let upload = |contents: &[u8]| {
+            let temp = temp_path(&uploads_path);
+            std::fs::write(&temp, contents).unwrap();
+            let mut hasher = FileHasher::new();
+            hasher.update(contents);
+            let hash = hasher.finish();
+            let is_new = store_blob(&uploads_path, &temp, &hash.blob_id).unwrap();
+            (hash, is_new)
+        };
+        let (hash, is_new) = upload(b"same");
+        assert!(is_new);
+        let (duplicate, is_new) = upload(b"same");
+        assert!(!is_new);
+        assert_eq!(hash, duplicate);
+        assert_eq!(std::fs::read_dir(&uploads_path).unwrap().count(), 1);
+
+        let blob = uploads_path.join(&hash.blob_id);
+        assert!(verify_blob(&blob, &hash.checksum).unwrap());
+        std::fs::write(&blob, b"changed").unwrap();
+        assert!(!verify_blob(&blob, &hash.checksum).unwrap());
+
+        // Recent blobs are kept, even if they are not used yet
+        let store = atomic_lib::Store::init().unwrap();
+        let verified = VerifiedBlobs::default();
+        let report = collect_garbage(&uploads_path, &store, &verified).unwrap();
+        assert_eq!(report.removed, 0);
+        assert!(report.corrupted.is_empty());
+
+        // Used blobs are checked against the checksum of their File
+        let mut file = atomic_lib::Resource::new("http://localhost/file".into());
+        file.set_propval_unsafe(
+            atomic_lib::urls::INTERNAL_ID.into(),
+            hash.blob_id.clone().into(),
+        );
+        file.set_propval_unsafe(
+            atomic_lib::urls::CHECKSUM.into(),
+            hash.checksum.clone().into(),
+        );
+        store.add_resource(&file).unwrap();
+        let report = collect_garbage(&uploads_path, &store, &verified).unwrap();
+        assert_eq!(report.corrupted, vec![blob.clone()]);
+        assert!(!verified.verify(&blob, &hash.checksum).unwrap());
+    }
+
+    #[test]
+    fn verified_blobs_are_not_hashed_again() {
+        let uploads_path = PathBuf::from(format!(
+            "./.temp/{}/uploads",
+            atomic_lib::utils::random_string(10)
+        ));
+        std::fs::create_dir_all(&uploads_path).unwrap();
+        let mut hasher = FileHasher::new();
+        hasher.update(b"contents");
+        let hash = hasher.finish();
+        let blob = uploads_path.join(&hash.blob_id);
+        std::fs::write(&blob, b"contents").unwrap();
+        let verified = VerifiedBlobs::default();
+        assert!(!verified.verify(&blob, "other checksum").unwrap());
+        assert!(verified.verify(&blob, &hash.checksum).unwrap());
+        // Same modification time, so the blob is not hashed again
+        let modified = std::fs::metadata(&blob).unwrap().modified().unwrap();
+        assert!(verified.is_verified(&blob, modified, &hash.checksum));
+        verified.record(&blob, modified, &hash.checksum, false);
+        assert!(!verified.is_verified(&blob, modified, &hash.checksum));
+    }
+}
@@ -13,7 +13,7 @@ use actix::{
     prelude::{Actor, Context, Handler},
     Addr,
 };
-use atomic_lib::{Db, Storelike};
+use atomic_lib::{urls, Db, Resource, Storelike};
 use chrono::Local;
 use std::collections::{HashMap, HashSet};
 
@@ -106,7 +106,33 @@ impl CommitMonitor {
                 crate::search::remove_resource(&self.search_state, &target)?;
             };
             // Add new resource to search index
-            crate::search::add_resource(&self.search_state, resource)?;
+            crate::search::add_resource(
+                &self.search_state,
+                resource,
+                &mut crate::search::LanguageResolver::default(),
+                &self.store,
+            )?;
+            // Resources in a Drive are analyzed using the language of the Drive, so they need to be indexed again
+            let commit = &msg.commit_response.commit_struct;
+            let changed = |prop: &str| {
+                commit
+                    .set
+                    .as_ref()
+                    .map(|set| set.contains_key(prop))
+                    .unwrap_or(false)
+                    || commit
+                        .remove
+                        .as_ref()
+                        .map(|remove| remove.iter().any(|p| p == prop))
+                        .unwrap_or(false)
+            };
+            if changed(urls::SEARCH_LANGUAGE) {
+                self.search_state
+                    .update_drive_language(&target, &self.store)?;
+                crate::search::reindex_tree(&self.search_state, &target, &self.store)?;
+            } else if changed(urls::PARENT) {
+                self.reindex_if_moved(&msg, resource)?;
+            }
 
             // TODO: This is not ideal, as it does not _delay_ the search index update, but it prevents it.
             // Current implementation should work just fine in most scenario's.
@@ -122,6 +148,29 @@ impl CommitMonitor {
         } else {
             // If there is no new resource, it must have been deleted, so let's remove it from the search index.
             crate::search::remove_resource(&self.search_state, &target)?;
+            self.search_state
+                .update_drive_language(&target, &self.store)?;
+        }
+        Ok(())
+    }
+
+    /// Indexes the Resources below a moved Resource again, if it moved to a Drive with another language.
+    fn reindex_if_moved(&self, msg: &CommitMessage, resource: &Resource) -> AtomicServerResult<()> {
+        let old_parent = match msg
+            .commit_response
+            .resource_old
+            .as_ref()
+            .and_then(|old| old.get(urls::PARENT).ok())
+        {
+            Some(parent) => parent.to_string(),
+            // New Resources don't have children yet
+            None => return Ok(()),
+        };
+        let mut languages = crate::search::LanguageResolver::default();
+        if languages.language_of(&old_parent, &self.store)?
+            != languages.language_of(resource.get_subject(), &self.store)?
+        {
+            crate::search::reindex_tree(&self.search_state, resource.get_subject(), &self.store)?;
         }
         Ok(())
     }
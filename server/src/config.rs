@@ -77,10 +77,71 @@ pub struct Opts {
     #[clap(long, env = "ATOMIC_REMOVE_PREVIOUS_SEARCH")]
     pub remove_previous_search: bool,
 
+    /// Language used for stemming words in the full-text search index. Changing this triggers a rebuild of the search index on the next start. Drives can use a different language by setting `searchLanguage`.
+    #[clap(
+        arg_enum,
+        long,
+        default_value = "english",
+        env = "ATOMIC_SEARCH_LANGUAGE"
+    )]
+    pub search_language: SearchLanguage,
+
+    /// Removes common words (such as `the` and `a`) from the search index. Only available for English.
+    #[clap(long, env = "ATOMIC_SEARCH_STOPWORDS")]
+    pub search_stopwords: bool,
+
+    /// Also index values as n-grams, which enables searching for parts of words (e.g. `tom` finds `atomic`). Makes the search index considerably larger.
+    #[clap(long, env = "ATOMIC_SEARCH_NGRAM")]
+    pub search_ngram: bool,
+
+    /// Validates every incoming Commit against the Classes of the Resource: required properties, datatypes, allowed values and classtypes of linked resources. Rejects the Commit with a list of violations if it doesn't match.
+    #[clap(long, env = "ATOMIC_STRICT_SCHEMA")]
+    pub strict_schema: bool,
+
+    /// How long the session cookies handed out by the `/session` endpoint stay valid, in hours.
+    #[clap(long, default_value = "168", env = "ATOMIC_SESSION_HOURS")]
+    pub session_hours: i64,
+
     /// CAUTION: Skip authentication checks, making all data publicly readable. Improves performance.
     #[clap(long, env = "ATOMIC_PUBLIC_MODE")]
     pub public_mode: bool,
 
+    /// Maximum amount of requests per minute for anonymous clients, per IP address. Set to 0 to disable rate limiting.
+    #[clap(long, default_value = "600", env = "ATOMIC_RATE_LIMIT")]
+    pub rate_limit: u32,
+
+    /// Maximum amount of requests per minute for authenticated Agents. Authenticated Agents also get proportionally larger budgets for searching, uploading and writing.
+    #[clap(long, default_value = "3000", env = "ATOMIC_RATE_LIMIT_AGENT")]
+    pub rate_limit_agent: u32,
+
+    /// Maximum amount of search requests per minute for anonymous clients. Set to 0 for no separate limit.
+    #[clap(long, default_value = "60", env = "ATOMIC_RATE_LIMIT_SEARCH")]
+    pub rate_limit_search: u32,
+
+    /// Maximum amount of uploads and CSV imports per minute for anonymous clients. Set to 0 for no separate limit.
+    #[clap(long, default_value = "20", env = "ATOMIC_RATE_LIMIT_UPLOAD")]
+    pub rate_limit_upload: u32,
+
+    /// Maximum amount of Commits and accepted Invites per minute for anonymous clients. Set to 0 for no separate limit.
+    #[clap(long, default_value = "60", env = "ATOMIC_RATE_LIMIT_WRITE")]
+    pub rate_limit_write: u32,
+
+    /// Identify anonymous clients by the `Forwarded` or `X-Forwarded-For` header, instead of the IP address of the connection. Only enable this behind a reverse proxy that sets these headers, since clients can fake them.
+    #[clap(long, env = "ATOMIC_RATE_LIMIT_FORWARDED")]
+    pub rate_limit_forwarded: bool,
+
+    /// Maximum size of an uploaded file, in megabytes. Set to 0 for no limit.
+    #[clap(long, default_value = "100", env = "ATOMIC_MAX_FILE_SIZE")]
+    pub max_file_size: u64,
+
+    /// Maximum size of a Commit, in kilobytes.
+    #[clap(long, default_value = "1024", env = "ATOMIC_MAX_COMMIT_SIZE")]
+    pub max_commit_size: usize,
+
+    /// Maximum total size of the Files in a Drive, in megabytes. Set to 0 for no limit. The current usage is shown in the `quota-usage` of the Drive.
+    #[clap(long, default_value = "0", env = "ATOMIC_DRIVE_QUOTA")]
+    pub drive_quota: u64,
+
     /// The full URL of the server. It should resolve to the home page. Set this if you use an external server or tunnel, instead of directly exposing atomic-server. If you leave this out, it will be generated from `domain`, `port` and `http` / `https`.
     #[clap(long, env = "ATOMIC_SERVER_URL")]
     pub server_url: Option<String>,
@@ -104,6 +165,33 @@ pub enum Tracing {
     Opentelemetry,
 }
 
+/// Languages for which the search index can reduce words to their stem (e.g. `running` => `run`).
+#[derive(
+    clap::ArgEnum, Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub enum SearchLanguage {
+    /// Don't apply stemming, only lowercase words
+    None,
+    Arabic,
+    Danish,
+    Dutch,
+    English,
+    Finnish,
+    French,
+    German,
+    Greek,
+    Hungarian,
+    Italian,
+    Norwegian,
+    Portuguese,
+    Romanian,
+    Russian,
+    Spanish,
+    Swedish,
+    Tamil,
+    Turkish,
+}
+
 #[derive(clap::ArgEnum, Clone, Debug)]
 pub enum LogLevel {
     Warn,
@@ -126,6 +214,12 @@ pub enum Command {
     /// Returns the currently selected options, based on the passed flags and parsed environment variables.
     #[clap(name = "show-config")]
     ShowConfig,
+    /// Checks all resources in the store against their Classes and Properties. Prints a JSON report and exits with an error if anything is invalid.
+    #[clap(name = "validate")]
+    Validate(ValidateOpts),
+    /// Removes uploaded files that are no longer used by any File resource. The server also does this once a day.
+    #[clap(name = "collect-garbage")]
+    CollectGarbage,
     /// Danger! Removes all data from the store.
     #[clap(name = "reset")]
     Reset,
@@ -133,12 +227,55 @@ pub enum Command {
 
 #[derive(Parser, Clone, Debug)]
 pub struct ExportOpts {
-    /// Where the exported file should be saved  "~/.config/atomic/backups/{date}.json"
+    /// Where the exported file should be saved  "~/.config/atomic/backups/{date}.{extension}"
     #[clap(short)]
     pub path: Option<PathBuf>,
     /// Do not export resources that are externally defined, which are cached by this Server.
     #[clap(long)]
     pub only_internal: bool,
+    /// Export the audit log instead of the resources.
+    #[clap(long)]
+    pub audit: bool,
+    /// Serialization format of the exported resources.
+    #[clap(arg_enum, long, default_value = "json-ad")]
+    pub format: ExportFormat,
+}
+
+#[derive(clap::ArgEnum, Clone, Copy, Debug)]
+pub enum ExportFormat {
+    /// JSON-AD, which can be imported again
+    JsonAd,
+    /// RDF Turtle, with prefixes and grouped subjects
+    Turtle,
+    /// RDF N-Triples, one triple per line
+    NTriples,
+    /// RDF/XML
+    RdfXml,
+    /// RDF TriG, which contains the changes of every Commit as a named graph
+    Trig,
+}
+
+impl ExportFormat {
+    pub fn to_format(self) -> atomic_lib::serialize::Format {
+        match self {
+            ExportFormat::JsonAd => atomic_lib::serialize::Format::JsonAd,
+            ExportFormat::Turtle => atomic_lib::serialize::Format::Turtle,
+            ExportFormat::NTriples => atomic_lib::serialize::Format::NTriples,
+            ExportFormat::RdfXml => atomic_lib::serialize::Format::RdfXml,
+            ExportFormat::Trig => atomic_lib::serialize::Format::TriG,
+        }
+    }
+
+    /// File extension of exports in this format
+    pub fn extension(&self) -> &str {
+        match self {
+            ExportFormat::JsonAd => "json",
+            ExportFormat::Turtle => "ttl",
+            ExportFormat::NTriples => "nt",
+            ExportFormat::RdfXml => "rdf",
+            ExportFormat::Trig => "trig",
+        }
+    }
 }
 
 #[derive(Parser, Clone, Debug)]
@@ -148,6 +285,16 @@ pub struct ImportOpts {
     pub path: PathBuf,
 }
 
+#[derive(Parser, Clone, Debug)]
+pub struct ValidateOpts {
+    /// Also check whether every resource can be fetched from its URL. Slow!
+    #[clap(long)]
+    pub fetch: bool,
+    /// Where the JSON report should be saved. Prints to stdout if not set.
+    #[clap(short)]
+    pub path: Option<PathBuf>,
+}
+
 /// Start atomic-server, oi mate
 #[derive(Parser, Clone, Debug)]
 pub struct ServerOpts {}
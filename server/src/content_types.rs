@@ -2,7 +2,9 @@
 
 use actix_web::http::header::HeaderMap;
 
-#[derive(Debug, PartialEq)]
+use crate::errors::{AppErrorType, AtomicServerError, AtomicServerResult};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ContentType {
     /// Plain JSON, using shortnames as keys instead of URLs
     /// https://docs.atomicdata.dev/interoperability/json.html#atomic-data-as-plain-json
@@ -13,6 +15,9 @@ pub enum ContentType {
     /// JSON-LD, RDF compatible JSON with @context mapping
     /// https://docs.atomicdata.dev/interoperability/json.html#from-json-to-json-ad
     JsonLd,
+    /// JSON-LD that refers to the shared `/context` document, instead of including its own @context.
+    /// Requested using the `profile` parameter of JSON-LD.
+    JsonLdCompacted,
     Html,
     /// RDF Turtle format
     /// https://www.w3.org/TR/turtle/
@@ -20,15 +25,24 @@ pub enum ContentType {
     /// RDF N-Triples format
     /// https://www.w3.org/TR/n-triples/
     NTriples,
+    /// RDF/XML format
+    /// https://www.w3.org/TR/rdf-syntax-grammar/
+    RdfXml,
+    /// Comma separated values, for spreadsheets. Only available for Collections.
+    /// https://tools.ietf.org/html/rfc4180
+    Csv,
 }
 
 const MIME_HTML: &str = "text/html";
-const MIME_XML: &str = "application/xml";
 const MIME_JSON: &str = "application/json";
 const MIME_JSONLD: &str = "application/ld+json";
 const MIME_JSONAD: &str = "application/ad+json";
 const MIME_TURTLE: &str = "text/turtle";
 const MIME_NT: &str = "application/n-triples";
+const MIME_RDF_XML: &str = "application/rdf+xml";
+const MIME_CSV: &str = "text/csv";
+const MIME_JSONLD_COMPACTED: &str =
+    "application/ld+json; profile=\"http://www.w3.org/ns/json-ld#compacted\"";
 
 impl ContentType {
     pub fn to_mime(&self) -> &str {
@@ -39,50 +53,256 @@ impl ContentType {
             ContentType::Html => MIME_HTML,
             ContentType::Turtle => MIME_TURTLE,
             ContentType::NTriples => MIME_NT,
+            ContentType::RdfXml => MIME_RDF_XML,
+            ContentType::JsonLdCompacted => MIME_JSONLD_COMPACTED,
+            ContentType::Csv => MIME_CSV,
+        }
+    }
+}
+
+/// All content types, in order of preference of the server.
+/// Used when the client accepts multiple types equally, e.g. `*/*`.
+pub const ALL_CONTENT_TYPES: [ContentType; 9] = [
+    ContentType::Html,
+    ContentType::JsonAd,
+    ContentType::Json,
+    ContentType::JsonLd,
+    ContentType::JsonLdCompacted,
+    ContentType::Turtle,
+    ContentType::NTriples,
+    ContentType::RdfXml,
+    ContentType::Csv,
+];
+
+impl ContentType {
+    /// Parses the value of the `?format=` query parameter, which overrides the Accept header.
+    pub fn from_format(format: &str) -> Option<ContentType> {
+        match format {
+            "html" => Some(ContentType::Html),
+            "jsonad" | "json-ad" => Some(ContentType::JsonAd),
+            "json" => Some(ContentType::Json),
+            "jsonld" | "json-ld" => Some(ContentType::JsonLd),
+            "turtle" | "ttl" => Some(ContentType::Turtle),
+            "ntriples" | "nt" => Some(ContentType::NTriples),
+            "jsonld-compacted" => Some(ContentType::JsonLdCompacted),
+            "rdfxml" | "rdf" | "xml" => Some(ContentType::RdfXml),
+            "csv" => Some(ContentType::Csv),
+            _ => None,
         }
     }
 }
 
-/// Returns the preferred content type.
-/// Defaults to HTML if none is found.
-pub fn get_accept(map: &HeaderMap) -> ContentType {
+/// Picks the content type for the response from the `offered` ones, which should be sorted by preference.
+/// The `format` query parameter takes precedence over the Accept header.
+/// Without both, the first offered type is used.
+/// Returns a `406 Not Acceptable` error if none of the offered types is acceptable.
+pub fn negotiate(
+    map: &HeaderMap,
+    query: &str,
+    offered: &[ContentType],
+) -> AtomicServerResult<ContentType> {
+    let not_acceptable = |message: String| AtomicServerError {
+        message,
+        error_type: AppErrorType::NotAcceptable,
+    };
+    if let Some(format) = get_format_param(query) {
+        return match ContentType::from_format(&format) {
+            Some(content_type) if offered.contains(&content_type) => Ok(content_type),
+            _ => Err(not_acceptable(format!(
+                "Format `{}` is not available here. Use one of: {}",
+                format,
+                list_mimes(offered)
+            ))),
+        };
+    }
     let accept_header = match map.get("Accept") {
         Some(header) => header.to_str().unwrap_or(""),
-        None => return ContentType::Html,
+        None => "",
     };
-    parse_accept_header(accept_header)
+    parse_accept_header(accept_header, offered).ok_or_else(|| {
+        not_acceptable(format!(
+            "None of the types in the Accept header `{}` are available here. Use one of: {}",
+            accept_header,
+            list_mimes(offered)
+        ))
+    })
 }
 
-/// Parses an HTTP Accept header
-/// Does not fully adhere to the RFC spec: https://tools.ietf.org/html/rfc7231
-/// Does not take into consideration the q value, simply reads the first thing before the comma
-/// Defaults to HTML
-pub fn parse_accept_header(header: &str) -> ContentType {
-    for mimepart in header.split(',') {
-        if mimepart.contains(MIME_JSONAD) {
-            return ContentType::JsonAd;
-        }
-        if mimepart.contains(MIME_HTML) {
-            return ContentType::Html;
-        }
-        if mimepart.contains(MIME_XML) {
-            return ContentType::Html;
-        }
-        if mimepart.contains(MIME_JSON) {
-            return ContentType::Json;
+/// Returns the preferred content type for a Resource.
+/// Defaults to HTML if there is no Accept header.
+pub fn get_accept(map: &HeaderMap, query: &str) -> AtomicServerResult<ContentType> {
+    negotiate(map, query, &ALL_CONTENT_TYPES)
+}
+
+fn list_mimes(offered: &[ContentType]) -> String {
+    offered
+        .iter()
+        .map(|c| c.to_mime())
+        .collect::<Vec<&str>>()
+        .join(", ")
+}
+
+/// Returns the value of the `format` query parameter, if present.
+fn get_format_param(query: &str) -> Option<String> {
+    query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("format="))
+        .map(|format| format.to_lowercase())
+}
+
+/// Removes the `format` query parameter, since it is not part of the subject of the requested Resource.
+pub fn remove_format_param(query: &str) -> String {
+    query
+        .split('&')
+        .filter(|pair| !pair.starts_with("format="))
+        .collect::<Vec<&str>>()
+        .join("&")
+}
+
+/// A media range from an Accept header, e.g. `application/*;q=0.8`
+struct MediaRange {
+    mime_type: String,
+    subtype: String,
+    /// Amount of parameters other than `q`, which make a range more specific.
+    params: usize,
+    /// The `profile` parameter, which is used to pick a specific form of JSON-LD.
+    profile: Option<String>,
+    q: f32,
+}
+
+impl MediaRange {
+    fn parse(part: &str) -> Option<MediaRange> {
+        let mut params = part.split(';');
+        let (mime_type, subtype) = params
+            .next()?
+            .trim()
+            .to_lowercase()
+            .split_once('/')
+            .map(|(t, s)| (t.trim().to_string(), s.trim().to_string()))?;
+        let mut q = 1.0;
+        let mut other_params = 0;
+        let mut profile = None;
+        for param in params.map(|p| p.trim()).filter(|p| !p.is_empty()) {
+            if let Some(value) = param.strip_prefix("q=") {
+                q = value.trim().parse::<f32>().ok()?.clamp(0.0, 1.0);
+                continue;
+            }
+            if let Some(value) = param.strip_prefix("profile=") {
+                profile = Some(value.trim().trim_matches('"').to_string());
+            }
+            other_params += 1;
         }
-        if mimepart.contains(MIME_JSONLD) {
-            return ContentType::JsonLd;
+        Some(MediaRange {
+            mime_type,
+            subtype,
+            params: other_params,
+            profile,
+            q,
+        })
+    }
+
+    /// How specifically this range matches the MIME type, or None if it does not match.
+    /// A range with a `profile` only matches MIME types with the same profile.
+    fn specificity(&self, mime: &str) -> Option<usize> {
+        let (essence, mime_profile) = match mime.split_once(';') {
+            Some((essence, params)) => (
+                essence,
+                params
+                    .trim()
+                    .strip_prefix("profile=")
+                    .map(|p| p.trim_matches('"')),
+            ),
+            None => (mime, None),
+        };
+        if self.profile.is_some() && self.profile.as_deref() != mime_profile {
+            return None;
         }
-        if mimepart.contains(MIME_TURTLE) {
-            return ContentType::Turtle;
+        let (mime_type, subtype) = essence.split_once('/')?;
+        if self.mime_type == "*" && self.subtype == "*" {
+            Some(self.params)
+        } else if self.mime_type == mime_type && self.subtype == "*" {
+            Some(100 + self.params)
+        } else if self.mime_type == mime_type && self.subtype == subtype {
+            Some(200 + self.params)
+        } else {
+            None
         }
-        if mimepart.contains(MIME_NT) {
-            return ContentType::NTriples;
+    }
+}
+
+/// Parses an HTTP Accept header, and picks the best of the `offered` content types.
+/// Follows https://tools.ietf.org/html/rfc7231#section-5.3.2 :
+/// every type gets the `q` value of the most specific range that matches it, and the highest `q` wins.
+/// Types with an equal `q` are sorted by their position in the header, then by the order of `offered`.
+/// Returns the first offered type if the header is empty, and None if nothing is acceptable.
+pub fn parse_accept_header(header: &str, offered: &[ContentType]) -> Option<ContentType> {
+    let ranges: Vec<MediaRange> = header.split(',').filter_map(MediaRange::parse).collect();
+    if ranges.is_empty() {
+        return offered.first().copied();
+    }
+    let mut best: Option<(ContentType, f32, usize)> = None;
+    for content_type in offered {
+        let matched = ranges
+            .iter()
+            .enumerate()
+            .filter_map(|(i, range)| {
+                range
+                    .specificity(content_type.to_mime())
+                    .map(|specificity| (i, range, specificity))
+            })
+            .max_by_key(|(_, _, specificity)| *specificity);
+        if let Some((position, range, _)) = matched {
+            if range.q <= 0.0 {
+                continue;
+            }
+            let is_better = match best {
+                None => true,
+                Some((_, best_q, best_position)) => {
+                    range.q > best_q || (range.q == best_q && position < best_position)
+                }
+            };
+            if is_better {
+                best = Some((*content_type, range.q, position));
+            }
         }
     }
-    tracing::info!("Unknown Accept header, defaut to HTML: {}", header);
-    ContentType::Html
+    best.map(|(content_type, _, _)| content_type)
+}
+
+/// Returns the languages from the `Accept-Language` header, most preferred first.
+/// Returns an empty Vec if the header is missing.
+pub fn get_accept_language(map: &HeaderMap) -> Vec<String> {
+    match map.get("Accept-Language") {
+        Some(header) => parse_accept_language_header(header.to_str().unwrap_or("")),
+        None => Vec::new(),
+    }
+}
+
+/// Parses an HTTP Accept-Language header, e.g. `nl-NL,nl;q=0.9,en;q=0.8`.
+/// Sorts the languages by their q value. Languages with `q=0` are removed.
+pub fn parse_accept_language_header(header: &str) -> Vec<String> {
+    let mut languages: Vec<(String, f32)> = header
+        .split(',')
+        .filter_map(|part| {
+            let mut params = part.split(';');
+            let language = params.next()?.trim();
+            if language.is_empty() {
+                return None;
+            }
+            let q = params
+                .find_map(|p| p.trim().strip_prefix("q="))
+                .and_then(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            Some((language.to_string(), q))
+        })
+        .filter(|(_, q)| *q > 0.0)
+        .collect();
+    // Stable sort keeps the header order for equal q values
+    languages.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    languages
+        .into_iter()
+        .map(|(language, _)| language)
+        .collect()
 }
 
 #[cfg(test)]
@@ -91,14 +311,72 @@ mod test {
 
     #[test]
     fn parse_types() {
-        assert!(parse_accept_header("text/html,application/xml") == ContentType::Html);
-        assert!(parse_accept_header("application/ad+json") == ContentType::JsonAd);
-        assert!(parse_accept_header("application/ld+json") == ContentType::JsonLd);
+        let parse = |header| parse_accept_header(header, &ALL_CONTENT_TYPES);
+        assert!(parse("text/html,application/xml") == Some(ContentType::Html));
+        assert!(parse("application/ad+json") == Some(ContentType::JsonAd));
+        assert!(parse("application/ld+json") == Some(ContentType::JsonLd));
     }
 
     #[test]
     fn parse_types_with_blank_chars() {
-        assert!(parse_accept_header("application/ad+json ; ") == ContentType::JsonAd);
-        assert!(parse_accept_header(" application/ad+json ; ") == ContentType::JsonAd);
+        let parse = |header| parse_accept_header(header, &ALL_CONTENT_TYPES);
+        assert!(parse("application/ad+json ; ") == Some(ContentType::JsonAd));
+        assert!(parse(" application/ad+json ; ") == Some(ContentType::JsonAd));
+    }
+
+    #[test]
+    fn parse_types_with_q_values() {
+        let parse = |header| parse_accept_header(header, &ALL_CONTENT_TYPES);
+        assert_eq!(
+            parse("text/html;q=0.5, text/turtle"),
+            Some(ContentType::Turtle)
+        );
+        assert_eq!(
+            parse("application/json;q=0.9, application/*;q=0.95"),
+            Some(ContentType::JsonAd)
+        );
+        // The most specific range decides the q value
+        assert_eq!(parse("*/*;q=0.1, text/html;q=0"), Some(ContentType::JsonAd));
+        assert_eq!(parse("*/*"), Some(ContentType::Html));
+        assert_eq!(parse(""), Some(ContentType::Html));
+        assert_eq!(parse("image/png"), None);
+        assert_eq!(
+            parse_accept_header("text/html", &[ContentType::JsonAd, ContentType::Turtle]),
+            None
+        );
+        assert_eq!(
+            parse_accept_header("text/*", &[ContentType::JsonAd, ContentType::Turtle]),
+            Some(ContentType::Turtle)
+        );
+    }
+
+    #[test]
+    fn parse_types_with_profile() {
+        let parse = |header| parse_accept_header(header, &ALL_CONTENT_TYPES);
+        assert_eq!(parse("application/ld+json"), Some(ContentType::JsonLd));
+        assert_eq!(
+            parse("application/ld+json;profile=\"http://www.w3.org/ns/json-ld#compacted\""),
+            Some(ContentType::JsonLdCompacted)
+        );
+        assert_eq!(
+            parse("application/rdf+xml, */*;q=0.1"),
+            Some(ContentType::RdfXml)
+        );
+    }
+
+    #[test]
+    fn format_param() {
+        assert_eq!(remove_format_param("page=2&format=ttl"), "page=2");
+        assert_eq!(get_format_param("format=JSONAD").as_deref(), Some("jsonad"));
+        assert_eq!(get_format_param("page=2"), None);
+    }
+
+    #[test]
+    fn parse_accept_language() {
+        assert_eq!(
+            parse_accept_language_header("en;q=0.8, nl-NL, nl;q=0.9, de;q=0"),
+            vec!["nl-NL", "nl", "en"]
+        );
+        assert!(parse_accept_language_header("").is_empty());
     }
 }
@@ -9,6 +9,12 @@ pub type AtomicServerResult<T> = std::result::Result<T, AtomicServerError>;
 pub enum AppErrorType {
     NotFound,
     Unauthorized,
+    /// The request resulted in a Resource that does not match its Schema. Message is JSON.
+    Schema,
+    /// None of the content types that the client accepts are available.
+    NotAcceptable,
+    /// The request is larger than a configured limit or quota.
+    TooLarge,
     Other,
 }
 
@@ -35,12 +41,19 @@ impl ResponseError for AtomicServerError {
             AppErrorType::NotFound => StatusCode::NOT_FOUND,
             AppErrorType::Other => StatusCode::INTERNAL_SERVER_ERROR,
             AppErrorType::Unauthorized => StatusCode::UNAUTHORIZED,
+            AppErrorType::Schema => StatusCode::BAD_REQUEST,
+            AppErrorType::NotAcceptable => StatusCode::NOT_ACCEPTABLE,
+            AppErrorType::TooLarge => StatusCode::PAYLOAD_TOO_LARGE,
         }
     }
     fn error_response(&self) -> HttpResponse {
         let body = self.message.clone();
         tracing::info!("Error reponse {}: {}", self.status_code(), self.message);
-        HttpResponse::build(self.status_code()).body(body)
+        let mut builder = HttpResponse::build(self.status_code());
+        if let AppErrorType::Schema = self.error_type {
+            builder.content_type("application/json");
+        }
+        builder.body(body)
     }
 }
 
@@ -96,6 +109,15 @@ impl From<std::io::Error> for AtomicServerError {
     }
 }
 
+impl From<serde_json::Error> for AtomicServerError {
+    fn from(error: serde_json::Error) -> Self {
+        AtomicServerError {
+            message: error.to_string(),
+            error_type: AppErrorType::Other,
+        }
+    }
+}
+
 impl From<tantivy::directory::error::OpenDirectoryError> for AtomicServerError {
     fn from(error: tantivy::directory::error::OpenDirectoryError) -> Self {
         AtomicServerError {
@@ -138,6 +160,7 @@ impl From<atomic_lib::errors::AtomicError> for AtomicServerError {
         let error_type = match error.error_type {
             atomic_lib::errors::AtomicErrorType::NotFoundError => AppErrorType::NotFound,
             atomic_lib::errors::AtomicErrorType::UnauthorizedError => AppErrorType::Unauthorized,
+            atomic_lib::errors::AtomicErrorType::SchemaError => AppErrorType::Schema,
             _ => AppErrorType::Other,
         };
         AtomicServerError {
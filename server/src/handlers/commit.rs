@@ -22,6 +22,7 @@ pub async fn post_commit(
     }
     let opts = CommitOpts {
         validate_schema: true,
+        validate_strict_schema: appstate.config.opts.strict_schema,
         validate_signature: true,
         validate_timestamp: true,
         validate_rights: true,
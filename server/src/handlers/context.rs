@@ -0,0 +1,20 @@
+//! The shared JSON-LD @context document, which maps the shortnames of all Properties to their URLs.
+//! Compacted JSON-LD responses refer to it, so they don't have to include their own @context.
+
+use actix_web::{web, HttpResponse};
+
+use crate::{appstate::AppState, errors::AtomicServerResult};
+
+#[tracing::instrument(skip(appstate))]
+pub async fn json_ld_context(appstate: web::Data<AppState>) -> AtomicServerResult<HttpResponse> {
+    let context = atomic_lib::serialize::json_ld_context(&appstate.store)?;
+    let mut document = serde_json::Map::new();
+    document.insert("@context".into(), context.into());
+    let body = serde_json::to_string_pretty(&document)
+        .map_err(|e| format!("Could not serialize the @context. {}", e))?;
+    Ok(HttpResponse::Ok()
+        .content_type("application/ld+json")
+        // Properties are rarely changed, but they can be, so clients have to check
+        .append_header(("Cache-Control", "no-cache"))
+        .body(body))
+}
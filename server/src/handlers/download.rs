@@ -1,6 +1,9 @@
 use actix_files::NamedFile;
 use actix_web::{web, HttpRequest, HttpResponse};
-use atomic_lib::{urls, Resource, Storelike};
+use atomic_lib::{
+    audit::{AuditAction, AuditEntry},
+    urls, Resource, Storelike,
+};
 
 use crate::{appstate::AppState, errors::AtomicServerResult, helpers::get_client_agent};
 
@@ -27,6 +30,13 @@ pub async fn handle_download(
     let for_agent = get_client_agent(headers, &appstate, subject.clone())?;
     tracing::info!("handle_download: {}", subject);
     let resource = store.get_resource_extended(&subject, false, for_agent.as_deref())?;
+    store.add_audit_entry(&AuditEntry::new(
+        AuditAction::Download,
+        for_agent.as_deref().unwrap_or(urls::PUBLIC_AGENT),
+        &subject,
+        true,
+        None,
+    ));
     download_file_handler_partial(&resource, &req, &appstate)
 }
 
@@ -40,6 +50,19 @@ pub fn download_file_handler_partial(
         .map_err(|e| format!("Internal ID of file could not be resolved. {}", e))?;
     let mut file_path = appstate.config.uploads_path.clone();
     file_path.push(file_name.to_string());
+    // Files uploaded before checksums were added can't be verified
+    if let Ok(checksum) = resource.get(urls::CHECKSUM) {
+        if !appstate
+            .verified_blobs
+            .verify(&file_path, &checksum.to_string())?
+        {
+            tracing::error!(
+                "Checksum of {:?} does not match, the file is corrupted",
+                file_path
+            );
+            return Err("The file is corrupted, its checksum does not match.".into());
+        }
+    }
     let file = NamedFile::open(file_path)?;
     Ok(file.into_response(req))
 }
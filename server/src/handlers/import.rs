@@ -0,0 +1,51 @@
+use actix_web::{web, HttpResponse};
+use atomic_lib::{hierarchy::check_write, AtomicError, Storelike};
+use serde::Deserialize;
+
+use crate::{appstate::AppState, errors::AtomicServerResult, helpers::get_client_agent};
+
+#[derive(Deserialize, Debug)]
+pub struct ImportQuery {
+    /// The Resource that the new Resources are placed in. The Agent needs write rights here.
+    parent: String,
+    /// The Class of the new Resources. Used for finding the Properties of the columns.
+    class: String,
+}
+
+/// Creates a new Resource for every row of the CSV in the body, using the `/import?parent=&class=` endpoint.
+/// The header row contains the shortnames (or URLs) of the Properties, like in CSV exports of Collections.
+/// Responds with the created Resources as JSON-AD.
+#[tracing::instrument(skip(appstate, req, body))]
+pub async fn import_csv(
+    body: String,
+    appstate: web::Data<AppState>,
+    query: web::Query<ImportQuery>,
+    req: actix_web::HttpRequest,
+) -> AtomicServerResult<HttpResponse> {
+    let store = &appstate.store;
+    let parent = store.get_resource(&query.parent)?;
+    let subject = format!(
+        "{}{}",
+        store.get_server_url(),
+        req.head()
+            .uri
+            .path_and_query()
+            .ok_or("Path must be given")?
+    );
+    let agent = match get_client_agent(req.headers(), &appstate, subject)? {
+        Some(agent) => {
+            check_write(store, &parent, &agent)?;
+            agent
+        }
+        None => {
+            return Err(AtomicError::unauthorized(
+                "No authorization headers present. These are required when importing.".into(),
+            )
+            .into())
+        }
+    };
+    let created = atomic_lib::csv::import_csv(&body, &query.class, &query.parent, &agent, store)?;
+    Ok(HttpResponse::Ok()
+        .content_type("application/ad+json")
+        .body(atomic_lib::serialize::resources_to_json_ad(&created)?))
+}
@@ -1,8 +1,12 @@
 pub mod commit;
+pub mod context;
 pub mod download;
+pub mod import;
 pub mod resource;
 pub mod search;
+pub mod session;
 pub mod single_page_app;
+pub mod tokens;
 pub mod tpf;
 pub mod upload;
 pub mod web_sockets;
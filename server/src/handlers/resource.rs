@@ -1,13 +1,17 @@
 use crate::{
     appstate::AppState,
-    content_types::get_accept,
     content_types::ContentType,
-    errors::AtomicServerResult,
-    helpers::{get_client_agent, try_extension},
+    content_types::{get_accept, get_accept_language, remove_format_param},
+    errors::{AppErrorType, AtomicServerError, AtomicServerResult},
+    helpers::{
+        create_etag, etag_matches, get_client_agent, get_static_last_commit, serialize_resource,
+        try_extension,
+    },
     timer::Timer,
 };
-use actix_web::{web, HttpResponse};
-use atomic_lib::Storelike;
+use actix_web::{http::header::HttpDate, web, HttpResponse, HttpResponseBuilder};
+use atomic_lib::{urls, Resource, Storelike};
+use futures::StreamExt;
 
 /// Respond to a single resource.
 /// The URL should match the Subject of the resource.
@@ -20,7 +24,7 @@ pub async fn handle_get_resource(
     let mut timer = Timer::new();
 
     let headers = req.headers();
-    let mut content_type = get_accept(headers);
+    let mut content_type = get_accept(headers, req.query_string())?;
     let server_url = &appstate.config.server_url;
     // Get the subject from the path, or return the home URL
     let subject = if let Some(subj_end) = path {
@@ -37,10 +41,11 @@ pub async fn handle_get_resource(
             }
             // Check extensions and set datatype. Harder than it looks to get right...
             // This might not be the best way of creating the subject. But I can't access the full URL from any actix stuff!
-            let querystring = if req.query_string().is_empty() {
+            let query = remove_format_param(req.query_string());
+            let querystring = if query.is_empty() {
                 "".to_string()
             } else {
-                format!("?{}", req.query_string())
+                format!("?{}", query)
             };
             let subject = format!("{}/{}{}", server_url, subj_end_string, querystring);
             subject
@@ -53,34 +58,139 @@ pub async fn handle_get_resource(
     let store = &appstate.store;
     timer.add("parse_headers");
 
-    let for_agent = get_client_agent(headers, &appstate, subject.clone())?;
+    // Clients sign the URL that they request, which might include the `format` query parameter
+    let signed_subject = if remove_format_param(req.query_string()) == req.query_string() {
+        subject.clone()
+    } else {
+        format!("{}{}", server_url, req.uri())
+    };
+    let for_agent = get_client_agent(headers, &appstate, signed_subject)?;
     timer.add("get_agent");
 
-    let mut builder = HttpResponse::Ok();
-
     tracing::debug!("get_resource: {} as {}", subject, content_type.to_mime());
-    builder.append_header(("Content-Type", content_type.to_mime()));
-    // This prevents the browser from displaying the JSON response upon re-opening a closed tab
-    // https://github.com/joepio/atomic-data-rust/issues/137
-    builder.append_header((
-        "Cache-Control",
-        "no-store, no-cache, must-revalidate, private",
-    ));
-
-    let resource = store.get_resource_extended(&subject, false, for_agent.as_deref())?;
+    let mut resource = store.get_resource_extended(&subject, false, for_agent.as_deref())?;
     timer.add("get_resource");
 
-    let response_body = match content_type {
-        ContentType::Json => resource.to_json(store)?,
-        ContentType::JsonLd => resource.to_json_ld(store)?,
-        ContentType::JsonAd => resource.to_json_ad()?,
-        ContentType::Html => resource.to_json_ad()?,
-        ContentType::Turtle | ContentType::NTriples => {
-            let atoms = resource.to_atoms()?;
-            atomic_lib::serialize::atoms_to_ntriples(atoms, store)?
+    if content_type == ContentType::Csv {
+        if resource.get_main_class().ok().as_deref() != Some(urls::COLLECTION) {
+            return Err(AtomicServerError {
+                message: "CSV is only available for Collections".into(),
+                error_type: AppErrorType::NotAcceptable,
+            });
         }
+        if req
+            .query_string()
+            .split('&')
+            .any(|param| param == "all=true")
+        {
+            return stream_collection_csv(&appstate, &resource, for_agent);
+        }
+    }
+
+    let languages = get_accept_language(headers);
+    let last_commit = get_static_last_commit(&resource);
+    let last_modified = match &last_commit {
+        Some(commit) => store
+            .get_resource(commit)
+            .ok()
+            .and_then(|c| c.get(urls::CREATED_AT).ok().and_then(|t| t.to_int().ok())),
+        None => None,
     };
+    // Resources that haven't changed since their last Commit can be checked before serializing them
+    let static_etag = last_commit
+        .as_ref()
+        .map(|commit| create_etag(&[commit, content_type.to_mime(), &languages.join(",")]));
+    if let Some(etag) = &static_etag {
+        if etag_matches(headers, etag) {
+            return Ok(not_modified(&content_type, etag, last_modified));
+        }
+    }
+
+    let response_body = serialize_resource(&mut resource, &content_type, &languages, store)?;
     timer.add("serialize");
+
+    let etag = match static_etag {
+        Some(etag) => etag,
+        // Dynamic resources depend on the Agent and the current state of the store, so we hash what we send
+        None => {
+            let etag = create_etag(&[&response_body, content_type.to_mime()]);
+            if etag_matches(headers, &etag) {
+                return Ok(not_modified(&content_type, &etag, last_modified));
+            }
+            etag
+        }
+    };
+
+    let mut builder = HttpResponse::Ok();
+    builder.append_header(("Content-Type", content_type.to_mime()));
+    set_cache_headers(&mut builder, &content_type, &etag, last_modified);
     builder.append_header(("Server-Timing", timer.to_header()));
     Ok(builder.body(response_body))
 }
+
+/// Streams every page of the Collection as CSV, so large Collections don't have to fit in memory.
+/// Uses the columns of the first page for all pages.
+fn stream_collection_csv(
+    appstate: &web::Data<AppState>,
+    collection: &Resource,
+    for_agent: Option<String>,
+) -> AtomicServerResult<HttpResponse> {
+    let store = appstate.store.clone();
+    let columns = atomic_lib::csv::collection_columns(collection, &store)?;
+    let header = atomic_lib::csv::header_row(&columns, &store);
+    let total_pages = collection.get(urls::COLLECTION_TOTAL_PAGES)?.to_int()?;
+    let subject = collection.get_subject().clone();
+    let rows = futures::stream::iter(0..total_pages as usize).map(
+        move |page| -> AtomicServerResult<web::Bytes> {
+            let page_subject = atomic_lib::csv::page_subject(&subject, page)?;
+            let page = store.get_resource_extended(&page_subject, false, for_agent.as_deref())?;
+            let rows = atomic_lib::csv::collection_rows(&page, &columns, &store)?;
+            Ok(web::Bytes::from(rows))
+        },
+    );
+    let body = futures::stream::once(async move { Ok(web::Bytes::from(header)) }).chain(rows);
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::Csv.to_mime())
+        .append_header(("Cache-Control", "no-cache, private"))
+        .streaming(body))
+}
+
+/// Responds with `304 Not Modified`, so the client uses the version it already has.
+fn not_modified(
+    content_type: &ContentType,
+    etag: &str,
+    last_modified: Option<i64>,
+) -> HttpResponse {
+    let mut builder = HttpResponse::NotModified();
+    set_cache_headers(&mut builder, content_type, etag, last_modified);
+    builder.finish()
+}
+
+fn set_cache_headers(
+    builder: &mut HttpResponseBuilder,
+    content_type: &ContentType,
+    etag: &str,
+    last_modified: Option<i64>,
+) {
+    if content_type == &ContentType::Html {
+        // This prevents the browser from displaying the JSON response upon re-opening a closed tab
+        // https://github.com/joepio/atomic-data-rust/issues/137
+        builder.append_header((
+            "Cache-Control",
+            "no-store, no-cache, must-revalidate, private",
+        ));
+    } else {
+        // Clients may keep the response, but have to check with the server using the ETag before using it again
+        builder.append_header(("Cache-Control", "no-cache, private"));
+    }
+    // The same subject is served in different formats, and the contents depend on the Agent
+    builder.append_header((
+        "Vary",
+        "Accept, Accept-Language, Authorization, Cookie, x-atomic-agent",
+    ));
+    builder.append_header(("ETag", etag));
+    if let Some(timestamp) = last_modified {
+        let time = std::time::UNIX_EPOCH + std::time::Duration::from_millis(timestamp as u64);
+        builder.append_header(("Last-Modified", HttpDate::from(time).to_string()));
+    }
+}
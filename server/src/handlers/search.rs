@@ -4,12 +4,28 @@
 //! Tantivy requires a strict schema, whereas Atomic is dynamic.
 //! We deal with this discrepency by
 
-use crate::{appstate::AppState, errors::AtomicServerResult};
+use crate::{
+    appstate::AppState,
+    content_types::{get_accept_language, negotiate, remove_format_param, ContentType},
+    errors::AtomicServerResult,
+    helpers::serialize_resource,
+};
 use actix_web::{web, HttpResponse};
 use atomic_lib::{urls, Resource, Storelike};
 use serde::Deserialize;
 use tantivy::{collector::TopDocs, query::QueryParser};
 
+/// The formats that search results can be serialized to. HTML requests are handled by the single page app.
+const SEARCH_CONTENT_TYPES: [ContentType; 7] = [
+    ContentType::JsonAd,
+    ContentType::Json,
+    ContentType::JsonLd,
+    ContentType::JsonLdCompacted,
+    ContentType::Turtle,
+    ContentType::NTriples,
+    ContentType::RdfXml,
+];
+
 #[derive(Deserialize, Debug)]
 pub struct SearchQuery {
     /// The actual search query
@@ -30,6 +46,7 @@ pub async fn search_query(
     req: actix_web::HttpRequest,
 ) -> AtomicServerResult<HttpResponse> {
     let store = &appstate.store;
+    let content_type = negotiate(req.headers(), req.query_string(), &SEARCH_CONTENT_TYPES)?;
     let searcher = appstate.search_state.reader.searcher();
     let fields = crate::search::get_schema_fields(&appstate.search_state)?;
     let default_limit = 30;
@@ -62,21 +79,22 @@ pub async fn search_query(
             }
         }
 
+        // Values in Drives with their own language are analyzed in separate fields
+        let value_fields = crate::search::value_fields(&appstate.search_state, &fields)?;
         let query: Box<dyn tantivy::query::Query> = if should_fuzzy {
-            let term = tantivy::Term::from_field_text(fields.value, &q);
-            let query = tantivy::query::FuzzyTermQuery::new_prefix(term, 1, true);
-            Box::new(query)
+            crate::search::fuzzy_query(&appstate.search_state, &fields, &value_fields, &q)?
         } else {
             // construct the query
-            let query_parser = QueryParser::for_index(
-                &appstate.search_state.index,
-                vec![
-                    fields.subject,
-                    // I don't think we need to search in the property
-                    // fields.property,
-                    fields.value,
-                ],
-            );
+            let mut default_fields = vec![
+                fields.subject,
+                // I don't think we need to search in the property
+                // fields.property,
+            ];
+            default_fields.extend(value_fields);
+            if appstate.search_state.settings.ngram {
+                default_fields.push(fields.value_ngram);
+            }
+            let query_parser = QueryParser::for_index(&appstate.search_state.index, default_fields);
             let full_query = if let Some(prop) = &params.property {
                 format!("{}:{}", prop, &q)
             } else {
@@ -150,10 +168,16 @@ pub async fn search_query(
 
     // Create a valid atomic data resource.
     // You'd think there would be a simpler way of getting the requested URL...
+    let query = remove_format_param(req.query_string());
     let subject = format!(
-        "{}{}",
+        "{}{}{}",
         store.get_self_url().ok_or("No base URL set")?,
-        req.uri().path_and_query().ok_or("Add a query param")?
+        req.path(),
+        if query.is_empty() {
+            "".to_string()
+        } else {
+            format!("?{}", query)
+        }
     );
 
     let mut results_resource = atomic_lib::plugins::search::search_endpoint().to_resource(store)?;
@@ -170,7 +194,13 @@ pub async fn search_query(
         // But we could probably do some things to speed this up: make it async / parallel, check admin rights.
         // https://github.com/joepio/atomic-data-rust/issues/279
         // https://github.com/joepio/atomic-data-rust/issues/280
-        let for_agent = crate::helpers::get_client_agent(req.headers(), &appstate, subject)?;
+        // Clients sign the URL that they request, including the `format` query parameter
+        let signed_subject = format!(
+            "{}{}",
+            store.get_self_url().ok_or("No base URL set")?,
+            req.uri().path_and_query().ok_or("Add a query param")?
+        );
+        let for_agent = crate::helpers::get_client_agent(req.headers(), &appstate, signed_subject)?;
         for s in subjects {
             match store.get_resource_extended(&s, true, for_agent.as_deref()) {
                 Ok(r) => {
@@ -189,8 +219,14 @@ pub async fn search_query(
         results_resource.set_propval(urls::ENDPOINT_RESULTS.into(), resources.into(), store)?;
     }
     let mut builder = HttpResponse::Ok();
-    // TODO: support other serialization options
-    Ok(builder.body(results_resource.to_json_ad()?))
+    builder.append_header(("Content-Type", content_type.to_mime()));
+    let body = serialize_resource(
+        &mut results_resource,
+        &content_type,
+        &get_accept_language(req.headers()),
+        store,
+    )?;
+    Ok(builder.body(body))
 }
 
 /// Posts an N-Triples RDF document to index the triples in search
@@ -214,7 +250,16 @@ pub async fn search_index_rdf(
                 get_inner_value(t.object),
             ) {
                 (Some(s), Some(p), Some(o)) => {
-                    crate::search::add_triple(&writer, s, p, o, &fields).ok();
+                    crate::search::add_triple(
+                        &writer,
+                        s,
+                        p,
+                        o,
+                        &fields,
+                        None,
+                        appstate.search_state.settings.ngram,
+                    )
+                    .ok();
                 }
                 _ => return Ok(()),
             };
@@ -0,0 +1,63 @@
+//! Sessions let browsers authenticate with a cookie, so plain links and `<img>` tags can access private Resources.
+//! The cookie is `HttpOnly` and `SameSite=Lax`, which means scripts can't read it and other sites can't use it in background requests.
+
+use actix_web::{
+    cookie::{time::Duration, Cookie, SameSite},
+    web, HttpResponse,
+};
+use atomic_lib::{sessions::Session, utils::now};
+
+use crate::{
+    appstate::AppState,
+    errors::AtomicServerResult,
+    helpers::{get_auth_headers, get_session_cookie, SESSION_COOKIE},
+};
+
+/// Creates a session for the Agent that signed the request using the `x-atomic-*` headers, and sets it as a cookie.
+/// The headers should be signed for the `/session` URL.
+#[tracing::instrument(skip(appstate, req))]
+pub async fn post_session(
+    appstate: web::Data<AppState>,
+    req: actix_web::HttpRequest,
+) -> AtomicServerResult<HttpResponse> {
+    let store = &appstate.store;
+    let subject = format!("{}{}", appstate.config.server_url, req.head().uri.path());
+    let auth_values = get_auth_headers(req.headers(), subject)?.ok_or(
+        "Creating a session requires the `x-atomic-*` authentication headers, signed for this URL.",
+    )?;
+    let agent =
+        atomic_lib::authentication::get_agent_from_headers_and_check(Some(auth_values), store)
+            .map_err(|e| format!("Authentication failed: {}", e))?;
+
+    let hours = appstate.config.opts.session_hours;
+    let session = Session::new(&agent, now() + hours * 60 * 60 * 1000);
+    let cookie = Cookie::build(SESSION_COOKIE, session.sign(store)?)
+        .path("/")
+        .http_only(true)
+        .secure(appstate.config.server_url.starts_with("https"))
+        .same_site(SameSite::Lax)
+        .max_age(Duration::hours(hours))
+        .finish();
+
+    Ok(HttpResponse::Ok().cookie(cookie).json(serde_json::json!({
+        "agent": session.agent,
+        "expiresAt": session.expires_at,
+    })))
+}
+
+/// Logs out: revokes the session in the cookie, and tells the browser to remove it.
+#[tracing::instrument(skip(appstate, req))]
+pub async fn delete_session(
+    appstate: web::Data<AppState>,
+    req: actix_web::HttpRequest,
+) -> AtomicServerResult<HttpResponse> {
+    if let Some(cookie) = get_session_cookie(req.headers())? {
+        // Sessions that are already invalid don't have to be revoked
+        if let Ok(session) = Session::verify(&cookie, &appstate.store) {
+            appstate.store.revoke_session(&session)?;
+        }
+    }
+    let mut removal = Cookie::build(SESSION_COOKIE, "").path("/").finish();
+    removal.make_removal();
+    Ok(HttpResponse::Ok().cookie(removal).finish())
+}
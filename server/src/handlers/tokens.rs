@@ -0,0 +1,23 @@
+use actix_web::{web, HttpResponse};
+use atomic_lib::{AtomicError, Storelike};
+
+use crate::{appstate::AppState, errors::AtomicServerResult, helpers::get_client_agent};
+
+/// Revokes the capability token in the body. Only the Agent that issued the token can do this.
+/// This is a `POST`, so other sites can't make browsers revoke tokens using their session cookie.
+#[tracing::instrument(skip(appstate, req, body))]
+pub async fn post_revoke_token(
+    body: String,
+    appstate: web::Data<AppState>,
+    req: actix_web::HttpRequest,
+) -> AtomicServerResult<HttpResponse> {
+    let store = &appstate.store;
+    let subject = format!("{}{}", store.get_server_url(), req.head().uri.path());
+    let agent = get_client_agent(req.headers(), &appstate, subject)?.ok_or_else(|| {
+        AtomicError::unauthorized(
+            "No authorization headers present. These are required when revoking tokens.".into(),
+        )
+    })?;
+    let token = atomic_lib::plugins::tokens::revoke_token(body.trim(), &agent, store)?;
+    Ok(HttpResponse::Ok().body(format!("Token {} has been revoked.", token.id)))
+}
@@ -1,10 +1,18 @@
-use crate::{appstate::AppState, content_types::get_accept};
+use crate::{appstate::AppState, content_types::negotiate};
 use crate::{content_types::ContentType, errors::AtomicServerResult, helpers::empty_to_nothing};
 use actix_web::{web, HttpResponse};
 use atomic_lib::{Storelike, Value};
 use serde::Deserialize;
 use std::collections::HashSet;
 
+/// The formats that TPF results can be serialized to.
+const TPF_CONTENT_TYPES: [ContentType; 4] = [
+    ContentType::JsonAd,
+    ContentType::Turtle,
+    ContentType::NTriples,
+    ContentType::RdfXml,
+];
+
 #[derive(Deserialize, Debug)]
 pub struct TpfQuery {
     pub subject: Option<String>,
@@ -27,7 +35,7 @@ pub async fn tpf(
     }
     // This is how locally items are stored (which don't know their full subject URL) in Atomic Data
     let mut builder = HttpResponse::Ok();
-    let content_type = get_accept(req.headers());
+    let content_type = negotiate(req.headers(), req.query_string(), &TPF_CONTENT_TYPES)?;
     let subject = empty_to_nothing(query.subject.clone());
     let property = empty_to_nothing(query.property.clone());
     let value = query.value.clone().map(Value::String);
@@ -52,12 +60,14 @@ pub async fn tpf(
             }
             Ok(builder.body(atomic_lib::serialize::resources_to_json_ad(&resources)?))
         }
-        ContentType::Json | ContentType::Html | ContentType::JsonLd => {
-            // TODO
-            tracing::error!("This Content-Type is not implemented");
-            Ok(builder.body("This Content-Type is not implemented"))
+        ContentType::Turtle => {
+            Ok(builder.body(atomic_lib::serialize::atoms_to_turtle(atoms, store)?))
+        }
+        ContentType::RdfXml => {
+            Ok(builder.body(atomic_lib::serialize::atoms_to_rdf_xml(atoms, store)?))
         }
-        ContentType::Turtle | ContentType::NTriples => {
+        // Other types are not in TPF_CONTENT_TYPES.
+        _ => {
             let bod_string = atomic_lib::serialize::atoms_to_ntriples(atoms, store)?;
             Ok(builder.body(bod_string))
         }
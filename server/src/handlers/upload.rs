@@ -3,13 +3,22 @@ use std::{ffi::OsStr, io::Write, path::Path};
 use actix_multipart::Multipart;
 use actix_web::{web, HttpResponse};
 use atomic_lib::{
-    commit::CommitResponse, hierarchy::check_write, urls, utils::now, AtomicError, Resource,
-    Storelike, Value,
+    audit::{AuditAction, AuditEntry},
+    commit::CommitResponse,
+    hierarchy::check_write,
+    plugins::files::FileHasher,
+    urls,
+    utils::now,
+    AtomicError, Resource, Storelike, Value,
 };
 use futures::{StreamExt, TryStreamExt};
 use serde::Deserialize;
 
-use crate::{appstate::AppState, errors::AtomicServerResult, helpers::get_client_agent};
+use crate::{
+    appstate::AppState,
+    errors::{AppErrorType, AtomicServerError, AtomicServerResult},
+    helpers::get_client_agent,
+};
 
 #[derive(Deserialize, Debug)]
 pub struct UploadQuery {
@@ -20,8 +29,9 @@ pub struct UploadQuery {
 /// A parent Query parameter is required for checking rights and for placing the file in a Hierarchy.
 /// Creates new File resources for every submitted file.
 /// Submission is done using multipart/form-data.
-/// The file is stored in the `/uploads` directory.
+/// The file is stored in the `/uploads` directory, named after its SHA-256 hash (see [crate::blobs]).
 /// An `attachment` relationship is created from the parent
+/// Files that are larger than `max_file_size`, or that don't fit in the quota of the Drive, are rejected before they are written to disk.
 #[tracing::instrument(skip(appstate, req, body))]
 pub async fn upload_handler(
     mut body: Multipart,
@@ -39,14 +49,27 @@ pub async fn upload_handler(
             .path_and_query()
             .ok_or("Path must be given")?
     );
-    if let Some(agent) = get_client_agent(req.headers(), &appstate, subject)? {
+    let agent = if let Some(agent) = get_client_agent(req.headers(), &appstate, subject)? {
         check_write(store, &parent, &agent)?;
+        agent
     } else {
         return Err(AtomicError::unauthorized(
             "No authorization headers present. These are required when uploading files.".into(),
         )
         .into());
-    }
+    };
+
+    let max_file_size = megabytes(appstate.config.opts.max_file_size);
+    // The amount of bytes that can still be stored in the Drive of the parent
+    let mut remaining_quota = match megabytes(appstate.config.opts.drive_quota) {
+        Some(quota) => {
+            let drive = atomic_lib::plugins::files::find_drive(&query.parent, store)?
+                .ok_or("The parent is not part of a Drive, so its quota can't be checked")?;
+            let usage = store.get_drive_usage(&drive)?;
+            Some(quota.saturating_sub(usage.try_into().unwrap_or_default()))
+        }
+        None => None,
+    };
 
     let mut created_resources: Vec<Resource> = Vec::new();
     let mut commit_responses: Vec<CommitResponse> = Vec::new();
@@ -65,22 +88,35 @@ pub async fn upload_handler(
                 .replace(' ', "-")
         );
 
-        let mut file_path = appstate.config.uploads_path.clone();
-        file_path.push(&file_id);
-        let mut file = std::fs::File::create(file_path)?;
+        let uploads_path = &appstate.config.uploads_path;
+        let file_path = crate::blobs::temp_path(uploads_path);
+        let mut file = std::fs::File::create(&file_path)?;
+        let mut hasher = FileHasher::new();
 
         // Field in turn is stream of *Bytes* object
+        let mut written: u64 = 0;
         while let Some(chunk) = field.next().await {
             let data = chunk.map_err(|e| format!("Error while reading multipart data. {}", e))?;
-            // TODO: Update a SHA256 hash here for checksum
+            written += data.len() as u64;
+            // Check the limits before the chunk is written, and remove what has been written so far
+            if let Err(e) = check_size(written, max_file_size, remaining_quota, filename) {
+                drop(file);
+                std::fs::remove_file(&file_path)?;
+                return Err(e);
+            }
+            hasher.update(&data);
             file.write_all(&data)?;
         }
+        drop(file);
+        let hash = hasher.finish();
+        if !crate::blobs::store_blob(uploads_path, &file_path, &hash.blob_id)? {
+            tracing::info!("{} is identical to a file that is already stored", filename);
+        }
+        if let Some(remaining) = remaining_quota.as_mut() {
+            *remaining -= written;
+        }
 
-        let byte_count: i64 = file
-            .metadata()?
-            .len()
-            .try_into()
-            .map_err(|_e| "Too large")?;
+        let byte_count: i64 = written.try_into().map_err(|_e| "Too large")?;
 
         let subject_path = format!("files/{}", urlencoding::encode(&file_id));
         let new_subject = format!("{}/{}", store.get_server_url(), subject_path);
@@ -89,7 +125,8 @@ pub async fn upload_handler(
         let mut resource = atomic_lib::Resource::new_instance(urls::FILE, store)?;
         resource.set_subject(new_subject);
         resource.set_propval_string(urls::PARENT.into(), &query.parent, store)?;
-        resource.set_propval_string(urls::INTERNAL_ID.into(), &file_id, store)?;
+        resource.set_propval_string(urls::INTERNAL_ID.into(), &hash.blob_id, store)?;
+        resource.set_propval_string(urls::CHECKSUM.into(), &hash.checksum, store)?;
         resource.set_propval(urls::FILESIZE.into(), Value::Integer(byte_count), store)?;
         resource.set_propval_string(
             urls::MIMETYPE.into(),
@@ -99,6 +136,13 @@ pub async fn upload_handler(
         resource.set_propval_string(urls::FILENAME.into(), filename, store)?;
         resource.set_propval_string(urls::DOWNLOAD_URL.into(), &download_url, store)?;
         commit_responses.push(resource.save(store)?);
+        store.add_audit_entry(&AuditEntry::new(
+            AuditAction::Upload,
+            &agent,
+            resource.get_subject(),
+            true,
+            Some(format!("Uploaded {} ({} bytes)", filename, byte_count)),
+        ));
         created_resources.push(resource);
     }
 
@@ -122,6 +166,39 @@ pub async fn upload_handler(
     )?))
 }
 
+/// Converts a limit from the options to bytes. `0` means no limit.
+pub fn megabytes(limit: u64) -> Option<u64> {
+    if limit == 0 {
+        None
+    } else {
+        Some(limit * 1024 * 1024)
+    }
+}
+
+/// Throws if the file is larger than the maximum file size, or than the remaining quota of the Drive.
+fn check_size(
+    size: u64,
+    max_file_size: Option<u64>,
+    remaining_quota: Option<u64>,
+    filename: &str,
+) -> AtomicServerResult<()> {
+    let message = match (max_file_size, remaining_quota) {
+        (Some(max), _) if size > max => format!(
+            "File {} is larger than the maximum file size of {} bytes",
+            filename, max
+        ),
+        (_, Some(remaining)) if size > remaining => format!(
+            "File {} does not fit in the storage quota of the Drive, which has {} bytes left",
+            filename, remaining
+        ),
+        _ => return Ok(()),
+    };
+    Err(AtomicServerError {
+        message,
+        error_type: AppErrorType::TooLarge,
+    })
+}
+
 fn guess_mime_for_filename(filename: &str) -> String {
     if let Some(ext) = get_extension_from_filename(filename) {
         actix_files::file_extension_to_mime(ext).to_string()
@@ -1,7 +1,12 @@
 //! Functions useful in the server
 
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
 use actix_web::http::header::HeaderMap;
-use atomic_lib::authentication::AuthValues;
+use atomic_lib::{authentication::AuthValues, urls, Resource, Storelike, Value};
 
 use crate::{appstate::AppState, content_types::ContentType, errors::AtomicServerResult};
 
@@ -56,7 +61,43 @@ pub fn get_auth_headers(
     }
 }
 
+/// Returns the capability token from an `Authorization: Bearer atomic-token...` header, if there is one.
+pub fn get_bearer_token(map: &HeaderMap) -> AtomicServerResult<Option<String>> {
+    let header = match map.get("authorization") {
+        Some(header) => header
+            .to_str()
+            .map_err(|_e| "Only string headers allowed")?,
+        None => return Ok(None),
+    };
+    match header.strip_prefix("Bearer ") {
+        Some(token) if atomic_lib::tokens::is_token(token) => Ok(Some(token.trim().to_string())),
+        _ => Ok(None),
+    }
+}
+
+/// Name of the cookie that contains the signed [atomic_lib::sessions::Session].
+pub const SESSION_COOKIE: &str = "atomic_session";
+
+/// Returns the value of the session cookie, if there is one.
+pub fn get_session_cookie(map: &HeaderMap) -> AtomicServerResult<Option<String>> {
+    for header in map.get_all("cookie") {
+        let header = header
+            .to_str()
+            .map_err(|_e| "Only string headers allowed")?;
+        for pair in header.split(';') {
+            if let Some((name, value)) = pair.trim().split_once('=') {
+                if name == SESSION_COOKIE {
+                    return Ok(Some(value.to_string()));
+                }
+            }
+        }
+    }
+    Ok(None)
+}
+
 /// Checks for authentication headers and returns Some agent's subject if everything is well.
+/// If the request uses a capability token, the token itself is returned, which is checked by [atomic_lib::hierarchy::check_rights].
+/// Requests without headers can use a session cookie, see [crate::handlers::session].
 /// Skips these checks in public_mode and returns Ok(None).
 #[tracing::instrument(skip(appstate))]
 pub fn get_client_agent(
@@ -67,8 +108,22 @@ pub fn get_client_agent(
     if appstate.config.opts.public_mode {
         return Ok(None);
     }
+    if let Some(token) = get_bearer_token(headers)? {
+        atomic_lib::tokens::CapabilityToken::verify(&token, &appstate.store)
+            .map_err(|e| format!("Authentication failed: {}", e))?;
+        return Ok(Some(token));
+    }
     // Authentication check. If the user has no headers, continue with the Public Agent.
     let auth_header_values = get_auth_headers(headers, requested_subject)?;
+    if auth_header_values.is_none() {
+        if let Some(session) = get_session_cookie(headers)? {
+            // An expired or revoked session should not break public pages, so we continue with the Public Agent
+            match atomic_lib::sessions::Session::verify(&session, &appstate.store) {
+                Ok(session) => return Ok(Some(session.agent)),
+                Err(e) => tracing::info!("Ignoring session cookie: {}", e),
+            }
+        }
+    }
     let for_agent = atomic_lib::authentication::get_agent_from_headers_and_check(
         auth_header_values,
         &appstate.store,
@@ -77,6 +132,93 @@ pub fn get_client_agent(
     Ok(Some(for_agent))
 }
 
+/// Serializes a Resource in the negotiated content type.
+/// HTML requests are normally handled by the single page app, so these get JSON-AD.
+pub fn serialize_resource(
+    resource: &mut Resource,
+    content_type: &ContentType,
+    languages: &[String],
+    store: &impl Storelike,
+) -> AtomicServerResult<String> {
+    let body = match content_type {
+        // Plain JSON can't contain multiple languages, so we pick the best translation
+        ContentType::Json => {
+            resource.localize(languages);
+            resource.to_json(store)?
+        }
+        ContentType::JsonLd => resource.to_json_ld(store)?,
+        ContentType::JsonLdCompacted => {
+            let context = atomic_lib::serialize::json_ld_context(store)?;
+            resource.to_json_ld_compacted(&json_ld_context_url(store), &context)?
+        }
+        ContentType::JsonAd => resource.to_json_ad()?,
+        ContentType::Html => resource.to_json_ad()?,
+        ContentType::Turtle => atomic_lib::serialize::atoms_to_turtle(resource.to_atoms()?, store)?,
+        ContentType::NTriples => {
+            atomic_lib::serialize::atoms_to_ntriples(resource.to_atoms()?, store)?
+        }
+        ContentType::RdfXml => {
+            atomic_lib::serialize::atoms_to_rdf_xml(resource.to_atoms()?, store)?
+        }
+        ContentType::Csv => atomic_lib::csv::collection_to_csv(resource, store)?,
+    };
+    Ok(body)
+}
+
+/// URL of the shared JSON-LD @context document, which is used by compacted JSON-LD.
+pub fn json_ld_context_url(store: &impl Storelike) -> String {
+    format!("{}/context", store.get_server_url())
+}
+
+/// Classes of Resources that are (partly) calculated when they are requested, e.g. the members of a Collection.
+/// Their last Commit does not describe their contents.
+const DYNAMIC_CLASSES: [&str; 5] = [
+    urls::COLLECTION,
+    urls::DRIVE,
+    urls::INVITE,
+    urls::CHATROOM,
+    urls::ENDPOINT,
+];
+
+/// Returns the last Commit of a Resource if it fully describes its current state, which means it can be used as the ETag.
+/// Returns None for dynamic Resources, and for Resources with query parameters.
+pub fn get_static_last_commit(resource: &Resource) -> Option<String> {
+    if resource.get_subject().contains('?') {
+        return None;
+    }
+    if let Ok(Value::ResourceArray(classes)) = resource.get(urls::IS_A) {
+        let is_dynamic = classes
+            .iter()
+            .any(|class| DYNAMIC_CLASSES.contains(&class.to_string().as_str()));
+        if is_dynamic {
+            return None;
+        }
+    }
+    resource
+        .get(urls::LAST_COMMIT)
+        .ok()
+        .map(|commit| commit.to_string())
+}
+
+/// Creates a strong ETag (including the quotes) from the parts that determine the response body.
+// The hash is stable between restarts, but it might change between Rust versions. That only causes a cache miss.
+pub fn create_etag(parts: &[&str]) -> String {
+    let mut hasher = DefaultHasher::new();
+    for part in parts {
+        part.hash(&mut hasher);
+    }
+    format!("\"{:x}\"", hasher.finish())
+}
+
+/// Whether the `If-None-Match` header contains the ETag, which means the client already has the current version.
+pub fn etag_matches(map: &HeaderMap, etag: &str) -> bool {
+    map.get_all("if-none-match")
+        .filter_map(|header| header.to_str().ok())
+        .flat_map(|header| header.split(','))
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
 /// Finds the extension
 pub fn try_extension(path: &str) -> Option<(ContentType, &str)> {
     let items: Vec<&str> = path.split('.').collect();
@@ -88,6 +230,8 @@ pub fn try_extension(path: &str) -> Option<(ContentType, &str)> {
             "jsonad" => ContentType::JsonAd,
             "html" => ContentType::Html,
             "ttl" => ContentType::Turtle,
+            "rdf" => ContentType::RdfXml,
+            "csv" => ContentType::Csv,
             _ => return None,
         };
         return Some((content_type, path));
@@ -5,6 +5,7 @@ See https://github.com/joepio/atomic-data-rust/tree/master/src-tauri
 */
 mod actor_messages;
 mod appstate;
+mod blobs;
 mod commit_monitor;
 pub mod config;
 mod content_types;
@@ -16,11 +17,13 @@ mod https;
 mod jsonerrors;
 #[cfg(feature = "process-management")]
 mod process;
+mod rate_limit;
 mod routes;
 pub mod serve;
 // #[cfg(feature = "search")]
 mod search;
 #[cfg(test)]
 mod tests;
+mod text_extraction;
 mod timer;
 mod trace;
@@ -0,0 +1,467 @@
+//! Token-bucket rate limiting, per Agent and per IP address.
+//! Every request takes a token from the general budget of the client.
+//! Expensive requests (searching, uploading and writing) also take a token from their own, smaller budget.
+//! Authenticated Agents are identified by their subject, and get larger budgets. Anonymous clients are identified by their IP address.
+//! Only verified credentials count as authenticated. Clients that claim to be an Agent without valid credentials are identified by the Agent and their IP address, and get the budgets of anonymous clients.
+//! This way, nobody can use up the budgets of another Agent.
+//! To prevent clients from getting fresh budgets by creating many Agents, requests of Agents are also limited per IP address, using the budget of Agents.
+//! When a budget is empty, the server responds with `429 Too Many Requests` and a `Retry-After` header.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use actix_web::{
+    body::EitherBody,
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::{header, Method},
+    web, HttpResponse,
+};
+use futures::future::{ready, LocalBoxFuture, Ready};
+
+use atomic_lib::Storelike;
+
+use crate::{
+    appstate::AppState,
+    config::Opts,
+    helpers::{get_bearer_token, get_client_agent, get_session_cookie},
+};
+
+/// The buckets of a client. Expensive requests use both [Budget::General] and their own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Budget {
+    General,
+    /// The `/search` endpoint
+    Search,
+    /// Uploading files and importing CSV
+    Upload,
+    /// Posting Commits and accepting Invites
+    Write,
+}
+
+/// Maximum amount of requests per minute for every [Budget].
+#[derive(Clone, Debug)]
+pub struct Limits {
+    /// General budget for anonymous clients. `0` disables rate limiting.
+    pub anonymous: u32,
+    /// General budget for authenticated Agents.
+    pub agent: u32,
+    pub search: u32,
+    pub upload: u32,
+    pub write: u32,
+}
+
+impl Limits {
+    pub fn from_opts(opts: &Opts) -> Limits {
+        Limits {
+            anonymous: opts.rate_limit,
+            agent: opts.rate_limit_agent,
+            search: opts.rate_limit_search,
+            upload: opts.rate_limit_upload,
+            write: opts.rate_limit_write,
+        }
+    }
+
+    /// Returns the amount of requests per minute. `0` means unlimited.
+    /// Authenticated Agents get `agent / anonymous` times the budgets of expensive requests.
+    fn per_minute(&self, budget: Budget, authenticated: bool) -> f64 {
+        if self.anonymous == 0 {
+            return 0.0;
+        }
+        let anonymous = match budget {
+            Budget::General => self.anonymous,
+            Budget::Search => self.search,
+            Budget::Upload => self.upload,
+            Budget::Write => self.write,
+        } as f64;
+        match (authenticated, budget) {
+            (false, _) => anonymous,
+            (true, Budget::General) => self.agent as f64,
+            (true, _) => anonymous * self.agent as f64 / self.anonymous as f64,
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    capacity: f64,
+    /// Tokens per second
+    refill: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill).min(self.capacity);
+        self.updated = now;
+    }
+}
+
+/// How often the buckets that are full again are removed, since these are the same as new buckets.
+const EVICTION_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Default)]
+struct Buckets {
+    buckets: HashMap<(Budget, String), Bucket>,
+    evicted: Option<Instant>,
+}
+
+impl Buckets {
+    /// Removes the full buckets, at most once every [EVICTION_INTERVAL].
+    fn evict(&mut self, now: Instant) {
+        match self.evicted {
+            Some(evicted) if now.duration_since(evicted) < EVICTION_INTERVAL => {}
+            _ => {
+                self.buckets.retain(|_, bucket| {
+                    bucket.refill(now);
+                    bucket.tokens < bucket.capacity
+                });
+                self.evicted = Some(now);
+            }
+        }
+    }
+}
+
+/// Keeps the buckets of all clients. Cheap to clone, the buckets are shared.
+#[derive(Clone)]
+pub struct RateLimiter {
+    limits: Limits,
+    buckets: Arc<Mutex<Buckets>>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: Limits) -> RateLimiter {
+        RateLimiter {
+            limits,
+            buckets: Arc::new(Mutex::new(Buckets::default())),
+        }
+    }
+
+    /// Takes a token from the bucket of the client.
+    /// If the bucket is empty, returns the amount of seconds until a token is available.
+    pub fn check(&self, budget: Budget, client: &str, authenticated: bool) -> Result<(), u64> {
+        let per_minute = self.limits.per_minute(budget, authenticated);
+        if per_minute <= 0.0 {
+            return Ok(());
+        }
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        buckets.evict(now);
+        let bucket = buckets
+            .buckets
+            .entry((budget, client.to_string()))
+            .or_insert(Bucket {
+                tokens: per_minute,
+                capacity: per_minute,
+                refill: per_minute / 60.0,
+                updated: now,
+            });
+        bucket.refill(now);
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(((1.0 - bucket.tokens) / bucket.refill).ceil().max(1.0) as u64)
+        }
+    }
+}
+
+/// Returns the expensive [Budget] that the request uses, if any.
+pub fn budget_for_request(
+    method: &Method,
+    path: &str,
+    query: &str,
+    store: &impl Storelike,
+) -> Option<Budget> {
+    match (method, path) {
+        (_, "/search") => Some(Budget::Search),
+        (&Method::POST, "/upload" | "/import") => Some(Budget::Upload),
+        (&Method::POST, "/commit") => Some(Budget::Write),
+        // Accepting an Invite can create Agents and changes rights
+        _ if accepts_invite(path, query, store) => Some(Budget::Write),
+        _ => None,
+    }
+}
+
+/// Whether the request accepts an Invite, which is a GET of the Invite with the Agent or public key in the query.
+/// Only loads the Resource if the query has these parameters.
+fn accepts_invite(path: &str, query: &str, store: &impl Storelike) -> bool {
+    let has_agent = query.split('&').any(|pair| {
+        let key = pair.split('=').next().unwrap_or_default();
+        let key = urlencoding::decode(key).unwrap_or_default();
+        matches!(
+            key.as_ref(),
+            "public-key" | "agent" | atomic_lib::urls::INVITE_PUBKEY | atomic_lib::urls::AGENT
+        )
+    });
+    if !has_agent {
+        return false;
+    }
+    let subject = format!("{}{}", store.get_server_url(), path);
+    store
+        .get_resource_local(&subject)
+        .and_then(|resource| resource.get(atomic_lib::urls::IS_A)?.to_subjects(None))
+        .map(|classes| classes.iter().any(|c| c == atomic_lib::urls::INVITE))
+        .unwrap_or(false)
+}
+
+/// Who sent a request, according to its credentials.
+#[derive(Debug, PartialEq)]
+struct Client {
+    /// The subject of a verified Agent, the Agent and IP address of unverified Agents, or the IP address of anonymous clients.
+    key: String,
+    authenticated: bool,
+    ip: String,
+}
+
+impl Client {
+    /// `claimed` is the Agent that the credentials name, `verified` the Agent that they were verified for.
+    fn new(claimed: Option<String>, verified: Option<String>, ip: String) -> Client {
+        match claimed {
+            Some(agent) if agent == atomic_lib::urls::PUBLIC_AGENT => Client::new(None, None, ip),
+            Some(agent) if verified.as_deref() == Some(agent.as_str()) => Client {
+                key: agent,
+                authenticated: true,
+                ip,
+            },
+            Some(agent) => Client {
+                key: format!("{}@{}", agent, ip),
+                authenticated: false,
+                ip,
+            },
+            None => Client {
+                key: ip.clone(),
+                authenticated: false,
+                ip,
+            },
+        }
+    }
+}
+
+/// Identifies the client by the Agent in its headers, capability token or session cookie.
+/// Clients without credentials are identified by their IP address.
+fn identify_client(req: &ServiceRequest, appstate: &AppState) -> Client {
+    let headers = req.headers();
+    let claimed = if let Some(agent) = headers.get("x-atomic-agent") {
+        agent.to_str().ok().map(String::from)
+    } else if let Ok(Some(token)) = get_bearer_token(headers) {
+        atomic_lib::tokens::token_issuer(&token)
+    } else if let Ok(Some(session)) = get_session_cookie(headers) {
+        atomic_lib::sessions::session_agent(&session)
+    } else {
+        None
+    };
+    let ip = if appstate.config.opts.rate_limit_forwarded {
+        req.connection_info().realip_remote_addr().map(String::from)
+    } else {
+        req.peer_addr().map(|addr| addr.ip().to_string())
+    }
+    .unwrap_or_else(|| "unknown".into());
+    let verified = match claimed {
+        Some(_) => verified_agent(req, appstate),
+        None => None,
+    };
+    Client::new(claimed, verified, ip)
+}
+
+/// Verifies the credentials of the request, and returns the Agent they belong to.
+/// Signatures are checked against the URL of the request. Clients that sign another subject are not verified here, so they get the budgets of anonymous clients.
+fn verified_agent(req: &ServiceRequest, appstate: &AppState) -> Option<String> {
+    let signed_subject = format!("{}{}", appstate.config.server_url, req.uri());
+    let agent = get_client_agent(req.headers(), appstate, signed_subject).ok()??;
+    if atomic_lib::tokens::is_token(&agent) {
+        atomic_lib::tokens::token_issuer(&agent)
+    } else {
+        Some(agent)
+    }
+}
+
+/// Middleware that applies the [RateLimiter] of the [AppState].
+pub struct RateLimit;
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = RateLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddleware { service }))
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        if let Some(appstate) = req.app_data::<web::Data<AppState>>() {
+            let client = identify_client(&req, appstate);
+            let budgets = [
+                Some(Budget::General),
+                budget_for_request(
+                    req.method(),
+                    req.path(),
+                    req.query_string(),
+                    &appstate.store,
+                ),
+            ];
+            for budget in budgets.into_iter().flatten() {
+                let limiter = &appstate.rate_limiter;
+                let mut checked = limiter.check(budget, &client.key, client.authenticated);
+                if client.authenticated && checked.is_ok() {
+                    // Agents are cheap to create, so the IP address is limited too
+                    checked = limiter.check(budget, &format!("agents@{}", client.ip), true);
+                }
+                if let Err(retry_after) = checked {
+                    tracing::info!("Rate limit of {:?} reached for {}", budget, client.key);
+                    let response = HttpResponse::TooManyRequests()
+                        .insert_header((header::RETRY_AFTER, retry_after.to_string()))
+                        .body(format!(
+                            "Too many requests. Try again in {} seconds.",
+                            retry_after
+                        ));
+                    return Box::pin(ready(Ok(req.into_response(response).map_into_right_body())));
+                }
+            }
+        }
+        let fut = self.service.call(req);
+        Box::pin(async move { Ok(fut.await?.map_into_left_body()) })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn limits() -> Limits {
+        Limits {
+            anonymous: 2,
+            agent: 4,
+            search: 1,
+            upload: 0,
+            write: 1,
+        }
+    }
+
+    #[test]
+    fn empty_bucket_is_limited() {
+        let limiter = RateLimiter::new(limits());
+        limiter.check(Budget::General, "1.2.3.4", false).unwrap();
+        limiter.check(Budget::General, "1.2.3.4", false).unwrap();
+        let retry_after = limiter
+            .check(Budget::General, "1.2.3.4", false)
+            .unwrap_err();
+        // Two requests per minute means a new token every 30 seconds
+        assert_eq!(retry_after, 30);
+        // Other clients have their own buckets
+        limiter.check(Budget::General, "5.6.7.8", false).unwrap();
+    }
+
+    #[test]
+    fn budgets() {
+        let limiter = RateLimiter::new(limits());
+        limiter.check(Budget::Search, "agent", true).unwrap();
+        // Authenticated Agents get twice the search budget, since `agent` is twice `anonymous`
+        limiter.check(Budget::Search, "agent", true).unwrap();
+        limiter.check(Budget::Search, "agent", true).unwrap_err();
+        // Zero means unlimited
+        for _ in 0..10 {
+            limiter.check(Budget::Upload, "agent", true).unwrap();
+        }
+        let disabled = RateLimiter::new(Limits {
+            anonymous: 0,
+            ..limits()
+        });
+        for _ in 0..10 {
+            disabled.check(Budget::General, "1.2.3.4", false).unwrap();
+        }
+    }
+
+    #[test]
+    fn expensive_requests() {
+        let store = atomic_lib::Store::init().unwrap();
+        let mut invite = atomic_lib::Resource::new(format!("{}/setup", store.get_server_url()));
+        invite.set_propval_unsafe(
+            atomic_lib::urls::IS_A.into(),
+            atomic_lib::Value::ResourceArray(vec![atomic_lib::urls::INVITE.into()]),
+        );
+        store.add_resource_opts(&invite, false, true, true).unwrap();
+        let budget = |method: &Method, path: &str, query: &str| {
+            budget_for_request(method, path, query, &store)
+        };
+
+        assert_eq!(
+            budget(&Method::GET, "/search", "q=test"),
+            Some(Budget::Search)
+        );
+        assert_eq!(budget(&Method::POST, "/commit", ""), Some(Budget::Write));
+        assert_eq!(
+            budget(&Method::GET, "/setup", "public-key=abc"),
+            Some(Budget::Write)
+        );
+        assert_eq!(budget(&Method::GET, "/setup", ""), None);
+        // Other endpoints can use the same parameters
+        assert_eq!(
+            budget(&Method::GET, "/permissions", "subject=x&agent=y"),
+            None
+        );
+        assert_eq!(budget(&Method::GET, "/commit", ""), None);
+    }
+
+    #[test]
+    fn unverified_agents() {
+        let agent = "https://example.com/agents/a".to_string();
+        let ip = "1.2.3.4".to_string();
+        let verified = Client::new(Some(agent.clone()), Some(agent.clone()), ip.clone());
+        assert_eq!(verified.key, agent);
+        assert!(verified.authenticated);
+        // Claiming to be an Agent without valid credentials should not use the budget of that Agent
+        let claimed = Client::new(Some(agent.clone()), None, ip.clone());
+        assert_eq!(claimed.key, format!("{}@{}", agent, ip));
+        assert!(!claimed.authenticated);
+        let other = Client::new(
+            Some(agent),
+            Some("https://example.com/b".into()),
+            ip.clone(),
+        );
+        assert!(!other.authenticated);
+        let public = Client::new(
+            Some(atomic_lib::urls::PUBLIC_AGENT.into()),
+            None,
+            ip.clone(),
+        );
+        assert_eq!(public.key, ip);
+    }
+
+    #[test]
+    fn full_buckets_are_evicted() {
+        let limiter = RateLimiter::new(limits());
+        limiter.check(Budget::General, "1.2.3.4", false).unwrap();
+        let later = Instant::now() + EVICTION_INTERVAL * 2;
+        let mut buckets = limiter.buckets.lock().unwrap();
+        buckets.evict(later);
+        assert!(buckets.buckets.is_empty());
+    }
+}
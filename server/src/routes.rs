@@ -27,8 +27,9 @@ pub fn config_routes(app: &mut actix_web::web::ServiceConfig, config: &Config) {
             web::resource(ANY)
                 .guard(actix_web::guard::Method(Method::GET))
                 .guard(actix_web::guard::fn_guard(|guard_ctx| {
-                    content_types::get_accept(guard_ctx.head().headers())
-                        == content_types::ContentType::Html
+                    let head = guard_ctx.head();
+                    content_types::get_accept(head.headers(), head.uri.query().unwrap_or("")).ok()
+                        == Some(content_types::ContentType::Html)
                 }))
                 .to(handlers::single_page_app::single_page),
         )
@@ -37,16 +38,38 @@ pub fn config_routes(app: &mut actix_web::web::ServiceConfig, config: &Config) {
                 .guard(actix_web::guard::Method(Method::POST))
                 .to(handlers::upload::upload_handler),
         )
+        .service(
+            web::resource("/import")
+                .guard(actix_web::guard::Method(Method::POST))
+                .to(handlers::import::import_csv),
+        )
         .service(web::resource("/tpf").to(handlers::tpf::tpf))
         .service(
             web::resource("/commit")
                 .guard(actix_web::guard::Method(Method::POST))
+                // Larger Commits are rejected with a 413 before they are read
+                .app_data(web::PayloadConfig::new(config.opts.max_commit_size * 1024))
                 .to(handlers::commit::post_commit),
         )
+        .service(
+            web::resource("/session")
+                .route(web::post().to(handlers::session::post_session))
+                .route(web::delete().to(handlers::session::delete_session)),
+        )
+        .service(
+            web::resource("/revoke-token")
+                .guard(actix_web::guard::Method(Method::POST))
+                .to(handlers::tokens::post_revoke_token),
+        )
         .service(
             web::resource("/search")
                 .guard(actix_web::guard::Method(Method::GET))
                 .to(handlers::search::search_query),
+        )
+        .service(
+            web::resource("/context")
+                .guard(actix_web::guard::Method(Method::GET))
+                .to(handlers::context::json_ld_context),
         );
     if config.opts.rdf_search {
         tracing::info!("RDF search enabled. You can POST to /search to index RDF documents.");
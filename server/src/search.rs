@@ -1,16 +1,29 @@
 //! Full-text search, powered by Tantivy.
 //! A folder for the index is stored in the config.
 //! You can see the Endpoint on `http://localhost/search`
+//! Values are processed by a configurable analyzer (see [SearchSettings]), which handles stemming, stopwords and n-grams.
+//! The settings used for building the index are stored next to it, so changing them triggers a rebuild.
+//! Drives can set their own [urls::SEARCH_LANGUAGE]. Values of Resources in these Drives are also indexed in a field that is analyzed for that language.
 
+use std::collections::{HashMap, HashSet};
+
+use atomic_lib::urls;
 use atomic_lib::Db;
 use atomic_lib::Resource;
 use atomic_lib::Storelike;
+use clap::ArgEnum;
+use serde::{Deserialize, Serialize};
+use tantivy::query::{BooleanQuery, FuzzyTermQuery, Occur, Query, TermQuery};
 use tantivy::schema::*;
+use tantivy::tokenizer::{
+    Language, LowerCaser, NgramTokenizer, RemoveLongFilter, SimpleTokenizer, Stemmer,
+    StopWordFilter, TextAnalyzer,
+};
 use tantivy::Index;
 use tantivy::IndexWriter;
 use tantivy::ReloadPolicy;
 
-use crate::config::Config;
+use crate::config::{Config, SearchLanguage};
 use crate::errors::AtomicServerResult;
 
 /// The actual Schema used for search.
@@ -19,6 +32,150 @@ pub struct Fields {
     pub subject: Field,
     pub property: Field,
     pub value: Field,
+    /// Same content as `value`, but split up in n-grams. Only filled if `--search-ngram` is enabled.
+    pub value_ngram: Field,
+    /// Same content as `value`, analyzed for the [urls::SEARCH_LANGUAGE] of the Drive.
+    /// Only filled if the Drive uses a different language than `--search-language`.
+    pub value_languages: HashMap<SearchLanguage, Field>,
+}
+
+/// Name of the tokenizer used for `value` fields
+const VALUE_TOKENIZER: &str = "atomic_value";
+/// Name of the tokenizer used for `value_ngram` fields
+const NGRAM_TOKENIZER: &str = "atomic_ngram";
+/// Used as the `property` of documents that contain the extracted text of an uploaded File.
+/// This is not an Atomic Property - it only exists in the search index.
+pub const FILE_CONTENTS: &str = "file-contents";
+/// File in the `search_index_path` that stores the [SearchSettings] that were used to build the index
+const SETTINGS_FILE: &str = "settings.json";
+/// Increase this when the fields in [build_schema] change, so existing indexes are rebuilt.
+const SCHEMA_VERSION: u32 = 1;
+
+/// Options that determine how values are analyzed when they are indexed and queried.
+/// If these change, the existing index is no longer valid and has to be rebuilt.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SearchSettings {
+    /// Used for Resources that are not in a Drive with a [urls::SEARCH_LANGUAGE]
+    pub language: SearchLanguage,
+    pub stopwords: bool,
+    pub ngram: bool,
+    pub schema_version: u32,
+}
+
+impl SearchSettings {
+    pub fn from_config(config: &Config) -> SearchSettings {
+        SearchSettings {
+            language: config.opts.search_language,
+            stopwords: config.opts.search_stopwords,
+            ngram: config.opts.search_ngram,
+            schema_version: SCHEMA_VERSION,
+        }
+    }
+
+    /// Builds the analyzer that is used for indexing and querying values in a language.
+    /// Stopwords are only removed for English.
+    fn value_analyzer(&self, language: SearchLanguage) -> TextAnalyzer {
+        let analyzer = TextAnalyzer::from(SimpleTokenizer)
+            .filter(RemoveLongFilter::limit(40))
+            .filter(LowerCaser);
+        let analyzer = if self.stopwords && language == SearchLanguage::English {
+            analyzer.filter(StopWordFilter::default())
+        } else {
+            analyzer
+        };
+        match to_tantivy_language(language) {
+            Some(language) => analyzer.filter(Stemmer::new(language)),
+            None => analyzer,
+        }
+    }
+
+    /// Builds the analyzer that splits values in n-grams, for partial word matches.
+    fn ngram_analyzer(&self) -> TextAnalyzer {
+        TextAnalyzer::from(NgramTokenizer::new(2, 4, false)).filter(LowerCaser)
+    }
+}
+
+fn to_tantivy_language(language: SearchLanguage) -> Option<Language> {
+    let lang = match language {
+        SearchLanguage::None => return None,
+        SearchLanguage::Arabic => Language::Arabic,
+        SearchLanguage::Danish => Language::Danish,
+        SearchLanguage::Dutch => Language::Dutch,
+        SearchLanguage::English => Language::English,
+        SearchLanguage::Finnish => Language::Finnish,
+        SearchLanguage::French => Language::French,
+        SearchLanguage::German => Language::German,
+        SearchLanguage::Greek => Language::Greek,
+        SearchLanguage::Hungarian => Language::Hungarian,
+        SearchLanguage::Italian => Language::Italian,
+        SearchLanguage::Norwegian => Language::Norwegian,
+        SearchLanguage::Portuguese => Language::Portuguese,
+        SearchLanguage::Romanian => Language::Romanian,
+        SearchLanguage::Russian => Language::Russian,
+        SearchLanguage::Spanish => Language::Spanish,
+        SearchLanguage::Swedish => Language::Swedish,
+        SearchLanguage::Tamil => Language::Tamil,
+        SearchLanguage::Turkish => Language::Turkish,
+    };
+    Some(lang)
+}
+
+/// Parses the value of a [urls::SEARCH_LANGUAGE], such as `english`.
+pub fn parse_language(name: &str) -> Option<SearchLanguage> {
+    SearchLanguage::from_str(name, true).ok()
+}
+
+/// Name of the field that contains the values analyzed for a language
+fn language_field_name(language: SearchLanguage) -> String {
+    format!("value_{:?}", language).to_lowercase()
+}
+
+/// Name of the tokenizer used for the field of a language
+fn language_tokenizer_name(language: SearchLanguage) -> String {
+    format!("{}_{:?}", VALUE_TOKENIZER, language).to_lowercase()
+}
+
+/// Returns the [urls::SEARCH_LANGUAGE] of a Drive, if it has a valid one.
+fn drive_language(drive: &str, store: &impl Storelike) -> Option<SearchLanguage> {
+    let name = store
+        .get_resource_local(drive)
+        .ok()?
+        .get(urls::SEARCH_LANGUAGE)
+        .ok()?
+        .to_string();
+    parse_language(&name)
+}
+
+/// Finds the [SearchLanguage] of Resources, using the Drive they are in.
+/// Remembers the Drives of visited Resources, so indexing many Resources doesn't walk up the same hierarchy again.
+#[derive(Default)]
+pub struct LanguageResolver {
+    drives: HashMap<String, Option<String>>,
+    languages: HashMap<String, Option<SearchLanguage>>,
+}
+
+impl LanguageResolver {
+    /// Returns the language of the Drive of the Resource, or `None` if it should use the default language.
+    pub fn language_of(
+        &mut self,
+        subject: &str,
+        store: &impl Storelike,
+    ) -> AtomicServerResult<Option<SearchLanguage>> {
+        let drive = match atomic_lib::plugins::files::find_drive_cached(
+            subject,
+            store,
+            &mut self.drives,
+        )? {
+            Some(drive) => drive,
+            None => return Ok(None),
+        };
+        if let Some(language) = self.languages.get(&drive) {
+            return Ok(*language);
+        }
+        let language = drive_language(&drive, store);
+        self.languages.insert(drive, language);
+        Ok(language)
+    }
 }
 
 /// Contains the index and the schema. for search
@@ -34,13 +191,23 @@ pub struct SearchState {
     pub writer: std::sync::Arc<std::sync::RwLock<tantivy::IndexWriter>>,
     /// The shape of data stored in the index
     pub schema: tantivy::schema::Schema,
+    /// How values are analyzed
+    pub settings: SearchSettings,
+    /// True if the index was created with different [SearchSettings], and has been cleared.
+    /// All resources need to be indexed again.
+    pub needs_rebuild: bool,
+    /// Where uploaded files are stored, used for indexing their contents
+    pub uploads_path: std::path::PathBuf,
+    /// Subjects of the Drives that use another language than `--search-language`, see [value_fields].
+    pub drive_languages: std::sync::Arc<std::sync::RwLock<HashMap<String, SearchLanguage>>>,
 }
 
 impl SearchState {
     /// Create a new SearchState for the Server, which includes building the schema and index.
     pub fn new(config: &Config) -> AtomicServerResult<SearchState> {
+        let settings = SearchSettings::from_config(config);
         let schema = crate::search::build_schema()?;
-        let (writer, index) = crate::search::get_index(config)?;
+        let (writer, index, needs_rebuild) = crate::search::get_index(config, &settings)?;
         let reader = crate::search::get_reader(&index)?;
         let locked = std::sync::RwLock::from(writer);
         let arced = std::sync::Arc::from(locked);
@@ -49,8 +216,38 @@ impl SearchState {
             reader,
             index,
             writer: arced,
+            settings,
+            needs_rebuild,
+            uploads_path: config.uploads_path.clone(),
+            drive_languages: Default::default(),
         })
     }
+
+    /// Finds the [urls::SEARCH_LANGUAGE] of all Drives in the store.
+    pub fn load_drive_languages(&self, store: &impl Storelike) -> AtomicServerResult<()> {
+        let drive_class = atomic_lib::Value::AtomicUrl(urls::DRIVE.into());
+        for atom in store.tpf(None, Some(urls::IS_A), Some(&drive_class), false)? {
+            self.update_drive_language(&atom.subject, store)?;
+        }
+        Ok(())
+    }
+
+    /// Remembers the current [urls::SEARCH_LANGUAGE] of a Drive.
+    /// Forgets the Drive if it uses the default language, or if it no longer exists.
+    pub fn update_drive_language(
+        &self,
+        drive: &str,
+        store: &impl Storelike,
+    ) -> AtomicServerResult<()> {
+        let language =
+            drive_language(drive, store).filter(|language| *language != self.settings.language);
+        let mut drive_languages = self.drive_languages.write()?;
+        match language {
+            Some(language) => drive_languages.insert(drive.into(), language),
+            None => drive_languages.remove(drive),
+        };
+        Ok(())
+    }
 }
 
 /// Returns the schema for the search index.
@@ -59,18 +256,56 @@ pub fn build_schema() -> AtomicServerResult<tantivy::schema::Schema> {
     // The STORED flag makes the index store the full values. Can be useful.
     schema_builder.add_text_field("subject", TEXT | STORED);
     schema_builder.add_text_field("property", TEXT | STORED);
-    schema_builder.add_text_field("value", TEXT | STORED);
+    let value_options = TextOptions::default()
+        .set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer(VALUE_TOKENIZER)
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+        )
+        .set_stored();
+    schema_builder.add_text_field("value", value_options);
+    let ngram_options = TextOptions::default().set_indexing_options(
+        TextFieldIndexing::default()
+            .set_tokenizer(NGRAM_TOKENIZER)
+            .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+    );
+    schema_builder.add_text_field("value_ngram", ngram_options);
+    for language in SearchLanguage::value_variants() {
+        let language_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer(&language_tokenizer_name(*language))
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+        );
+        schema_builder.add_text_field(&language_field_name(*language), language_options);
+    }
     let schema = schema_builder.build();
     Ok(schema)
 }
 
 /// Creates or reads the index from the `search_index_path` and allocates some heap size.
-pub fn get_index(config: &Config) -> AtomicServerResult<(IndexWriter, Index)> {
+/// Registers the analyzers from the [SearchSettings].
+/// If the existing index was built using other settings, it is removed, and the returned boolean is `true`.
+pub fn get_index(
+    config: &Config,
+    settings: &SearchSettings,
+) -> AtomicServerResult<(IndexWriter, Index, bool)> {
     let schema = build_schema()?;
     std::fs::create_dir_all(&config.search_index_path)?;
-    if config.opts.rebuild_index {
+    let settings_path = config.search_index_path.join(SETTINGS_FILE);
+    let settings_changed = match std::fs::read_to_string(&settings_path) {
+        Ok(existing) => serde_json::from_str::<SearchSettings>(&existing)
+            .map(|existing| &existing != settings)
+            .unwrap_or(true),
+        // No settings file means no index, or an index from before analyzers were configurable
+        Err(_) => true,
+    };
+    if config.opts.rebuild_index || settings_changed {
+        if settings_changed && !config.opts.rebuild_index {
+            tracing::warn!("Search settings have changed, the search index will be rebuilt");
+        }
         std::fs::remove_dir_all(&config.search_index_path)?;
         std::fs::create_dir_all(&config.search_index_path)?;
+        std::fs::write(&settings_path, serde_json::to_string(settings)?)?;
     }
     let mmap_directory = tantivy::directory::MmapDirectory::open(&config.search_index_path)?;
     let index = Index::open_or_create(mmap_directory, schema).map_err(|e| {
@@ -79,9 +314,24 @@ pub fn get_index(config: &Config) -> AtomicServerResult<(IndexWriter, Index)> {
             e
         )
     })?;
+    if settings.stopwords && settings.language != SearchLanguage::English {
+        tracing::warn!(
+            "No stopwords available for {:?}, ignoring",
+            settings.language
+        );
+    }
+    let tokenizers = index.tokenizers();
+    tokenizers.register(VALUE_TOKENIZER, settings.value_analyzer(settings.language));
+    for language in SearchLanguage::value_variants() {
+        tokenizers.register(
+            &language_tokenizer_name(*language),
+            settings.value_analyzer(*language),
+        );
+    }
+    tokenizers.register(NGRAM_TOKENIZER, settings.ngram_analyzer());
     let heap_size_bytes = 50_000_000;
     let index_writer = index.writer(heap_size_bytes)?;
-    Ok((index_writer, index))
+    Ok((index_writer, index, settings_changed))
 }
 
 /// Returns the schema for the search index.
@@ -98,40 +348,104 @@ pub fn get_schema_fields(appstate: &SearchState) -> AtomicServerResult<Fields> {
         .schema
         .get_field("value")
         .ok_or("No 'value' in the schema")?;
+    let value_ngram = appstate
+        .schema
+        .get_field("value_ngram")
+        .ok_or("No 'value_ngram' in the schema")?;
+    let mut value_languages = HashMap::new();
+    for language in SearchLanguage::value_variants() {
+        let field = appstate
+            .schema
+            .get_field(&language_field_name(*language))
+            .ok_or_else(|| format!("No '{}' in the schema", language_field_name(*language)))?;
+        value_languages.insert(*language, field);
+    }
 
     Ok(Fields {
         subject,
         property,
         value,
+        value_ngram,
+        value_languages,
     })
 }
 
 /// Indexes all resources from the store to search.
 /// At this moment does not remove existing index.
 pub fn add_all_resources(search_state: &SearchState, store: &Db) -> AtomicServerResult<()> {
+    let mut languages = LanguageResolver::default();
     for resource in store.all_resources(true) {
         // Skip commits
         // TODO: Better check, this might overfit
         if resource.get_subject().contains("/commits/") {
             continue;
         }
-        add_resource(search_state, &resource)?;
+        add_resource(search_state, &resource, &mut languages, store)?;
+    }
+    search_state.writer.write()?.commit()?;
+    Ok(())
+}
+
+/// Indexes a Resource and all Resources below it again, and commits.
+/// Used when the [urls::SEARCH_LANGUAGE] of a Drive changes, or when Resources are moved to a Drive with another language.
+pub fn reindex_tree(
+    search_state: &SearchState,
+    subject: &str,
+    store: &Db,
+) -> AtomicServerResult<()> {
+    let mut languages = LanguageResolver::default();
+    let mut visited = HashSet::new();
+    let mut queue = vec![subject.to_string()];
+    while let Some(subject) = queue.pop() {
+        if !visited.insert(subject.clone()) {
+            continue;
+        }
+        if let Ok(resource) = store.get_resource_local(&subject) {
+            remove_resource(search_state, &subject)?;
+            add_resource(search_state, &resource, &mut languages, store)?;
+        }
+        let parent = atomic_lib::Value::AtomicUrl(subject);
+        for atom in store.tpf(None, Some(urls::PARENT), Some(&parent), false)? {
+            queue.push(atom.subject);
+        }
     }
     search_state.writer.write()?.commit()?;
     Ok(())
 }
 
 /// Adds a single resource to the search index, but does _not_ commit!
-/// Does not index outgoing links, or resourcesArrays
+/// Does not index outgoing links, or resourcesArrays.
+/// For uploaded Files, the text contents are indexed as well (see [add_file_contents]).
 /// `appstate.search_index_writer.write()?.commit()?;`
-#[tracing::instrument(skip(appstate))]
-pub fn add_resource(appstate: &SearchState, resource: &Resource) -> AtomicServerResult<()> {
+#[tracing::instrument(skip(appstate, languages, store))]
+pub fn add_resource(
+    appstate: &SearchState,
+    resource: &Resource,
+    languages: &mut LanguageResolver,
+    store: &impl Storelike,
+) -> AtomicServerResult<()> {
     let fields = get_schema_fields(appstate)?;
     let subject = resource.get_subject();
+    let language_field = languages
+        .language_of(subject, store)?
+        .filter(|language| *language != appstate.settings.language)
+        .and_then(|language| fields.value_languages.get(&language).copied());
     let writer = appstate.writer.read()?;
     for (prop, val) in resource.get_propvals() {
         match val {
             atomic_lib::Value::AtomicUrl(_) | atomic_lib::Value::ResourceArray(_) => continue,
+            // Index all translations, so Resources can be found in every language
+            atomic_lib::Value::LangString(map) => {
+                add_triple(
+                    &writer,
+                    subject.into(),
+                    prop.into(),
+                    map.values().cloned().collect::<Vec<String>>().join("\n"),
+                    &fields,
+                    language_field,
+                    appstate.settings.ngram,
+                )?;
+            }
             _ => {
                 add_triple(
                     &writer,
@@ -139,13 +453,56 @@ pub fn add_resource(appstate: &SearchState, resource: &Resource) -> AtomicServer
                     prop.into(),
                     val.to_string(),
                     &fields,
+                    language_field,
+                    appstate.settings.ngram,
                 )?;
             }
         };
     }
+    add_file_contents(appstate, &writer, &fields, language_field, resource)?;
     Ok(())
 }
 
+/// If the resource is an uploaded File with a textual mimetype, extracts its text and adds it to the index, using the [FILE_CONTENTS] property.
+/// Read rights are checked when searching, just like for other values of the File.
+fn add_file_contents(
+    search_state: &SearchState,
+    writer: &IndexWriter,
+    fields: &Fields,
+    language_field: Option<Field>,
+    resource: &Resource,
+) -> AtomicServerResult<()> {
+    let (internal_id, mimetype) = match (
+        resource.get(urls::INTERNAL_ID),
+        resource.get(urls::MIMETYPE),
+    ) {
+        (Ok(id), Ok(mime)) => (id.to_string(), mime.to_string()),
+        _ => return Ok(()),
+    };
+    let path = search_state.uploads_path.join(&internal_id);
+    match crate::text_extraction::extract_text(&path, &mimetype) {
+        Ok(Some(text)) if !text.trim().is_empty() => add_triple(
+            writer,
+            resource.get_subject().into(),
+            FILE_CONTENTS.into(),
+            text,
+            fields,
+            language_field,
+            search_state.settings.ngram,
+        ),
+        Ok(_) => Ok(()),
+        Err(e) => {
+            // A missing or unreadable file should not prevent indexing the rest
+            tracing::warn!(
+                "Could not extract text from file {}: {}",
+                resource.get_subject(),
+                e
+            );
+            Ok(())
+        }
+    }
+}
+
 // / Removes a single resource from the search index, but does _not_ commit!
 // / Does not index outgoing links, or resourcesArrays
 // / `appstate.search_index_writer.write()?.commit()?;`
@@ -159,6 +516,8 @@ pub fn remove_resource(search_state: &SearchState, subject: &str) -> AtomicServe
 }
 
 /// Adds a single atom or triple to the search index, but does _not_ commit!
+/// If `language_field` is set, the value is also indexed in that field (see [Fields::value_languages]).
+/// If `ngram` is true, the value is also indexed in the `value_ngram` field.
 /// `appstate.search_index_writer.write()?.commit()?;`
 #[tracing::instrument(skip(writer, fields))]
 pub fn add_triple(
@@ -167,15 +526,109 @@ pub fn add_triple(
     property: String,
     value: String,
     fields: &Fields,
+    language_field: Option<Field>,
+    ngram: bool,
 ) -> AtomicServerResult<()> {
     let mut doc = Document::default();
     doc.add_text(fields.property, property);
+    if let Some(field) = language_field {
+        doc.add_text(field, &value);
+    }
+    if ngram {
+        doc.add_text(fields.value_ngram, &value);
+    }
     doc.add_text(fields.value, value);
     doc.add_text(fields.subject, subject);
     writer.add_document(doc)?;
     Ok(())
 }
 
+/// Returns the fields that contain analyzed values: `value`, and the fields of the languages that Drives use.
+pub fn value_fields(search_state: &SearchState, fields: &Fields) -> AtomicServerResult<Vec<Field>> {
+    let mut value_fields = vec![fields.value];
+    for language in search_state.drive_languages.read()?.values() {
+        if let Some(field) = fields.value_languages.get(language) {
+            if !value_fields.contains(field) {
+                value_fields.push(*field);
+            }
+        }
+    }
+    Ok(value_fields)
+}
+
+/// Runs a query string through the analyzer of a field, so the tokens can be used in term queries.
+/// Returns no tokens if the analyzer removed everything (e.g. stopwords).
+pub fn analyze(
+    search_state: &SearchState,
+    field: Field,
+    text: &str,
+) -> AtomicServerResult<Vec<String>> {
+    let analyzer = search_state.index.tokenizer_for_field(field)?;
+    Ok(tokenize(&analyzer, text))
+}
+
+fn tokenize(analyzer: &TextAnalyzer, text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut stream = analyzer.token_stream(text);
+    while stream.advance() {
+        tokens.push(stream.token().text.clone());
+    }
+    tokens
+}
+
+/// Combines the queries so that every one of them has to match.
+/// Returns `None` if there are no queries.
+fn all_of(queries: Vec<Box<dyn Query>>) -> Option<Box<dyn Query>> {
+    if queries.is_empty() {
+        return None;
+    }
+    let clauses = queries.into_iter().map(|q| (Occur::Must, q)).collect();
+    Some(Box::new(BooleanQuery::new(clauses)))
+}
+
+/// Builds a query that tolerates typos and matches the start of words.
+/// All words of the query have to be present in one of the `value_fields`.
+/// If `--search-ngram` is enabled, values that contain the query as a part of a word are found too.
+pub fn fuzzy_query(
+    search_state: &SearchState,
+    fields: &Fields,
+    value_fields: &[Field],
+    q: &str,
+) -> AtomicServerResult<Box<dyn Query>> {
+    let mut alternatives: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+    for field in value_fields {
+        // The indexed values are stemmed / lowercased, so the query terms should be too
+        let words = analyze(search_state, *field, q)?
+            .into_iter()
+            .map(|word| {
+                let term = tantivy::Term::from_field_text(*field, &word);
+                Box::new(FuzzyTermQuery::new_prefix(term, 1, true)) as Box<dyn Query>
+            })
+            .collect();
+        if let Some(query) = all_of(words) {
+            alternatives.push((Occur::Should, query));
+        }
+    }
+    if search_state.settings.ngram {
+        let ngrams = analyze(search_state, fields.value_ngram, q)?
+            .into_iter()
+            .map(|ngram| {
+                let term = tantivy::Term::from_field_text(fields.value_ngram, &ngram);
+                Box::new(TermQuery::new(term, IndexRecordOption::Basic)) as Box<dyn Query>
+            })
+            .collect();
+        if let Some(query) = all_of(ngrams) {
+            alternatives.push((Occur::Should, query));
+        }
+    }
+    if alternatives.is_empty() {
+        // Only stopwords, so search for the query as it is
+        let term = tantivy::Term::from_field_text(fields.value, q);
+        return Ok(Box::new(FuzzyTermQuery::new_prefix(term, 1, true)));
+    }
+    Ok(Box::new(BooleanQuery::new(alternatives)))
+}
+
 // For a search server you will typically create one reader for the entire lifetime of your program, and acquire a new searcher for every single request.
 pub fn get_reader(index: &tantivy::Index) -> AtomicServerResult<tantivy::IndexReader> {
     Ok(index
@@ -183,3 +636,173 @@ pub fn get_reader(index: &tantivy::Index) -> AtomicServerResult<tantivy::IndexRe
         .reload_policy(ReloadPolicy::OnCommit)
         .try_into()?)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::Opts;
+    use atomic_lib::Value;
+    use clap::StructOpt;
+
+    fn test_config(ngram: bool) -> Config {
+        let unique_string = atomic_lib::utils::random_string(10);
+        let data_dir = format!("./.temp/{}/db", unique_string);
+        let mut args = vec!["atomic-server", "--data-dir", &data_dir];
+        if ngram {
+            args.push("--search-ngram");
+        }
+        let mut config = crate::config::build_config(Opts::parse_from(&args)).unwrap();
+        // This prevents folder access issues when running concurrent tests
+        config.search_index_path = format!("./.temp/{}/search_index", unique_string).into();
+        config
+    }
+
+    /// Returns the subjects of the documents that match the query
+    fn search(search_state: &SearchState, query: &dyn Query) -> Vec<String> {
+        search_state.reader.reload().unwrap();
+        let searcher = search_state.reader.searcher();
+        let fields = get_schema_fields(search_state).unwrap();
+        let mut subjects: Vec<String> = searcher
+            .search(query, &tantivy::collector::TopDocs::with_limit(10))
+            .unwrap()
+            .into_iter()
+            .map(|(_score, address)| {
+                let doc = searcher.doc(address).unwrap();
+                doc.get_first(fields.subject)
+                    .and_then(|subject| subject.as_text())
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+        subjects.sort();
+        subjects
+    }
+
+    #[test]
+    fn analyzers() {
+        let settings = SearchSettings {
+            language: SearchLanguage::English,
+            stopwords: true,
+            ngram: false,
+            schema_version: SCHEMA_VERSION,
+        };
+        let english = settings.value_analyzer(SearchLanguage::English);
+        assert_eq!(tokenize(&english, "The running Dogs"), vec!["run", "dog"]);
+        // Stopwords are only removed for English
+        let dutch = settings.value_analyzer(SearchLanguage::Dutch);
+        assert_eq!(tokenize(&dutch, "de boeken"), vec!["de", "boek"]);
+        let none = settings.value_analyzer(SearchLanguage::None);
+        assert_eq!(
+            tokenize(&none, "The running Dogs"),
+            vec!["the", "running", "dogs"]
+        );
+        let ngrams = tokenize(&settings.ngram_analyzer(), "Atom");
+        assert!(ngrams.contains(&"tom".to_string()));
+        assert_eq!(parse_language("dutch"), Some(SearchLanguage::Dutch));
+        assert_eq!(parse_language("klingon"), None);
+    }
+
+    #[test]
+    fn rebuild_when_settings_change() {
+        let config = test_config(false);
+        let settings = SearchSettings::from_config(&config);
+        let (writer, _index, needs_rebuild) = get_index(&config, &settings).unwrap();
+        assert!(needs_rebuild, "A new index has to be filled");
+        // Releases the lock on the index
+        drop(writer);
+        let (writer, _index, needs_rebuild) = get_index(&config, &settings).unwrap();
+        assert!(!needs_rebuild, "Same settings should keep the index");
+        drop(writer);
+
+        let ngram = SearchSettings {
+            ngram: true,
+            ..settings
+        };
+        let (_writer, _index, needs_rebuild) = get_index(&config, &ngram).unwrap();
+        assert!(needs_rebuild, "Changed settings should rebuild the index");
+        let stored = std::fs::read_to_string(config.search_index_path.join(SETTINGS_FILE)).unwrap();
+        assert_eq!(
+            serde_json::from_str::<SearchSettings>(&stored).unwrap(),
+            ngram
+        );
+    }
+
+    #[test]
+    fn fuzzy_queries() {
+        let search_state = SearchState::new(&test_config(true)).unwrap();
+        let fields = get_schema_fields(&search_state).unwrap();
+        let store = atomic_lib::Store::init().unwrap();
+        let mut languages = LanguageResolver::default();
+        for (subject, name) in [
+            ("http://localhost/server", "Atomic Server"),
+            ("http://localhost/data", "Atomic Data"),
+        ] {
+            let mut resource = Resource::new(subject.into());
+            resource.set_propval_unsafe(urls::NAME.into(), Value::String(name.into()));
+            add_resource(&search_state, &resource, &mut languages, &store).unwrap();
+        }
+        search_state.writer.write().unwrap().commit().unwrap();
+
+        let value_fields = vec![fields.value];
+        let query = |q: &str| {
+            let query = fuzzy_query(&search_state, &fields, &value_fields, q).unwrap();
+            search(&search_state, query.as_ref())
+        };
+        let both = vec!["http://localhost/data", "http://localhost/server"];
+        assert_eq!(query("atomic"), both);
+        // All words of the query have to match
+        assert_eq!(query("atomic.server"), vec!["http://localhost/server"]);
+        // Typos are tolerated
+        assert_eq!(query("atom.sever"), vec!["http://localhost/server"]);
+        // Parts of words are found using n-grams
+        assert_eq!(query("mic"), both);
+    }
+
+    #[test]
+    fn drive_languages() {
+        let search_state = SearchState::new(&test_config(false)).unwrap();
+        let fields = get_schema_fields(&search_state).unwrap();
+        let store = atomic_lib::Store::init().unwrap();
+        let mut drive = Resource::new("http://localhost/drive".into());
+        drive.set_propval_unsafe(urls::IS_A.into(), vec![urls::DRIVE].into());
+        drive.set_propval_unsafe(urls::SEARCH_LANGUAGE.into(), Value::String("dutch".into()));
+        store.add_resource_opts(&drive, false, true, true).unwrap();
+        let mut book = Resource::new("http://localhost/drive/book".into());
+        book.set_propval_unsafe(
+            urls::PARENT.into(),
+            Value::AtomicUrl(drive.get_subject().into()),
+        );
+        book.set_propval_unsafe(urls::NAME.into(), Value::String("boeken".into()));
+        store.add_resource_opts(&book, false, true, true).unwrap();
+
+        let mut languages = LanguageResolver::default();
+        assert_eq!(
+            languages.language_of(book.get_subject(), &store).unwrap(),
+            Some(SearchLanguage::Dutch)
+        );
+        add_resource(&search_state, &book, &mut languages, &store).unwrap();
+        search_state.writer.write().unwrap().commit().unwrap();
+
+        let dutch = fields.value_languages[&SearchLanguage::Dutch];
+        assert_eq!(
+            value_fields(&search_state, &fields).unwrap(),
+            vec![fields.value],
+            "Drive languages are not loaded yet"
+        );
+        search_state.load_drive_languages(&store).unwrap();
+        assert_eq!(
+            value_fields(&search_state, &fields).unwrap(),
+            vec![fields.value, dutch]
+        );
+        // Only the field of the Drive's language reduces `boeken` to its stem
+        let stem = |field: Field| {
+            let term = tantivy::Term::from_field_text(field, "boek");
+            search(
+                &search_state,
+                &TermQuery::new(term, IndexRecordOption::Basic),
+            )
+        };
+        assert_eq!(stem(dutch), vec!["http://localhost/drive/book"]);
+        assert!(stem(fields.value).is_empty());
+    }
+}
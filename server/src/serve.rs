@@ -19,15 +19,20 @@ fn rebuild_index(appstate: &crate::appstate::AppState) -> AtomicServerResult<()>
             .expect("Failed to build value index");
         tracing::info!("Building value index finished!");
     });
+    rebuild_search_index(appstate)
+}
+
+/// Removes all documents from the search index, and adds all resources again.
+fn rebuild_search_index(appstate: &crate::appstate::AppState) -> AtomicServerResult<()> {
     tracing::info!("Removing existing search index...");
-    appstate_clone
+    appstate
         .search_state
         .writer
         .write()
         .expect("Could not get a lock on search writer")
         .delete_all_documents()?;
     tracing::info!("Building search index...");
-    crate::search::add_all_resources(&appstate_clone.search_state, &appstate.store)?;
+    crate::search::add_all_resources(&appstate.search_state, &appstate.store)?;
     tracing::info!("Search index finished!");
     Ok(())
 }
@@ -43,13 +48,17 @@ pub async fn serve(config: crate::config::Config) -> AtomicServerResult<()> {
     // Start async processes
     if config.opts.rebuild_index {
         rebuild_index(&appstate)?;
+    } else if appstate.search_state.needs_rebuild {
+        rebuild_search_index(&appstate)?;
     }
+    crate::blobs::spawn_garbage_collector(&appstate);
 
     let server = HttpServer::new(move || {
         let cors = Cors::permissive();
 
         actix_web::App::new()
             .app_data(web::Data::new(appstate.clone()))
+            .wrap(crate::rate_limit::RateLimit)
             .wrap(cors)
             .wrap(tracing_actix_web::TracingLogger::default())
             .wrap(middleware::Compress::default())
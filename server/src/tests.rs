@@ -63,8 +63,7 @@ async fn server_tests() {
     //         .expect("could not fetch drive");
 
     // Get HTML page
-    let req =
-        build_request_authenticated("/", &appstate).insert_header(("Accept", "application/html"));
+    let req = build_request_authenticated("/", &appstate).insert_header(("Accept", "text/html"));
     let resp = test::call_service(&app, req.to_request()).await;
     let is_success = resp.status().is_success();
     let body = get_body(resp);
@@ -106,6 +105,41 @@ async fn server_tests() {
         "resource should not be authorized for public"
     );
 
+    // Create a session, which lets the browser authenticate using a cookie
+    let req =
+        build_request_authenticated("/session", &appstate).method(actix_web::http::Method::POST);
+    let resp = test::call_service(&app, req.to_request()).await;
+    assert!(resp.status().is_success(), "session not created");
+    let session_cookie = resp
+        .response()
+        .cookies()
+        .find(|c| c.name() == crate::helpers::SESSION_COOKIE)
+        .expect("no session cookie")
+        .into_owned();
+    assert_eq!(session_cookie.http_only(), Some(true));
+    let cookie_header = format!("{}={}", session_cookie.name(), session_cookie.value());
+    let req = test::TestRequest::with_uri("/properties")
+        .insert_header(("Accept", "application/ad+json"))
+        .insert_header(("Cookie", cookie_header.clone()));
+    let resp = test::call_service(&app, req.to_request()).await;
+    assert_eq!(resp.status().as_u16(), 200, "session cookie not accepted");
+
+    // Log out, the cookie should no longer work
+    let req = test::TestRequest::with_uri("/session")
+        .method(actix_web::http::Method::DELETE)
+        .insert_header(("Cookie", cookie_header.clone()));
+    let resp = test::call_service(&app, req.to_request()).await;
+    assert!(resp.status().is_success(), "logout failed");
+    let req = test::TestRequest::with_uri("/properties")
+        .insert_header(("Accept", "application/ad+json"))
+        .insert_header(("Cookie", cookie_header));
+    let resp = test::call_service(&app, req.to_request()).await;
+    assert_eq!(
+        resp.status().as_u16(),
+        401,
+        "revoked session should not be accepted"
+    );
+
     // Get JSON-AD
     let req = build_request_authenticated("/properties", &appstate);
     let resp = test::call_service(&app, req.to_request()).await;
@@ -116,6 +150,41 @@ async fn server_tests() {
         "response should be json-ad"
     );
 
+    // Conditional requests using the ETag
+    let req = build_request_authenticated("/properties", &appstate);
+    let resp = test::call_service(&app, req.to_request()).await;
+    let etag = resp
+        .headers()
+        .get("etag")
+        .expect("no etag")
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert!(resp.headers().get("vary").is_some());
+    let req = build_request_authenticated("/properties", &appstate)
+        .insert_header(("If-None-Match", etag));
+    let resp = test::call_service(&app, req.to_request()).await;
+    assert_eq!(
+        resp.status().as_u16(),
+        304,
+        "unchanged resource should be 304"
+    );
+
+    // Should 406 (Not Acceptable)
+    let req = build_request_authenticated("/properties", &appstate)
+        .insert_header(("Accept", "image/png"));
+    let resp = test::call_service(&app, req.to_request()).await;
+    assert_eq!(resp.status().as_u16(), 406, "unknown type should be 406");
+
+    // The format query parameter overrides the Accept header
+    let req = build_request_authenticated("/properties?format=jsonld", &appstate);
+    let resp = test::call_service(&app, req.to_request()).await;
+    assert!(resp.status().is_success(), "format param not accepted");
+    assert!(
+        get_body(resp).as_str().contains("@context"),
+        "format param should return json-ld"
+    );
+
     // Get JSON-LD
     let req = build_request_authenticated("/properties", &appstate)
         .insert_header(("Accept", "application/ld+json"));
@@ -134,10 +203,80 @@ async fn server_tests() {
     assert!(resp.status().is_success());
     let body = get_body(resp);
     assert!(
-        body.as_str().starts_with("<htt"),
+        body.as_str().starts_with("@prefix"),
         "response should be turtle"
     );
 
+    // Get a Collection as CSV
+    let req = build_request_authenticated("/classes?include_external=true", &appstate)
+        .insert_header(("Accept", "text/csv"));
+    let resp = test::call_service(&app, req.to_request()).await;
+    assert!(resp.status().is_success());
+    let body = get_body(resp);
+    assert!(body.as_str().starts_with("@id,shortname,description"));
+    // Streaming all pages
+    let req = build_request_authenticated(
+        "/classes?include_external=true&page_size=1&all=true",
+        &appstate,
+    )
+    .insert_header(("Accept", "text/csv"));
+    let resp = test::call_service(&app, req.to_request()).await;
+    assert!(resp.status().is_success());
+    let body = test::read_body(resp).await;
+    let body = String::from_utf8(body.to_vec()).unwrap();
+    assert!(
+        body.as_str().lines().count() > 2,
+        "should contain all pages"
+    );
+    // Other resources can't be CSV
+    let req = build_request_authenticated("/", &appstate).insert_header(("Accept", "text/csv"));
+    let resp = test::call_service(&app, req.to_request()).await;
+    assert_eq!(resp.status().as_u16(), 406);
+
+    // Import CSV
+    let import_path = format!(
+        "/import?parent={}&class={}",
+        urlencoding::encode(&appstate.config.server_url),
+        urlencoding::encode(urls::CLASS)
+    );
+    let req = build_request_authenticated(&import_path, &appstate)
+        .method(actix_web::http::Method::POST)
+        .set_payload("shortname,description\r\nimported-class,Imported from CSV\r\n");
+    let resp = test::call_service(&app, req.to_request()).await;
+    assert!(resp.status().is_success());
+    let body = get_body(resp);
+    assert!(body.as_str().contains("Imported from CSV"));
+
+    // Get RDF/XML
+    let req = build_request_authenticated("/properties", &appstate)
+        .insert_header(("Accept", "application/rdf+xml"));
+    let resp = test::call_service(&app, req.to_request()).await;
+    assert!(resp.status().is_success());
+    let body = get_body(resp);
+    assert!(
+        body.as_str().contains("<rdf:RDF"),
+        "response should be rdf/xml"
+    );
+
+    // Get compacted JSON-LD, which refers to the shared context
+    let req = build_request_authenticated("/properties", &appstate).insert_header((
+        "Accept",
+        "application/ld+json;profile=\"http://www.w3.org/ns/json-ld#compacted\"",
+    ));
+    let resp = test::call_service(&app, req.to_request()).await;
+    assert!(resp.status().is_success());
+    let body = get_body(resp);
+    assert!(
+        body.as_str().contains("/context\""),
+        "response should refer to the shared context"
+    );
+    let req =
+        test::TestRequest::with_uri("/context").insert_header(("Accept", "application/ld+json"));
+    let resp = test::call_service(&app, req.to_request()).await;
+    assert!(resp.status().is_success());
+    let body = get_body(resp);
+    assert!(body.as_str().contains("\"description\""));
+
     // Get Search
     // Does not test the contents of the results - the index isn't built at this point
     let req = build_request_authenticated("/search?q=setup", &appstate);
@@ -149,6 +288,46 @@ async fn server_tests() {
         body.as_str().contains("/results"),
         "response should be a search resource"
     );
+
+    // Commits larger than `max_commit_size` are rejected
+    let req = test::TestRequest::post()
+        .uri("/commit")
+        .set_payload(" ".repeat(2 * 1024 * 1024));
+    let resp = test::call_service(&app, req.to_request()).await;
+    assert_eq!(resp.status().as_u16(), 413, "commit should be too large");
+
+    // Rate limiting
+    let mut limited_state = appstate.clone();
+    limited_state.rate_limiter = crate::rate_limit::RateLimiter::new(crate::rate_limit::Limits {
+        anonymous: 1,
+        agent: 2,
+        search: 0,
+        upload: 0,
+        write: 0,
+    });
+    let limited = test::init_service(
+        App::new()
+            .app_data(Data::new(limited_state.clone()))
+            .wrap(crate::rate_limit::RateLimit)
+            .configure(|app| crate::routes::config_routes(app, &limited_state.config)),
+    )
+    .await;
+    let anonymous_request = || {
+        test::TestRequest::with_uri("/properties").insert_header(("Accept", "application/ad+json"))
+    };
+    let resp = test::call_service(&limited, anonymous_request().to_request()).await;
+    assert!(resp.status().is_success());
+    let resp = test::call_service(&limited, anonymous_request().to_request()).await;
+    assert_eq!(
+        resp.status().as_u16(),
+        429,
+        "second request should be limited"
+    );
+    assert_eq!(resp.headers().get("retry-after").unwrap(), "60");
+    // Authenticated Agents have their own, larger budget
+    let req = build_request_authenticated("/properties", &limited_state);
+    let resp = test::call_service(&limited, req.to_request()).await;
+    assert!(resp.status().is_success());
 }
 
 /// Gets the body from the response as a String. Why doen't actix provide this?
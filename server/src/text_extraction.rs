@@ -0,0 +1,143 @@
+//! Extracts plain text from uploaded files, so their contents can be indexed in search.
+//! Only handles formats that can be parsed without external services: plain text, Markdown, HTML and JSON.
+
+use std::{io::Read, path::Path};
+
+use crate::errors::AtomicServerResult;
+
+/// Files larger than this are only partially read for indexing
+const MAX_TEXT_BYTES: u64 = 1_000_000;
+
+/// Returns the (plain) text contents of a file, if its mimetype is supported.
+/// Returns `None` for unsupported files, such as images or PDFs.
+pub fn extract_text(path: &Path, mimetype: &str) -> AtomicServerResult<Option<String>> {
+    let kind = match TextKind::from_mime(mimetype) {
+        Some(kind) => kind,
+        None => return Ok(None),
+    };
+    let mut bytes = Vec::new();
+    std::fs::File::open(path)?
+        .take(MAX_TEXT_BYTES)
+        .read_to_end(&mut bytes)?;
+    let raw = String::from_utf8_lossy(&bytes);
+    let text = match kind {
+        TextKind::Plain => raw.to_string(),
+        TextKind::Html => strip_html(&raw),
+        TextKind::Json => json_strings(&raw),
+    };
+    Ok(Some(text))
+}
+
+enum TextKind {
+    /// Plain text and Markdown, which are indexed as-is
+    Plain,
+    Html,
+    Json,
+}
+
+impl TextKind {
+    fn from_mime(mimetype: &str) -> Option<TextKind> {
+        // Remove parameters, such as `; charset=utf-8`
+        let essence = mimetype.split(';').next().unwrap_or_default().trim();
+        match essence {
+            "text/html" | "application/xhtml+xml" => Some(TextKind::Html),
+            "application/json" | "application/ld+json" | "application/ad+json" => {
+                Some(TextKind::Json)
+            }
+            "text/markdown" | "text/x-markdown" => Some(TextKind::Plain),
+            other if other.starts_with("text/") => Some(TextKind::Plain),
+            _ => None,
+        }
+    }
+}
+
+/// Removes tags, comments, scripts and styles from an HTML document, and decodes the most common entities.
+fn strip_html(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let tag = &rest[start..];
+        let lower = tag.get(..8).unwrap_or(tag).to_ascii_lowercase();
+        // Skip everything inside these elements, including their closing tag
+        let skip_until = if tag.starts_with("<!--") {
+            Some("-->")
+        } else if lower.starts_with("<script") {
+            Some("</script>")
+        } else if lower.starts_with("<style") {
+            Some("</style>")
+        } else {
+            None
+        };
+        rest = match skip_until {
+            Some(end) => match tag.to_ascii_lowercase().find(end) {
+                Some(i) => &tag[i + end.len()..],
+                None => "",
+            },
+            None => match tag.find('>') {
+                Some(i) => &tag[i + 1..],
+                None => "",
+            },
+        };
+        // Tags often separate words
+        text.push(' ');
+    }
+    text.push_str(rest);
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Collects all string values from a JSON document. Falls back to the raw text if it can't be parsed.
+fn json_strings(json: &str) -> String {
+    fn collect(value: &serde_json::Value, out: &mut Vec<String>) {
+        match value {
+            serde_json::Value::String(s) => out.push(s.clone()),
+            serde_json::Value::Array(items) => items.iter().for_each(|v| collect(v, out)),
+            serde_json::Value::Object(map) => map.values().for_each(|v| collect(v, out)),
+            _ => {}
+        }
+    }
+    match serde_json::from_str::<serde_json::Value>(json) {
+        Ok(value) => {
+            let mut out = Vec::new();
+            collect(&value, &mut out);
+            out.join("\n")
+        }
+        Err(_) => json.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn strips_html() {
+        let html = "<html><head><style>p { color: red; }</style><script>let x = 1 < 2;</script></head><body><!-- hidden --><p>Hello &amp; <b>welcome</b></p></body></html>";
+        let text = strip_html(html);
+        assert!(text.contains("Hello & "));
+        assert!(text.contains("welcome"));
+        assert!(!text.contains("color"));
+        assert!(!text.contains("let x"));
+        assert!(!text.contains("hidden"));
+    }
+
+    #[test]
+    fn collects_json_strings() {
+        let text = json_strings(r#"{"title": "Atomic", "tags": ["data", 1, {"nested": "value"}]}"#);
+        assert!(text.contains("Atomic"));
+        assert!(text.contains("data"));
+        assert!(text.contains("value"));
+        assert!(!text.contains("title"));
+    }
+
+    #[test]
+    fn unsupported_mimetypes() {
+        assert!(TextKind::from_mime("image/png").is_none());
+        assert!(TextKind::from_mime("text/markdown; charset=utf-8").is_some());
+    }
+}